@@ -2,12 +2,32 @@
 
 use std::path::Path;
 
-use muxux_core::command::Command;
+use muxux_core::command::{Command, Request};
 use cmx_utils::response::Response;
 
 
-/// Send a command to the MuxUX daemon via Unix socket.
+/// Send a command to the MuxUX daemon via Unix socket, along with the
+/// project it runs in.
 pub fn send_command(config_dir: &Path, cmd: &Command, timeout_ms: u64) -> Result<Response, String> {
     let sock_path = config_dir.join("mux.sock");
-    cmx_utils::client::send_and_receive(&sock_path, cmd, timeout_ms)
+    let request = Request {
+        command: cmd.clone(),
+        project_root: Some(project_root()),
+    };
+    cmx_utils::client::send_and_receive(&sock_path, &request, timeout_ms)
+}
+
+
+/// The project this client works in: `$MUX_PROJECT_ROOT`, or the current
+/// directory.
+pub fn project_root() -> String {
+    std::env::var("MUX_PROJECT_ROOT")
+        .ok()
+        .filter(|root| !root.is_empty())
+        .or_else(|| {
+            std::env::current_dir()
+                .ok()
+                .map(|dir| dir.to_string_lossy().into())
+        })
+        .unwrap_or_default()
 }
@@ -1,17 +1,53 @@
 //! MuxUX CLI — the command-line entry point for the Structure App.
+//!
+//! Commands are sent to a running daemon over `mux.sock` in the config
+//! directory. When no socket exists the command is executed in-process.
+//! `--daemon` / `--local` force one path or the other. `mux watch` always
+//! talks to the daemon.
 
-use std::path::PathBuf;
+mod client;
+mod watch;
+
+use std::path::{Path, PathBuf};
 use std::process;
 
 use muxux_core::command::Command;
+use muxux_core::infrastructure::executing::ExecutingTmuxBackend;
+use muxux_core::infrastructure::runner::ShellRunner;
+use muxux_core::sys::Sys;
+use muxux_core::types::config::MuxSettings;
 use cmx_utils::response::Response;
 
 
+/// Default time to wait for a daemon response, in milliseconds.
+const DAEMON_TIMEOUT_MS: u64 = 10_000;
+
+
+/// Where a command is executed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ExecMode {
+    /// Use the daemon if its socket exists, otherwise run locally.
+    Auto,
+    /// Always run in-process, ignoring any daemon.
+    Local,
+    /// Always send to the daemon; fail if it is not reachable.
+    Daemon,
+}
+
+
 fn main() {
     let args: Vec<String> = std::env::args().collect();
     let arg_refs: Vec<&str> = args[1..].iter().map(|s| s.as_str()).collect();
 
-    let cmd = match parse_args(&arg_refs) {
+    let (mode, rest) = match split_mode_flags(&arg_refs) {
+        Ok(split) => split,
+        Err(e) => {
+            eprintln!("mux: {}", e);
+            process::exit(1);
+        }
+    };
+
+    let cmd = match parse_args(&rest).and_then(with_absolute_paths) {
         Ok(c) => c,
         Err(e) => {
             eprintln!("mux: {}", e);
@@ -19,13 +55,28 @@ fn main() {
         }
     };
 
-    let _config_dir = resolve_config_dir();
+    let config_dir = resolve_config_dir();
+
+    if cmd == Command::DaemonRun {
+        run_daemon(&config_dir);
+        return;
+    }
+
+    if let Command::Watch { .. } = cmd {
+        if let Err(e) = run_watch(mode, &config_dir, &rest[1..], &cmd) {
+            eprintln!("mux: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
 
-    // For now, execute locally (daemon mode can be added later)
-    let mut sys = muxux_core::sys::Sys::new(
-        std::env::var("MUX_PROJECT_ROOT").unwrap_or_default(),
-    );
-    let response = sys.execute(cmd);
+    let response = match dispatch(mode, &config_dir, cmd) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("mux: {}", e);
+            process::exit(1);
+        }
+    };
 
     match response {
         Response::Ok { output } => {
@@ -41,6 +92,158 @@ fn main() {
 }
 
 
+/// Run the daemon in the foreground; exits non-zero if it fails to start.
+/// Requests from clients bring their own project root; this one is the
+/// fallback.
+fn run_daemon(config_dir: &Path) {
+    let result = new_sys(config_dir, std::env::var("MUX_PROJECT_ROOT").unwrap_or_default())
+        .and_then(|mut sys| muxux_core::service::run_daemon(config_dir, &mut sys, ShellRunner));
+    if let Err(e) = result {
+        eprintln!("mux daemon: {}", e);
+        process::exit(1);
+    }
+}
+
+
+/// A `Sys` with the settings in `config_dir`, working in `project_root`.
+fn new_sys(config_dir: &Path, project_root: String) -> Result<Sys, String> {
+    let settings = MuxSettings {
+        project_root,
+        config_dir: config_dir.to_string_lossy().into(),
+        ..MuxSettings::load(config_dir)?
+    };
+    Ok(Sys::with_settings(settings))
+}
+
+
+/// Stream daemon events until interrupted.
+fn run_watch(mode: ExecMode, config_dir: &Path, args: &[&str], cmd: &Command) -> Result<(), String> {
+    if mode == ExecMode::Local || !config_dir.join("mux.sock").exists() {
+        return Err("mux watch needs a running daemon (start one with 'mux daemon run')".into());
+    }
+    let options = watch::WatchOptions::parse(args)?;
+    watch::run(config_dir, &options, response_timeout_ms(cmd))
+}
+
+
+/// Route a command to the daemon or to an in-process `Sys`.
+fn dispatch(mode: ExecMode, config_dir: &Path, cmd: Command) -> Result<Response, String> {
+    let use_daemon = match mode {
+        ExecMode::Local => false,
+        ExecMode::Daemon => true,
+        ExecMode::Auto => config_dir.join("mux.sock").exists(),
+    };
+    if cmd == Command::DaemonStop && !use_daemon {
+        return Err("no daemon is running".into());
+    }
+    if use_daemon {
+        let timeout_ms = response_timeout_ms(&cmd);
+        client::send_command(config_dir, &cmd, timeout_ms).map_err(|e| {
+            format!(
+                "cannot reach daemon at {}: {} (use --local to run without it)",
+                config_dir.join("mux.sock").display(),
+                e
+            )
+        })
+    } else {
+        Ok(run_local(config_dir, cmd))
+    }
+}
+
+
+/// Execute a command in-process with a throwaway `Sys`, running the tmux
+/// actions it queues.
+fn run_local(config_dir: &Path, cmd: Command) -> Response {
+    let mut sys = match new_sys(config_dir, client::project_root()) {
+        Ok(sys) => sys,
+        Err(message) => return Response::Error { message },
+    };
+    let mut backend = ExecutingTmuxBackend::new(ShellRunner);
+    muxux_core::service::execute_command(&mut sys, &mut backend, cmd)
+}
+
+
+/// How long to wait for the daemon to answer a command. Watch requests are
+/// held open by the daemon, so they get their own timeout plus a margin.
+fn response_timeout_ms(cmd: &Command) -> u64 {
+    match cmd {
+        Command::Watch { timeout, .. } => {
+            let watch_ms = timeout
+                .as_deref()
+                .and_then(|t| t.parse::<u64>().ok())
+                .unwrap_or(30_000);
+            watch_ms + DAEMON_TIMEOUT_MS
+        }
+        _ => DAEMON_TIMEOUT_MS,
+    }
+}
+
+
+/// Strip the global `--local` / `--daemon` switches from the argument list.
+fn split_mode_flags<'a>(args: &[&'a str]) -> Result<(ExecMode, Vec<&'a str>), String> {
+    let mut mode = ExecMode::Auto;
+    let mut rest = Vec::new();
+    for arg in args {
+        let flag_mode = match *arg {
+            "--local" => ExecMode::Local,
+            "--daemon" => ExecMode::Daemon,
+            _ => {
+                rest.push(*arg);
+                continue;
+            }
+        };
+        if mode != ExecMode::Auto && mode != flag_mode {
+            return Err("--local and --daemon cannot be combined".into());
+        }
+        mode = flag_mode;
+    }
+    Ok((mode, rest))
+}
+
+
+/// Resolve file arguments against the current directory, since the daemon
+/// that opens them runs somewhere else.
+fn with_absolute_paths(cmd: Command) -> Result<Command, String> {
+    Ok(match cmd {
+        Command::PartsCheck { file } => Command::PartsCheck {
+            file: absolute(file)?,
+        },
+        Command::PartsAdd { name, body, file } => Command::PartsAdd {
+            name,
+            body,
+            file: absolute(file)?,
+        },
+        Command::PartsUpdate { name, body, file } => Command::PartsUpdate {
+            name,
+            body,
+            file: absolute(file)?,
+        },
+        Command::PartsRemove { name, file } => Command::PartsRemove {
+            name,
+            file: absolute(file)?,
+        },
+        Command::PartsRename { name, to, file } => Command::PartsRename {
+            name,
+            to,
+            file: absolute(file)?,
+        },
+        cmd => cmd,
+    })
+}
+
+
+fn absolute(path: Option<String>) -> Result<Option<String>, String> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+    if Path::new(&path).is_absolute() {
+        return Ok(Some(path));
+    }
+    let cwd = std::env::current_dir().map_err(|e| format!("cannot resolve '{}': {}", path, e))?;
+    Ok(Some(cwd.join(path).to_string_lossy().into()))
+}
+
+
 fn resolve_config_dir() -> PathBuf {
     if let Ok(dir) = std::env::var("MUX_CONFIG_DIR") {
         return PathBuf::from(dir);
@@ -57,15 +260,16 @@ fn parse_args(args: &[&str]) -> Result<Command, String> {
 
     match args[0] {
         "status" => Ok(Command::Status {
-            format: args.get(1).and_then(|a| {
-                if *a == "--json" { Some("json".into()) } else { None }
-            }),
+            format: args[1..].contains(&"--json").then(|| "json".into()),
         }),
         "view" => {
             if args.len() < 2 {
-                return Err("Usage: mux view <name>".into());
+                return Err("Usage: mux view <name> [--diagram]".into());
             }
-            Ok(Command::View { name: args[1].into() })
+            Ok(Command::View {
+                name: args[1].into(),
+                diagram: args[2..].contains(&"--diagram"),
+            })
         }
         "help" => Ok(Command::Help {
             topic: args.get(1).map(|s| s.to_string()),
@@ -75,10 +279,14 @@ fn parse_args(args: &[&str]) -> Result<Command, String> {
         "daemon" => parse_daemon(args),
         "studio" => parse_studio(args),
         "setup" => parse_setup(args),
-        "watch" => Ok(Command::Watch {
-            since: None,
-            timeout: None,
-        }),
+        "parts" => parse_parts(args),
+        "watch" => {
+            let options = watch::WatchOptions::parse(&args[1..])?;
+            Ok(Command::Watch {
+                since: options.since.map(|s| s.to_string()),
+                timeout: options.timeout.map(|t| t.to_string()),
+            })
+        }
         _ => Err(format!("Unknown command: '{}'. Run 'mux help' for usage.", args[0])),
     }
 }
@@ -111,10 +319,21 @@ fn parse_layout(args: &[&str]) -> Result<Command, String> {
         }
         "merge" => {
             if args.len() < 3 {
-                return Err("Usage: mux layout merge <session>".into());
+                return Err("Usage: mux layout merge <session> [--keep <pane>] [--break]".into());
             }
             Ok(Command::LayoutMerge {
                 session: args[2].into(),
+                keep: find_flag(args, "--keep"),
+                break_out: args.contains(&"--break"),
+            })
+        }
+        "apply" => {
+            if args.len() < 4 {
+                return Err("Usage: mux layout apply <session> <part|layout>".into());
+            }
+            Ok(Command::LayoutApply {
+                session: args[2].into(),
+                layout: args[3..].join(" "),
             })
         }
         "place" => {
@@ -134,6 +353,28 @@ fn parse_layout(args: &[&str]) -> Result<Command, String> {
                 session: args[2].into(),
             })
         }
+        "restore" => {
+            if args.len() < 3 {
+                return Err(
+                    "Usage: mux layout restore <session> --part <name> | --file <snapshot.json> | --at <time|index>"
+                        .into(),
+                );
+            }
+            Ok(Command::LayoutRestore {
+                session: args[2].into(),
+                part: find_flag(args, "--part"),
+                file: find_flag(args, "--file"),
+                at: find_flag(args, "--at"),
+            })
+        }
+        "history" => {
+            if args.len() < 3 {
+                return Err("Usage: mux layout history <session>".into());
+            }
+            Ok(Command::LayoutHistory {
+                session: args[2].into(),
+            })
+        }
         "session" => {
             if args.len() < 3 {
                 return Err("Usage: mux layout session <name> [--cwd <path>]".into());
@@ -200,6 +441,122 @@ fn parse_setup(args: &[&str]) -> Result<Command, String> {
 }
 
 
+fn parse_parts(args: &[&str]) -> Result<Command, String> {
+    if args.len() < 2 {
+        return Err("Usage: mux parts <list|show|check|add|update|rm|mv> ...".into());
+    }
+    match args[1] {
+        "list" => Ok(Command::PartsList),
+        "show" => {
+            if args.len() < 3 {
+                return Err(
+                    "Usage: mux parts show <name> [--width <n>] [--height <n>] [--ascii]".into(),
+                );
+            }
+            Ok(Command::PartsShow {
+                name: args[2].into(),
+                width: size_flag(args, "--width")?,
+                height: size_flag(args, "--height")?,
+                ascii: args[3..].contains(&"--ascii"),
+            })
+        }
+        // A bare path after `check` is still read as --file, for old scripts.
+        "check" => Ok(Command::PartsCheck {
+            file: find_flag(args, "--file")
+                .or_else(|| args.get(2).filter(|a| !a.starts_with("--")).map(|s| s.to_string())),
+        }),
+        "add" | "update" => {
+            if args.len() < 4 {
+                return Err(format!(
+                    "Usage: mux parts {} <name> (<layout expr> | --role <r> [--type <t>] \
+                     [--host <h>]) [--file <path>]",
+                    args[1]
+                ));
+            }
+            let (body, file) = parse_part_body(&args[3..])?;
+            let name = args[2].to_string();
+            Ok(if args[1] == "add" {
+                Command::PartsAdd { name, body, file }
+            } else {
+                Command::PartsUpdate { name, body, file }
+            })
+        }
+        "rm" => {
+            if args.len() < 3 {
+                return Err("Usage: mux parts rm <name> [--file <path>]".into());
+            }
+            Ok(Command::PartsRemove {
+                name: args[2].into(),
+                file: find_flag(args, "--file"),
+            })
+        }
+        "mv" => {
+            if args.len() < 4 {
+                return Err("Usage: mux parts mv <old> <new> [--file <path>]".into());
+            }
+            Ok(Command::PartsRename {
+                name: args[2].into(),
+                to: args[3].into(),
+                file: find_flag(args, "--file"),
+            })
+        }
+        _ => Err(format!("Unknown parts subcommand: '{}'", args[1])),
+    }
+}
+
+
+/// The body of a part from `parts add`/`update` arguments — agent fields
+/// from `--role`, `--type`, and `--host`, or the remaining words as a layout
+/// expression — and the `--file` flag.
+fn parse_part_body(args: &[&str]) -> Result<(String, Option<String>), String> {
+    let mut fields = Vec::new();
+    let mut words = Vec::new();
+    let mut file = None;
+    let mut i = 0;
+    while i < args.len() {
+        let key = match args[i] {
+            "--role" => "role",
+            "--type" => "type",
+            "--host" => "host",
+            "--file" => "file",
+            word => {
+                words.push(word);
+                i += 1;
+                continue;
+            }
+        };
+        let value = args
+            .get(i + 1)
+            .ok_or_else(|| format!("{} needs a value", args[i]))?;
+        if key == "file" {
+            file = Some(value.to_string());
+        } else {
+            fields.push(format!("{}: {}", key, value));
+        }
+        i += 2;
+    }
+    match (fields.is_empty(), words.is_empty()) {
+        (true, false) => Ok((words.join(" "), file)),
+        (false, true) if fields.iter().any(|f| f.starts_with("role:")) => Ok((fields.join("\n"), file)),
+        (false, true) => Err("An agent part needs --role".into()),
+        (true, true) => Err("Give a layout expression or --role".into()),
+        (false, false) => Err("Give a layout expression or agent flags, not both".into()),
+    }
+}
+
+
+/// A positive diagram size given with `flag`, if present.
+fn size_flag(args: &[&str], flag: &str) -> Result<Option<u16>, String> {
+    let Some(value) = find_flag(args, flag) else {
+        return Ok(None);
+    };
+    match value.parse::<u16>() {
+        Ok(size) if size > 0 => Ok(Some(size)),
+        _ => Err(format!("{} expects a positive number, got '{}'", flag, value)),
+    }
+}
+
+
 fn find_flag(args: &[&str], flag: &str) -> Option<String> {
     for (i, arg) in args.iter().enumerate() {
         if *arg == flag {
@@ -265,4 +622,222 @@ mod tests {
         let args = vec!["setup", "foo"];
         assert!(parse_args(&args).is_err());
     }
+
+    #[test]
+    fn parse_parts_show_flags() {
+        let args = vec!["parts", "show", "pair", "--width", "60", "--ascii"];
+        let cmd = parse_args(&args).unwrap();
+        assert_eq!(
+            cmd,
+            Command::PartsShow {
+                name: "pair".into(),
+                width: Some(60),
+                height: None,
+                ascii: true,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_parts_show_rejects_bad_sizes() {
+        assert!(parse_args(&["parts", "show", "pair", "--width", "wide"]).is_err());
+        assert!(parse_args(&["parts", "show", "pair", "--height", "0"]).is_err());
+        assert!(parse_args(&["parts", "show", "pair", "--width", "70000"]).is_err());
+    }
+
+    #[test]
+    fn parse_parts_check_file() {
+        let cmd = parse_args(&["parts", "check", "--file", "my.md"]).unwrap();
+        assert_eq!(cmd, Command::PartsCheck { file: Some("my.md".into()) });
+        let cmd = parse_args(&["parts", "check", "my.md"]).unwrap();
+        assert_eq!(cmd, Command::PartsCheck { file: Some("my.md".into()) });
+        let cmd = parse_args(&["parts", "check"]).unwrap();
+        assert_eq!(cmd, Command::PartsCheck { file: None });
+    }
+
+    #[test]
+    fn file_arguments_are_made_absolute() {
+        let cwd = std::env::current_dir().unwrap();
+        let args = ["parts", "check", "--file", "my.md"];
+        let cmd = with_absolute_paths(parse_args(&args).unwrap()).unwrap();
+        let expected = cwd.join("my.md").to_string_lossy().to_string();
+        assert_eq!(cmd, Command::PartsCheck { file: Some(expected) });
+        let cmd = with_absolute_paths(parse_args(&["parts", "check", "/etc/p.md"]).unwrap()).unwrap();
+        assert_eq!(cmd, Command::PartsCheck { file: Some("/etc/p.md".into()) });
+        let args = ["parts", "mv", "a", "b", "--file", "p.md"];
+        let cmd = with_absolute_paths(parse_args(&args).unwrap()).unwrap();
+        let expected = cwd.join("p.md").to_string_lossy().to_string();
+        assert_eq!(
+            cmd,
+            Command::PartsRename {
+                name: "a".into(),
+                to: "b".into(),
+                file: Some(expected),
+            }
+        );
+        let cmd = with_absolute_paths(parse_args(&["parts", "rm", "a"]).unwrap()).unwrap();
+        assert_eq!(cmd, Command::PartsRemove { name: "a".into(), file: None });
+    }
+
+    #[test]
+    fn parse_parts_add_and_update() {
+        let args = ["parts", "add", "pair", "ROW(pm,", "worker)", "--file", "p.md"];
+        assert_eq!(
+            parse_args(&args).unwrap(),
+            Command::PartsAdd {
+                name: "pair".into(),
+                body: "ROW(pm, worker)".into(),
+                file: Some("p.md".into()),
+            }
+        );
+        let args = ["parts", "update", "pm", "--role", "pm", "--type", "claude"];
+        assert_eq!(
+            parse_args(&args).unwrap(),
+            Command::PartsUpdate {
+                name: "pm".into(),
+                body: "role: pm\ntype: claude".into(),
+                file: None,
+            }
+        );
+        assert!(parse_args(&["parts", "add", "pm", "--type", "claude"]).is_err());
+        assert!(parse_args(&["parts", "add", "pm", "ROW(a)", "--role", "pm"]).is_err());
+        assert!(parse_args(&["parts", "add", "pm"]).is_err());
+    }
+
+    #[test]
+    fn parse_parts_rm_and_mv() {
+        let cmd = parse_args(&["parts", "mv", "worker", "coder"]).unwrap();
+        assert_eq!(
+            cmd,
+            Command::PartsRename {
+                name: "worker".into(),
+                to: "coder".into(),
+                file: None,
+            }
+        );
+        let cmd = parse_args(&["parts", "rm", "pm", "--file", "p.md"]).unwrap();
+        assert_eq!(
+            cmd,
+            Command::PartsRemove {
+                name: "pm".into(),
+                file: Some("p.md".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_layout_merge_flags() {
+        let args = vec!["layout", "merge", "work", "--keep", "%2", "--break"];
+        let cmd = parse_args(&args).unwrap();
+        assert_eq!(
+            cmd,
+            Command::LayoutMerge {
+                session: "work".into(),
+                keep: Some("%2".into()),
+                break_out: true,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_layout_restore_at() {
+        let args = vec!["layout", "restore", "work", "--at", "-2"];
+        match parse_args(&args).unwrap() {
+            Command::LayoutRestore { at, part, .. } => {
+                assert_eq!(at.as_deref(), Some("-2"));
+                assert!(part.is_none());
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_layout_restore_part() {
+        let args = vec!["layout", "restore", "work", "--part", "dev-station"];
+        assert_eq!(
+            parse_args(&args).unwrap(),
+            Command::LayoutRestore {
+                session: "work".into(),
+                part: Some("dev-station".into()),
+                file: None,
+                at: None,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_layout_apply_joins_expression() {
+        let args = vec!["layout", "apply", "work", "ROW(a,", "b)"];
+        let cmd = parse_args(&args).unwrap();
+        assert_eq!(
+            cmd,
+            Command::LayoutApply {
+                session: "work".into(),
+                layout: "ROW(a, b)".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn mode_defaults_to_auto() {
+        let (mode, rest) = split_mode_flags(&["status", "--json"]).unwrap();
+        assert_eq!(mode, ExecMode::Auto);
+        assert_eq!(rest, vec!["status", "--json"]);
+    }
+
+    #[test]
+    fn mode_flags_are_stripped() {
+        let (mode, rest) = split_mode_flags(&["--local", "layout", "row", "main"]).unwrap();
+        assert_eq!(mode, ExecMode::Local);
+        assert_eq!(rest, vec!["layout", "row", "main"]);
+
+        let (mode, rest) = split_mode_flags(&["status", "--daemon"]).unwrap();
+        assert_eq!(mode, ExecMode::Daemon);
+        assert_eq!(rest, vec!["status"]);
+    }
+
+    #[test]
+    fn mode_flags_conflict() {
+        assert!(split_mode_flags(&["--local", "--daemon", "status"]).is_err());
+    }
+
+    #[test]
+    fn auto_mode_without_socket_runs_locally() {
+        let dir = std::env::temp_dir().join(format!("mux-cli-test-{}", std::process::id()));
+        let resp = dispatch(ExecMode::Auto, &dir, Command::Help { topic: None }).unwrap();
+        assert!(matches!(resp, Response::Ok { .. }));
+    }
+
+    #[test]
+    fn daemon_mode_without_socket_errors() {
+        let dir = std::env::temp_dir().join(format!("mux-cli-test-nosock-{}", std::process::id()));
+        let err = dispatch(ExecMode::Daemon, &dir, Command::Help { topic: None }).unwrap_err();
+        assert!(err.contains("--local"));
+    }
+
+    #[test]
+    fn daemon_stop_without_daemon_errors() {
+        let dir = std::env::temp_dir().join(format!("mux-cli-test-stop-{}", std::process::id()));
+        assert!(dispatch(ExecMode::Auto, &dir, Command::DaemonStop).is_err());
+    }
+
+    #[test]
+    fn parse_watch_flags() {
+        let args = vec!["watch", "--since", "7", "--timeout", "1000", "--json"];
+        assert_eq!(
+            parse_args(&args).unwrap(),
+            Command::Watch {
+                since: Some("7".into()),
+                timeout: Some("1000".into()),
+            }
+        );
+        assert!(parse_args(&["watch", "--since"]).is_err());
+    }
+
+    #[test]
+    fn watch_timeout_extends_response_timeout() {
+        let cmd = Command::Watch { since: None, timeout: Some("5000".into()) };
+        assert_eq!(response_timeout_ms(&cmd), 5000 + DAEMON_TIMEOUT_MS);
+        assert_eq!(response_timeout_ms(&Command::SessionList), DAEMON_TIMEOUT_MS);
+    }
 }
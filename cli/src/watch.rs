@@ -0,0 +1,298 @@
+//! `mux watch` — stream daemon events to stdout.
+//!
+//! Long-polls the daemon with `Watch` requests, resuming each poll from the
+//! last sequence number seen so no event is lost between polls. Events are
+//! filtered on the client by session, type, and agent, and printed one per
+//! line, either human-readable or as NDJSON.
+
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use cmx_utils::response::Response;
+use muxux_core::command::Command;
+use muxux_core::event::{EventRecord, MuxEvent};
+
+use crate::client;
+
+
+/// Flags accepted by `mux watch`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WatchOptions {
+    /// Replay events after this sequence number before streaming.
+    pub since: Option<u64>,
+    /// How long the daemon holds each poll open, in ms.
+    pub timeout: Option<u64>,
+    pub session: Option<String>,
+    /// Event types to keep (`layout_changed`, ...); empty keeps all.
+    pub types: Vec<String>,
+    pub agent: Option<String>,
+    /// Print NDJSON instead of one human-readable line per event.
+    pub json: bool,
+}
+
+
+impl WatchOptions {
+    /// Parse the arguments after `watch`.
+    pub fn parse(args: &[&str]) -> Result<WatchOptions, String> {
+        let mut options = WatchOptions::default();
+        let mut i = 0;
+        while i < args.len() {
+            let value = || {
+                args.get(i + 1)
+                    .map(|v| v.to_string())
+                    .ok_or_else(|| format!("{} needs a value", args[i]))
+            };
+            match args[i] {
+                "--since" => options.since = Some(number(&value()?, "--since")?),
+                "--timeout" => options.timeout = Some(number(&value()?, "--timeout")?),
+                "--session" => options.session = Some(value()?),
+                "--agent" => options.agent = Some(value()?),
+                "--type" => options
+                    .types
+                    .extend(value()?.split(',').map(|t| t.trim().to_string())),
+                "--json" => {
+                    options.json = true;
+                    i += 1;
+                    continue;
+                }
+                other => return Err(format!("Unknown watch flag: '{}'", other)),
+            }
+            i += 2;
+        }
+        Ok(options)
+    }
+
+    /// Whether `record` passes the session, type, and agent filters.
+    pub fn matches(&self, record: &EventRecord) -> bool {
+        if !self.types.is_empty() && !self.types.iter().any(|t| t == record.event.kind()) {
+            return false;
+        }
+        if let Some(session) = &self.session {
+            if event_field(&record.event, "session").as_deref() != Some(session) {
+                return false;
+            }
+        }
+        if let Some(agent) = &self.agent {
+            if event_field(&record.event, "agent").as_deref() != Some(agent) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+
+/// Poll the daemon forever, printing matching events as they arrive.
+pub fn run(config_dir: &Path, options: &WatchOptions, timeout_ms: u64) -> Result<(), String> {
+    let mut since = options.since;
+    let stdout = std::io::stdout();
+    loop {
+        let cmd = Command::Watch {
+            since: since.map(|s| s.to_string()),
+            timeout: options.timeout.map(|t| t.to_string()),
+        };
+        let records = poll(config_dir, &cmd, timeout_ms)?;
+        let mut out = stdout.lock();
+        for record in &records {
+            since = Some(since.map_or(record.seq, |s| s.max(record.seq)));
+            if options.matches(record) {
+                let line = if options.json {
+                    serde_json::to_string(record).unwrap_or_default()
+                } else {
+                    format_record(record)
+                };
+                writeln!(out, "{}", line).map_err(|e| e.to_string())?;
+            }
+        }
+        out.flush().map_err(|e| e.to_string())?;
+    }
+}
+
+
+/// Send one `Watch` poll and return the events it carries.
+///
+/// The daemon answers a poll that saw no events with `[]` once its timeout
+/// is up. If the socket read runs out first, the poll was quiet too, so
+/// that is an empty batch rather than an error.
+fn poll(config_dir: &Path, cmd: &Command, timeout_ms: u64) -> Result<Vec<EventRecord>, String> {
+    let started = Instant::now();
+    let output = match client::send_command(config_dir, cmd, timeout_ms) {
+        Ok(Response::Ok { output }) => output,
+        Ok(Response::Error { message }) => return Err(message),
+        Err(_) if started.elapsed() >= Duration::from_millis(timeout_ms) => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    serde_json::from_str(&output).map_err(|e| format!("bad watch reply '{}': {}", output, e))
+}
+
+
+/// One human-readable line: `#12 14:03:07 layout_changed work ROW(...)`.
+pub fn format_record(record: &EventRecord) -> String {
+    let secs = (record.timestamp_ms / 1000) % 86_400;
+    let detail = match &record.event {
+        MuxEvent::CommandExecuted { command, ok } => format!(
+            "{} {}",
+            command["command"].as_str().unwrap_or("?"),
+            if *ok { "ok" } else { "failed" }
+        ),
+        MuxEvent::LayoutChanged { session, layout } => format!("{} {}", session, layout),
+        MuxEvent::SessionCreated { session } | MuxEvent::SessionClosed { session } => {
+            session.clone()
+        }
+        MuxEvent::PaneFocused { session, pane } => format!("{} {}", session, pane),
+        MuxEvent::AgentStatusChanged { agent, status } => {
+            let status = serde_json::to_value(status).unwrap_or_default();
+            format!("{} {}", agent, status.as_str().unwrap_or("?"))
+        }
+        MuxEvent::HistoryFailed { session, error } => format!("{} {}", session, error),
+    };
+    format!(
+        "#{} {:02}:{:02}:{:02} {} {}",
+        record.seq,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        record.event.kind(),
+        detail
+    )
+}
+
+
+/// A string field of the event, looking inside the command for
+/// `command_executed` (e.g. `session`, `agent`).
+fn event_field(event: &MuxEvent, key: &str) -> Option<String> {
+    let value = serde_json::to_value(event).ok()?;
+    let field = match event {
+        MuxEvent::CommandExecuted { .. } => &value["command"][key],
+        _ => &value[key],
+    };
+    field.as_str().map(|s| s.to_string())
+}
+
+
+fn number(value: &str, flag: &str) -> Result<u64, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} expects a number, got '{}'", flag, value))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use muxux_core::types::agent::AgentStatus;
+
+    fn record(seq: u64, event: MuxEvent) -> EventRecord {
+        EventRecord {
+            seq,
+            timestamp_ms: 3_723_000,
+            event,
+        }
+    }
+
+    fn layout(session: &str) -> MuxEvent {
+        MuxEvent::LayoutChanged {
+            session: session.into(),
+            layout: "ROW(a, b)".into(),
+        }
+    }
+
+    #[test]
+    fn parse_all_flags() {
+        let args = [
+            "--since", "12", "--timeout", "500", "--session", "work", "--type",
+            "layout_changed,pane_focused", "--agent", "pm", "--json",
+        ];
+        let options = WatchOptions::parse(&args).unwrap();
+        assert_eq!(options.since, Some(12));
+        assert_eq!(options.timeout, Some(500));
+        assert_eq!(options.session.as_deref(), Some("work"));
+        assert_eq!(options.types, vec!["layout_changed", "pane_focused"]);
+        assert_eq!(options.agent.as_deref(), Some("pm"));
+        assert!(options.json);
+    }
+
+    #[test]
+    fn parse_rejects_bad_flags() {
+        assert!(WatchOptions::parse(&["--since", "soon"]).is_err());
+        assert!(WatchOptions::parse(&["--session"]).is_err());
+        assert!(WatchOptions::parse(&["--verbose"]).is_err());
+    }
+
+    #[test]
+    fn filters_by_session_and_type() {
+        let options = WatchOptions {
+            session: Some("work".into()),
+            types: vec!["layout_changed".into()],
+            ..WatchOptions::default()
+        };
+        assert!(options.matches(&record(1, layout("work"))));
+        assert!(!options.matches(&record(2, layout("play"))));
+        let created = MuxEvent::SessionCreated {
+            session: "work".into(),
+        };
+        assert!(!options.matches(&record(3, created)));
+    }
+
+    #[test]
+    fn filters_commands_by_their_fields() {
+        let options = WatchOptions {
+            agent: Some("pm".into()),
+            ..WatchOptions::default()
+        };
+        let placed = MuxEvent::CommandExecuted {
+            command: serde_json::json!({"command": "layout.place", "pane": "%1", "agent": "pm"}),
+            ok: true,
+        };
+        assert!(options.matches(&record(1, placed)));
+        let dead = MuxEvent::AgentStatusChanged {
+            agent: "worker".into(),
+            status: AgentStatus::Dead,
+        };
+        assert!(!options.matches(&record(2, dead)));
+    }
+
+    #[test]
+    fn human_format() {
+        assert_eq!(
+            format_record(&record(5, layout("work"))),
+            "#5 01:02:03 layout_changed work ROW(a, b)"
+        );
+        let dead = MuxEvent::AgentStatusChanged {
+            agent: "pm".into(),
+            status: AgentStatus::Dead,
+        };
+        assert!(format_record(&record(6, dead)).ends_with("agent_status_changed pm dead"));
+    }
+
+    #[test]
+    fn quiet_poll_outlives_the_socket_timeout() {
+        let dir = std::env::temp_dir().join(format!("muxux-watch-quiet-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        // A daemon that accepts the poll and never answers it.
+        let listener = std::os::unix::net::UnixListener::bind(dir.join("mux.sock")).unwrap();
+        let quiet = std::thread::spawn(move || {
+            let (_stream, _) = listener.accept().unwrap();
+            std::thread::sleep(Duration::from_millis(300));
+        });
+        let cmd = Command::Watch {
+            since: None,
+            timeout: None,
+        };
+        assert_eq!(poll(&dir, &cmd, 100), Ok(Vec::new()));
+        quiet.join().unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn poll_fails_without_a_daemon() {
+        let dir = std::env::temp_dir().join(format!("muxux-watch-none-{}", std::process::id()));
+        let cmd = Command::Watch {
+            since: None,
+            timeout: None,
+        };
+        assert!(poll(&dir, &cmd, 100).is_err());
+    }
+}
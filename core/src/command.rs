@@ -25,6 +25,8 @@ pub enum Command {
     #[serde(rename = "view")]
     View {
         name: String,
+        #[serde(default)]
+        diagram: bool,
     },
 
     // -----------------------------------------------------------------
@@ -48,6 +50,16 @@ pub enum Command {
     #[serde(rename = "layout.merge")]
     LayoutMerge {
         session: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        keep: Option<String>,
+        #[serde(default)]
+        break_out: bool,
+    },
+
+    #[serde(rename = "layout.apply")]
+    LayoutApply {
+        session: String,
+        layout: String,
     },
 
     #[serde(rename = "layout.place")]
@@ -61,6 +73,22 @@ pub enum Command {
         session: String,
     },
 
+    #[serde(rename = "layout.restore")]
+    LayoutRestore {
+        session: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        part: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        file: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        at: Option<String>,
+    },
+
+    #[serde(rename = "layout.history")]
+    LayoutHistory {
+        session: String,
+    },
+
     #[serde(rename = "layout.session")]
     LayoutSession {
         name: String,
@@ -125,6 +153,67 @@ pub enum Command {
 
     #[serde(rename = "parts.list")]
     PartsList,
+
+    #[serde(rename = "parts.show")]
+    PartsShow {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        width: Option<u16>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        height: Option<u16>,
+        #[serde(default)]
+        ascii: bool,
+    },
+
+    #[serde(rename = "parts.check")]
+    PartsCheck {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        file: Option<String>,
+    },
+
+    #[serde(rename = "parts.add")]
+    PartsAdd {
+        name: String,
+        body: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        file: Option<String>,
+    },
+
+    #[serde(rename = "parts.update")]
+    PartsUpdate {
+        name: String,
+        body: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        file: Option<String>,
+    },
+
+    #[serde(rename = "parts.rm")]
+    PartsRemove {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        file: Option<String>,
+    },
+
+    #[serde(rename = "parts.mv")]
+    PartsRename {
+        name: String,
+        to: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        file: Option<String>,
+    },
+}
+
+
+/// What a client sends the daemon: a command, plus the project the client
+/// is working in. A bare `Command` reads as a request without one.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Request {
+    #[serde(flatten)]
+    pub command: Command,
+    /// The client's project root, for the project catalog layer and as the
+    /// default cwd. The daemon's own is used when this is absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_root: Option<String>,
 }
 
 
@@ -132,6 +221,22 @@ pub enum Command {
 mod tests {
     use super::*;
 
+    #[test]
+    fn request_carries_the_project_root() {
+        let request = Request {
+            command: Command::SessionList,
+            project_root: Some("/repo".into()),
+        };
+        let json = serde_json::to_string(&request).unwrap();
+        assert_eq!(json, r#"{"command":"session.list","project_root":"/repo"}"#);
+        let back: Request = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, request);
+        let json = serde_json::to_string(&Command::PartsCheck { file: Some("p.md".into()) }).unwrap();
+        let bare: Request = serde_json::from_str(&json).unwrap();
+        assert_eq!(bare.command, Command::PartsCheck { file: Some("p.md".into()) });
+        assert_eq!(bare.project_root, None);
+    }
+
     #[test]
     fn status_round_trip() {
         let cmd = Command::Status { format: None };
@@ -222,4 +327,51 @@ mod tests {
         let back: Command = serde_json::from_str(&json).unwrap();
         assert_eq!(back, cmd);
     }
+
+    #[test]
+    fn parts_show_defaults() {
+        let json = r#"{"command":"parts.show","name":"pair"}"#;
+        let cmd: Command = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            cmd,
+            Command::PartsShow {
+                name: "pair".into(),
+                width: None,
+                height: None,
+                ascii: false,
+            }
+        );
+    }
+
+    #[test]
+    fn parts_check_round_trip() {
+        let cmd = Command::PartsCheck { file: None };
+        let json = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(json, r#"{"command":"parts.check"}"#);
+        let back: Command = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, cmd);
+    }
+
+    #[test]
+    fn parts_edit_round_trip() {
+        let cmd = Command::PartsRename {
+            name: "worker".into(),
+            to: "coder".into(),
+            file: None,
+        };
+        let json = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(json, r#"{"command":"parts.mv","name":"worker","to":"coder"}"#);
+        let back: Command = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, cmd);
+        let json = r#"{"command":"parts.add","name":"qa","body":"role: qa","file":"p.md"}"#;
+        let cmd: Command = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            cmd,
+            Command::PartsAdd {
+                name: "qa".into(),
+                body: "role: qa".into(),
+                file: Some("p.md".into()),
+            }
+        );
+    }
 }
@@ -1,135 +1,789 @@
-use crate::types::session::{LayoutEntry, LayoutNode};
+//! Layout expressions — `ROW(pilot 30%, COL(w1, w2) 70%)` and back.
+//!
+//! Each child may carry sizes after its name: a share of the parent
+//! (`30%`), an exact size in cells (`40c` for columns inside a ROW, `12r`
+//! for rows inside a COL), bounds (`>=10r`, `<=80c`), or `*` to take what
+//! the others leave, which is also what a child without a size does.
+//! `layout::solver` turns these into concrete split sizes.
+//!
+//! A leaf may carry attributes in brackets between its name and its sizes:
+//! `worker[cmd="cargo watch", cwd="~/proj", title="build", focus] 40%`.
+//! A leaf named `_` is a pane without an agent, as in `ROW(pm, _ 30%)`.
+//! `cmd` replaces the agent's launch command, `cwd` is where the pane
+//! starts, `title` names the pane, and `focus` selects it. A whole session
+//! is written `WIN(editor: ROW(...), logs: tail)`, one named window per
+//! entry; `parse_windows_expr` reads it and `parse_layout_expr` refuses it.
+//!
+//! Expressions are tokenized and parsed with byte spans, so a syntax error
+//! points at the offending token by line and column, with a caret under
+//! it. `validate` also reports expressions that parse but look wrong:
+//! percentages that do not add up, repeated or unknown agents, and empty
+//! entries.
+
+use std::fmt;
+
+use crate::types::session::{LayoutEntry, LayoutNode, PaneOptions, SizeSpec, WindowSnapshot};
+
+
+/// The leaf name written for a pane without an agent.
+pub const UNNAMED: &str = "_";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+
+/// A problem in an expression, located by byte offsets into it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Diagnostic {
+    fn error(message: impl Into<String>, start: usize, end: usize) -> Diagnostic {
+        Diagnostic { severity: Severity::Error, message: message.into(), start, end }
+    }
+
+    fn warning(message: impl Into<String>, start: usize, end: usize) -> Diagnostic {
+        Diagnostic { severity: Severity::Warning, message: message.into(), start, end }
+    }
+
+    /// 1-based line and column (in characters) where the problem starts.
+    pub fn line_column(&self, source: &str) -> (usize, usize) {
+        let before = &source[..self.start.min(source.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+        (line, column)
+    }
+
+    /// The source line holding the problem, with carets under it.
+    pub fn snippet(&self, source: &str) -> String {
+        let start = self.start.min(source.len());
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[start..].find('\n').map_or(source.len(), |i| start + i);
+        let pad = source[line_start..start].chars().count();
+        let width = source[start..self.end.clamp(start, line_end)].chars().count().max(1);
+        format!("  {}\n  {}{}", &source[line_start..line_end], " ".repeat(pad), "^".repeat(width))
+    }
+
+    /// `error: <message> at line L, column C`, then the snippet.
+    pub fn render(&self, source: &str) -> String {
+        let (line, column) = self.line_column(source);
+        format!(
+            "{}: {} at line {}, column {}\n{}",
+            self.severity, self.message, line, column, self.snippet(source)
+        )
+    }
+}
+
 
 pub fn parse_layout_expr(input: &str) -> Result<LayoutNode, String> {
-    let trimmed = input.trim();
-    if trimmed.is_empty() { return Err("empty layout expression".into()); }
-    let upper = trimmed.to_uppercase();
-    if upper.starts_with("ROW(") {
-        let inner = extract_parens(trimmed)?;
-        Ok(LayoutNode::Row { children: parse_children(inner)? })
-    } else if upper.starts_with("COL(") {
-        let inner = extract_parens(trimmed)?;
-        Ok(LayoutNode::Col { children: parse_children(inner)? })
-    } else {
-        let (name, _) = parse_leaf(trimmed)?;
-        Ok(LayoutNode::Pane { agent: name })
-    }
-}
-
-fn parse_children(inner: &str) -> Result<Vec<LayoutEntry>, String> {
-    let parts = split_top_level_commas(inner);
-    let mut entries = Vec::new();
-    for part in parts {
-        let part = part.trim();
-        if part.is_empty() { continue; }
-        let (node, percent) = parse_child_entry(part)?;
-        entries.push(LayoutEntry { node, percent });
-    }
-    if entries.is_empty() { return Err("empty children list".into()); }
-    Ok(entries)
-}
-
-fn parse_child_entry(s: &str) -> Result<(LayoutNode, Option<u32>), String> {
-    let trimmed = s.trim();
-    let upper = trimmed.to_uppercase();
-    if upper.starts_with("ROW(") || upper.starts_with("COL(") {
-        let close = find_matching_paren(trimmed)?;
-        let nested_str = &trimmed[..=close];
-        let remainder = trimmed[close + 1..].trim();
-        let node = parse_layout_expr(nested_str)?;
-        let percent = parse_trailing_percent(remainder)?;
-        Ok((node, percent))
-    } else {
-        let (name, percent) = parse_leaf(trimmed)?;
-        Ok((LayoutNode::Pane { agent: name }, percent))
-    }
-}
-
-fn extract_parens(s: &str) -> Result<&str, String> {
-    let open = s.find('(').ok_or_else(|| format!("expected '(' in: {}", s))?;
-    let close = s.rfind(')').ok_or_else(|| format!("expected ')' in: {}", s))?;
-    if close <= open { return Err(format!("mismatched parens in: {}", s)); }
-    Ok(&s[open + 1..close])
-}
-
-fn find_matching_paren(s: &str) -> Result<usize, String> {
-    let start = s.find('(').ok_or_else(|| format!("no open paren in: {}", s))?;
-    let mut depth = 0;
-    for (i, ch) in s.char_indices() {
-        match ch {
-            '(' => depth += 1,
-            ')' => { depth -= 1; if depth == 0 { return Ok(i); } }
-            _ => {}
+    parse_spanned(input).map_err(|d| d.render(input))
+}
+
+/// Parse `input`, locating the first syntax error.
+pub fn parse_spanned(input: &str) -> Result<LayoutNode, Diagnostic> {
+    Parser::new(input).expression()
+}
+
+/// Parse a `WIN(name: layout, ...)` expression into its windows, indexed
+/// from 0. A plain layout comes back as a single unnamed window.
+pub fn parse_windows_expr(input: &str) -> Result<Vec<WindowSnapshot>, String> {
+    Parser::new(input).document().map_err(|d| d.render(input))
+}
+
+/// Whether `input` is a `WIN(...)` expression rather than a single layout.
+pub fn is_windows_expr(input: &str) -> bool {
+    let tokens = tokenize(input);
+    matches!(tokens.as_slice(), [first, second, ..]
+        if first.tok == Tok::Word
+            && input[first.start..first.end].eq_ignore_ascii_case("WIN")
+            && second.tok == Tok::Open)
+}
+
+/// Every leaf name in `input` with its byte span, in source order, so a
+/// name can be edited without rewriting the rest of the expression.
+pub fn leaf_spans(input: &str) -> Result<Vec<(String, usize, usize)>, Diagnostic> {
+    let mut parser = Parser::new(input);
+    parser.document()?;
+    Ok(parser.leaves)
+}
+
+/// Parse `input` and report everything that looks wrong with it. A syntax
+/// error is returned alone; otherwise warnings are returned in source
+/// order. Leaf names for which `is_part` returns false are reported as
+/// unknown.
+pub fn validate(input: &str, is_part: &dyn Fn(&str) -> bool) -> Vec<Diagnostic> {
+    let mut parser = Parser::new(input);
+    if let Err(error) = parser.document() {
+        return vec![error];
+    }
+    let mut found = Vec::new();
+    for group in &parser.groups {
+        let sum: u32 = group.percents.iter().flatten().sum();
+        let all_set = group.percents.iter().all(Option::is_some);
+        // Captured layouts round each share, so allow a cell of slack each.
+        let slack = group.percents.len() as u32 - 1;
+        if all_set && sum.abs_diff(100) > slack {
+            let message = format!("percentages in {} add up to {}%, not 100%", group.kind, sum);
+            found.push(Diagnostic::warning(message, group.start, group.end));
+        } else if !all_set && sum >= 100 {
+            let message = format!(
+                "percentages in {} already add up to {}%, leaving no room for children without one",
+                group.kind, sum
+            );
+            found.push(Diagnostic::warning(message, group.start, group.end));
+        }
+    }
+    for &at in &parser.empties {
+        found.push(Diagnostic::warning("empty entry", at, at + 1));
+    }
+    let mut windows: Vec<&str> = Vec::new();
+    for (name, start, end) in &parser.window_names {
+        if windows.contains(&name.as_str()) {
+            let message = format!("window '{}' appears more than once", name);
+            found.push(Diagnostic::warning(message, *start, *end));
+        } else {
+            windows.push(name);
+        }
+    }
+    for (i, (window, start, end)) in parser.focused.iter().enumerate() {
+        if parser.focused[..i].iter().any(|(w, _, _)| w == window) {
+            let message = "another pane in this window already has focus";
+            found.push(Diagnostic::warning(message, *start, *end));
+        }
+    }
+    let mut seen: Vec<&str> = Vec::new();
+    for (name, start, end) in &parser.leaves {
+        if seen.contains(&name.as_str()) {
+            let message = format!("'{}' appears more than once", name);
+            found.push(Diagnostic::warning(message, *start, *end));
+        } else {
+            seen.push(name);
+        }
+        if !is_part(name) {
+            let message = format!("'{}' is not a part in the catalog", name);
+            found.push(Diagnostic::warning(message, *start, *end));
         }
     }
-    Err(format!("unmatched paren at {}", start))
+    found.sort_by_key(|d| d.start);
+    found
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Tok {
+    Open,
+    Close,
+    Comma,
+    Colon,
+    Word,
+    /// Tokens only produced inside `[...]`.
+    LBracket,
+    RBracket,
+    Equals,
+    /// A `"..."` string, quotes included; may be missing its closing quote.
+    Str,
 }
 
-fn split_top_level_commas(s: &str) -> Vec<&str> {
-    let mut parts = Vec::new();
-    let mut depth = 0;
-    let mut start = 0;
-    for (i, ch) in s.char_indices() {
-        match ch {
-            '(' => depth += 1,
-            ')' => depth -= 1,
-            ',' if depth == 0 => { parts.push(&s[start..i]); start = i + 1; }
+#[derive(Debug, Clone, Copy)]
+struct Token {
+    tok: Tok,
+    start: usize,
+    end: usize,
+}
+
+/// Split `input` into punctuation and whitespace-separated words. Inside
+/// `[...]`, `=` and quoted strings are tokens of their own too. `:` only
+/// ends a window name in a `WIN(...)` entry; anywhere else it is part of a
+/// word, so agents like `pm:lead` need no quoting.
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut word: Option<usize> = None;
+    let mut in_attrs = false;
+    let mut depth = 0usize;
+    let mut in_win = false;
+    // Whether a WIN entry's name is still being read.
+    let mut header = false;
+    let mut chars = input.char_indices().peekable();
+    while let Some((i, ch)) = chars.next() {
+        if in_attrs && ch == '"' && word.is_none() {
+            let mut end = input.len();
+            let mut escaped = false;
+            for (j, c) in chars.by_ref() {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => {
+                        end = j + 1;
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            tokens.push(Token { tok: Tok::Str, start: i, end });
+            continue;
+        }
+        let single = match ch {
+            '(' => Some(Tok::Open),
+            ')' => Some(Tok::Close),
+            ',' => Some(Tok::Comma),
+            ':' if header => Some(Tok::Colon),
+            '[' => Some(Tok::LBracket),
+            ']' => Some(Tok::RBracket),
+            '=' if in_attrs => Some(Tok::Equals),
+            _ => None,
+        };
+        if single.is_some() || ch.is_whitespace() {
+            if let Some(start) = word.take() {
+                tokens.push(Token { tok: Tok::Word, start, end: i });
+            }
+            if let Some(tok) = single {
+                tokens.push(Token { tok, start: i, end: i + 1 });
+            }
+        } else if word.is_none() {
+            word = Some(i);
+        }
+        match single {
+            Some(Tok::LBracket) => in_attrs = true,
+            Some(Tok::RBracket) => in_attrs = false,
+            Some(Tok::Open) => {
+                if depth == 0 && tokens.len() == 2 {
+                    in_win = input[tokens[0].start..tokens[0].end].eq_ignore_ascii_case("WIN");
+                }
+                depth += 1;
+                header = in_win && depth == 1;
+            }
+            Some(Tok::Close) => {
+                depth = depth.saturating_sub(1);
+                header = false;
+            }
+            Some(Tok::Comma) if !in_attrs => header = in_win && depth == 1,
+            Some(Tok::Colon) => header = false,
             _ => {}
         }
     }
-    if start <= s.len() { parts.push(&s[start..]); }
-    parts
+    if let Some(start) = word {
+        tokens.push(Token { tok: Tok::Word, start, end: input.len() });
+    }
+    tokens
+}
+
+
+/// Facts about one ROW/COL kept for `validate`.
+struct Group {
+    kind: &'static str,
+    start: usize,
+    end: usize,
+    percents: Vec<Option<u32>>,
+}
+
+/// Recursive-descent parser over `tokenize` output. Besides the tree it
+/// records leaves, empty entries, groups, window names, and focus markers
+/// (with the window they are in) for `validate`.
+struct Parser<'a> {
+    input: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+    leaves: Vec<(String, usize, usize)>,
+    empties: Vec<usize>,
+    groups: Vec<Group>,
+    window_names: Vec<(String, usize, usize)>,
+    focused: Vec<(usize, usize, usize)>,
 }
 
-fn parse_leaf(s: &str) -> Result<(String, Option<u32>), String> {
-    let trimmed = s.trim();
-    if trimmed.is_empty() { return Err("empty leaf name".into()); }
-    if let Some(space_pos) = trimmed.rfind(' ') {
-        let maybe_pct = trimmed[space_pos + 1..].trim();
-        if let Some(num_str) = maybe_pct.strip_suffix('%') {
-            if let Ok(n) = num_str.parse::<u32>() {
-                let name = trimmed[..space_pos].trim().to_string();
-                if name.is_empty() { return Err("empty leaf name".into()); }
-                return Ok((name, Some(n)));
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Parser<'a> {
+        Parser {
+            input,
+            tokens: tokenize(input),
+            pos: 0,
+            leaves: Vec::new(),
+            empties: Vec::new(),
+            groups: Vec::new(),
+            window_names: Vec::new(),
+            focused: Vec::new(),
+        }
+    }
+
+    fn text(&self, token: Token) -> &'a str {
+        &self.input[token.start..token.end]
+    }
+
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn peek_is(&self, offset: usize, tok: Tok) -> bool {
+        self.tokens.get(self.pos + offset).is_some_and(|t| t.tok == tok)
+    }
+
+    /// A `WIN(...)` expression, or a plain layout as one unnamed window.
+    fn document(&mut self) -> Result<Vec<WindowSnapshot>, Diagnostic> {
+        if !is_windows_expr(self.input) {
+            let layout = self.expression()?;
+            return Ok(vec![WindowSnapshot {
+                index: 0,
+                name: String::new(),
+                layout,
+                tmux_layout: None,
+            }]);
+        }
+        let open = self.tokens[1];
+        self.pos = 2;
+        let mut windows = Vec::new();
+        let close = loop {
+            match self.peek() {
+                None => return Err(Diagnostic::error("'(' of WIN is never closed", open.start, open.end)),
+                Some(t) if t.tok == Tok::Comma || t.tok == Tok::Close => self.empties.push(t.start),
+                Some(_) => {
+                    let (name, layout) = self.window(windows.len())?;
+                    windows.push(WindowSnapshot {
+                        index: windows.len() as u32,
+                        name,
+                        layout,
+                        tmux_layout: None,
+                    });
+                }
+            }
+            match self.peek() {
+                Some(t) if t.tok == Tok::Comma => self.pos += 1,
+                Some(t) if t.tok == Tok::Close => {
+                    self.pos += 1;
+                    break t;
+                }
+                Some(t) => {
+                    let message = format!("expected ',' or ')', found '{}'", self.text(t));
+                    return Err(Diagnostic::error(message, t.start, t.end));
+                }
+                None => return Err(Diagnostic::error("'(' of WIN is never closed", open.start, open.end)),
             }
+        };
+        if windows.is_empty() {
+            return Err(Diagnostic::error("WIN has no windows", self.tokens[0].start, close.end));
         }
+        if let Some(extra) = self.peek() {
+            let message = format!("unexpected '{}' after WIN(...)", self.text(extra));
+            return Err(Diagnostic::error(message, extra.start, extra.end));
+        }
+        Ok(windows)
+    }
+
+    /// `name: layout` inside `WIN(...)`; `index` is the window's position.
+    fn window(&mut self, index: usize) -> Result<(String, LayoutNode), Diagnostic> {
+        let first = self.pos;
+        while self.peek_is(0, Tok::Word) && !self.peek_is(1, Tok::Open) {
+            self.pos += 1;
+        }
+        match self.peek() {
+            Some(t) if t.tok == Tok::Colon && self.pos > first => {}
+            Some(t) if t.tok == Tok::Colon => {
+                return Err(Diagnostic::error("window has no name before ':'", t.start, t.end));
+            }
+            Some(t) => {
+                let start = self.tokens[first].start;
+                let message = "expected 'name:' before the window's layout";
+                return Err(Diagnostic::error(message, start, t.end));
+            }
+            None => {
+                let end = self.input.len();
+                return Err(Diagnostic::error("expected ':' after the window name", end, end));
+            }
+        }
+        let (start, end) = (self.tokens[first].start, self.tokens[self.pos - 1].end);
+        let name = self.input[start..end].to_string();
+        self.window_names.push((name.clone(), start, end));
+        self.pos += 1;
+        let before = self.focused.len();
+        // A window's layout is a top-level layout: sizes on it mean nothing.
+        let (layout, _, _) = self.entry(None)?;
+        for focus in &mut self.focused[before..] {
+            focus.0 = index;
+        }
+        Ok((name, layout))
+    }
+
+    fn expression(&mut self) -> Result<LayoutNode, Diagnostic> {
+        if self.tokens.is_empty() {
+            return Err(Diagnostic::error("empty layout expression", 0, self.input.len()));
+        }
+        if is_windows_expr(self.input) {
+            let keyword = self.tokens[0];
+            let message = "WIN(...) describes a session's windows, not one window's layout";
+            return Err(Diagnostic::error(message, keyword.start, keyword.end));
+        }
+        // Sizes on a lone top-level pane have nothing to divide; ignore them.
+        let (node, _, _) = self.entry(None)?;
+        if let Some(extra) = self.peek() {
+            let message = format!("unexpected '{}' after the layout", self.text(extra));
+            return Err(Diagnostic::error(message, extra.start, extra.end));
+        }
+        Ok(node)
+    }
+
+    /// One child — a ROW/COL group or a leaf — and the sizes after it.
+    /// `unit` is the cell unit the parent expects, `None` at the top level.
+    fn entry(
+        &mut self,
+        unit: Option<char>,
+    ) -> Result<(LayoutNode, Option<u32>, SizeSpec), Diagnostic> {
+        match self.peek() {
+            Some(t) if t.tok == Tok::Word => {}
+            Some(t) => {
+                let message = format!("expected a name or ROW(...)/COL(...), found '{}'", self.text(t));
+                return Err(Diagnostic::error(message, t.start, t.end));
+            }
+            None => {
+                let end = self.input.len();
+                return Err(Diagnostic::error("expected a name or ROW(...)/COL(...)", end, end));
+            }
+        }
+        let mut percent = None;
+        let mut size = SizeSpec::default();
+
+        if self.peek_is(1, Tok::Open) {
+            let (node, kind) = self.group()?;
+            while let Some(t) = self.peek().filter(|t| t.tok == Tok::Word) {
+                if !self.size_token(t, unit, &mut percent, &mut size)? {
+                    let message = format!("unexpected '{}' after {}(...)", self.text(t), kind);
+                    return Err(Diagnostic::error(message, t.start, t.end));
+                }
+                self.pos += 1;
+            }
+            return Ok((node, percent, size));
+        }
+
+        let first = self.pos;
+        while self.peek_is(0, Tok::Word) {
+            self.pos += 1;
+        }
+        if let Some(t) = self.peek().filter(|t| t.tok == Tok::Open) {
+            return Err(Diagnostic::error("expected ',' or ')' before '('", t.start, t.end));
+        }
+        let words: Vec<Token> = self.tokens[first..self.pos].to_vec();
+        let mut last = words.len();
+        while last > 1 && self.size_token(words[last - 1], unit, &mut percent, &mut size)? {
+            last -= 1;
+        }
+        let (start, end) = (words[0].start, words[last - 1].end);
+        let name = self.input[start..end].to_string();
+        if name != UNNAMED {
+            self.leaves.push((name.clone(), start, end));
+        }
+        let mut options = PaneOptions::default();
+        if self.peek_is(0, Tok::LBracket) {
+            options = self.attributes(&name)?;
+            if options.focus {
+                self.focused.push((0, start, end));
+            }
+            while let Some(t) = self.peek().filter(|t| t.tok == Tok::Word) {
+                if !self.size_token(t, unit, &mut percent, &mut size)? {
+                    let message = format!("unexpected '{}' after {}[...]", self.text(t), name);
+                    return Err(Diagnostic::error(message, t.start, t.end));
+                }
+                self.pos += 1;
+            }
+        }
+        let agent = if name == UNNAMED { String::new() } else { name };
+        Ok((LayoutNode::Pane { agent, options }, percent, size))
+    }
+
+    /// `[cmd="...", cwd="...", title="...", focus]` after the leaf `name`,
+    /// starting at `[`.
+    fn attributes(&mut self, name: &str) -> Result<PaneOptions, Diagnostic> {
+        let open = self.tokens[self.pos];
+        self.pos += 1;
+        let unclosed = || {
+            let message = format!("'[' of {} is never closed", name);
+            Diagnostic::error(message, open.start, open.end)
+        };
+        let mut options = PaneOptions::default();
+        loop {
+            let key = match self.peek() {
+                None => return Err(unclosed()),
+                Some(t) if t.tok == Tok::RBracket => {
+                    self.pos += 1;
+                    return Ok(options);
+                }
+                Some(t) if t.tok == Tok::Word => t,
+                Some(t) => {
+                    let message = format!("expected an attribute name, found '{}'", self.text(t));
+                    return Err(Diagnostic::error(message, t.start, t.end));
+                }
+            };
+            self.pos += 1;
+            let duplicate = || {
+                let message = format!("'{}' is given more than once", self.text(key));
+                Diagnostic::error(message, key.start, key.end)
+            };
+            if self.text(key) == "focus" && !self.peek_is(0, Tok::Equals) {
+                if options.focus {
+                    return Err(duplicate());
+                }
+                options.focus = true;
+            } else {
+                let slot = match self.text(key) {
+                    "cmd" => &mut options.cmd,
+                    "cwd" => &mut options.cwd,
+                    "title" => &mut options.title,
+                    other => {
+                        let message = format!(
+                            "unknown attribute '{}', expected cmd, cwd, title or focus",
+                            other
+                        );
+                        return Err(Diagnostic::error(message, key.start, key.end));
+                    }
+                };
+                if slot.is_some() {
+                    return Err(duplicate());
+                }
+                match self.peek() {
+                    Some(t) if t.tok == Tok::Equals => self.pos += 1,
+                    _ => {
+                        let message = format!("expected '=' after '{}'", self.text(key));
+                        return Err(Diagnostic::error(message, key.start, key.end));
+                    }
+                }
+                let value = match self.peek() {
+                    Some(t) if t.tok == Tok::Word => self.text(t).to_string(),
+                    Some(t) if t.tok == Tok::Str => unquote(self.text(t)).ok_or_else(|| {
+                        Diagnostic::error("string is never closed", t.start, t.end)
+                    })?,
+                    Some(t) => {
+                        let message = format!("expected a value for '{}'", self.text(key));
+                        return Err(Diagnostic::error(message, t.start, t.end));
+                    }
+                    None => return Err(unclosed()),
+                };
+                self.pos += 1;
+                *slot = Some(value);
+            }
+            match self.peek() {
+                Some(t) if t.tok == Tok::Comma => self.pos += 1,
+                Some(t) if t.tok == Tok::RBracket => {}
+                Some(t) => {
+                    let message = format!("expected ',' or ']', found '{}'", self.text(t));
+                    return Err(Diagnostic::error(message, t.start, t.end));
+                }
+                None => return Err(unclosed()),
+            }
+        }
+    }
+
+    /// `ROW(...)` or `COL(...)`, starting at its keyword.
+    fn group(&mut self) -> Result<(LayoutNode, &'static str), Diagnostic> {
+        let keyword = self.tokens[self.pos];
+        let (kind, unit) = match self.text(keyword).to_uppercase().as_str() {
+            "ROW" => ("ROW", 'c'),
+            "COL" => ("COL", 'r'),
+            "WIN" => {
+                let message = "WIN(...) is only allowed around a whole expression";
+                return Err(Diagnostic::error(message, keyword.start, keyword.end));
+            }
+            _ => {
+                let message = format!("unknown split '{}', expected ROW or COL", self.text(keyword));
+                return Err(Diagnostic::error(message, keyword.start, keyword.end));
+            }
+        };
+        let open = self.tokens[self.pos + 1];
+        self.pos += 2;
+
+        let mut children = Vec::new();
+        let close = loop {
+            match self.peek() {
+                None => {
+                    let message = format!("'(' of {} is never closed", kind);
+                    return Err(Diagnostic::error(message, open.start, open.end));
+                }
+                Some(t) if t.tok == Tok::Comma || t.tok == Tok::Close => self.empties.push(t.start),
+                Some(_) => {
+                    let (node, percent, size) = self.entry(Some(unit))?;
+                    children.push(LayoutEntry { node, percent, size });
+                }
+            }
+            match self.peek() {
+                Some(t) if t.tok == Tok::Comma => self.pos += 1,
+                Some(t) if t.tok == Tok::Close => {
+                    self.pos += 1;
+                    break t;
+                }
+                Some(t) => {
+                    let message = format!("expected ',' or ')', found '{}'", self.text(t));
+                    return Err(Diagnostic::error(message, t.start, t.end));
+                }
+                None => {
+                    let message = format!("'(' of {} is never closed", kind);
+                    return Err(Diagnostic::error(message, open.start, open.end));
+                }
+            }
+        };
+        if children.is_empty() {
+            let message = format!("{} has no children", kind);
+            return Err(Diagnostic::error(message, keyword.start, close.end));
+        }
+        self.groups.push(Group {
+            kind,
+            start: keyword.start,
+            end: close.end,
+            percents: children.iter().map(|e| e.percent).collect(),
+        });
+        let node = if kind == "ROW" {
+            LayoutNode::Row { children }
+        } else {
+            LayoutNode::Col { children }
+        };
+        Ok((node, kind))
+    }
+
+    /// `apply_size_token` with errors located at `token`.
+    fn size_token(
+        &self,
+        token: Token,
+        unit: Option<char>,
+        percent: &mut Option<u32>,
+        size: &mut SizeSpec,
+    ) -> Result<bool, Diagnostic> {
+        apply_size_token(self.text(token), unit, percent, size)
+            .map_err(|message| Diagnostic::error(message, token.start, token.end))
     }
-    Ok((trimmed.to_string(), None))
 }
 
-fn parse_trailing_percent(s: &str) -> Result<Option<u32>, String> {
-    let trimmed = s.trim();
-    if trimmed.is_empty() { return Ok(None); }
-    if let Some(num_str) = trimmed.strip_suffix('%') {
-        match num_str.trim().parse::<u32>() {
-            Ok(n) => Ok(Some(n)),
-            Err(_) => Err(format!("invalid percentage: {}", trimmed)),
+/// Whether `token` would be read as a size (`30%`, `40c`, `>=10r`, `*`)
+/// rather than a name.
+pub fn is_size_token(token: &str) -> bool {
+    apply_size_token(token, None, &mut None, &mut SizeSpec::default()).unwrap_or(true)
+}
+
+/// Record one size token (`30%`, `40c`, `>=10r`, `<=80c`, `*`). Returns
+/// `Ok(false)` when `token` is not a size at all.
+fn apply_size_token(
+    token: &str,
+    unit: Option<char>,
+    percent: &mut Option<u32>,
+    size: &mut SizeSpec,
+) -> Result<bool, String> {
+    if token == "*" {
+        if size.fill || size.cells.is_some() || percent.is_some() {
+            return Err(format!("more than one size given: {}", token));
         }
-    } else {
-        Err(format!("unexpected trailing: {}", trimmed))
+        size.fill = true;
+        return Ok(true);
+    }
+    if let Some(num_str) = token.strip_suffix('%') {
+        return match num_str.parse::<u32>() {
+            Ok(n) if percent.is_none() && size.cells.is_none() && !size.fill => { *percent = Some(n); Ok(true) }
+            Ok(_) => Err(format!("more than one size given: {}", token)),
+            Err(_) => Ok(false),
+        };
     }
+    let (bound, rest) = match token.get(..2) {
+        Some(">=") => (Some(true), &token[2..]),
+        Some("<=") => (Some(false), &token[2..]),
+        _ => (None, token),
+    };
+    let Some(suffix) = rest.chars().last().filter(|c| *c == 'c' || *c == 'r') else { return Ok(false) };
+    let Ok(n) = rest[..rest.len() - 1].parse::<u32>() else { return Ok(false) };
+    if let Some(unit) = unit.filter(|u| *u != suffix) {
+        let (inside, expected) = if unit == 'c' { ("ROW", "columns") } else { ("COL", "rows") };
+        return Err(format!("'{}': {} children are sized in {} ({})", token, inside, expected, unit));
+    }
+    let slot = match bound {
+        Some(true) => &mut size.min,
+        Some(false) => &mut size.max,
+        None if percent.is_none() && !size.fill => &mut size.cells,
+        None => return Err(format!("more than one size given: {}", token)),
+    };
+    if slot.is_some() { return Err(format!("more than one size given: {}", token)); }
+    *slot = Some(n);
+    Ok(true)
 }
 
 pub fn serialize_layout_expr(node: &LayoutNode) -> String {
     match node {
         LayoutNode::Row { children } => {
-            let parts: Vec<String> = children.iter().map(serialize_entry).collect();
+            let parts: Vec<String> = children.iter().map(|e| serialize_entry(e, 'c')).collect();
             format!("ROW({})", parts.join(", "))
         }
         LayoutNode::Col { children } => {
-            let parts: Vec<String> = children.iter().map(serialize_entry).collect();
+            let parts: Vec<String> = children.iter().map(|e| serialize_entry(e, 'r')).collect();
             format!("COL({})", parts.join(", "))
         }
-        LayoutNode::Pane { agent } => agent.clone(),
+        LayoutNode::Pane { agent, options } => {
+            let name = if agent.is_empty() { UNNAMED } else { agent.as_str() };
+            if options.is_empty() {
+                return name.to_string();
+            }
+            let mut attrs = Vec::new();
+            for (key, value) in [("cmd", &options.cmd), ("cwd", &options.cwd), ("title", &options.title)] {
+                if let Some(value) = value {
+                    attrs.push(format!("{}={}", key, quote(value)));
+                }
+            }
+            if options.focus {
+                attrs.push("focus".to_string());
+            }
+            format!("{}[{}]", name, attrs.join(", "))
+        }
     }
 }
 
-fn serialize_entry(entry: &LayoutEntry) -> String {
-    let inner = serialize_layout_expr(&entry.node);
-    match entry.percent {
-        Some(p) => format!("{} {}%", inner, p),
-        None => inner,
+/// `WIN(name: layout, ...)` for several windows; a lone unnamed window is
+/// written as its plain layout.
+pub fn serialize_windows_expr(windows: &[WindowSnapshot]) -> String {
+    if let [window] = windows {
+        if window.name.is_empty() {
+            return serialize_layout_expr(&window.layout);
+        }
     }
+    let parts: Vec<String> = windows
+        .iter()
+        .map(|w| format!("{}: {}", w.name, serialize_layout_expr(&w.layout)))
+        .collect();
+    format!("WIN({})", parts.join(", "))
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The text of a `"..."` token with escapes resolved; `None` when the
+/// closing quote is missing.
+fn unquote(token: &str) -> Option<String> {
+    let inner = token.strip_prefix('"')?.strip_suffix('"')?;
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.push(chars.next()?),
+            '"' => return None,
+            c => out.push(c),
+        }
+    }
+    Some(out)
+}
+
+fn serialize_entry(entry: &LayoutEntry, unit: char) -> String {
+    let mut out = serialize_layout_expr(&entry.node);
+    if let Some(p) = entry.percent { out.push_str(&format!(" {}%", p)); }
+    if let Some(n) = entry.size.cells { out.push_str(&format!(" {}{}", n, unit)); }
+    if let Some(n) = entry.size.min { out.push_str(&format!(" >={}{}", n, unit)); }
+    if let Some(n) = entry.size.max { out.push_str(&format!(" <={}{}", n, unit)); }
+    if entry.size.fill { out.push_str(" *"); }
+    out
 }
 
 #[cfg(test)]
@@ -151,7 +805,7 @@ mod tests {
         assert_eq!(node, parse_layout_expr(&s).unwrap());
     }
     #[test] fn parse_single_pane() {
-        match parse_layout_expr("pilot").unwrap() { LayoutNode::Pane { agent } => assert_eq!(agent, "pilot"), _ => panic!() }
+        match parse_layout_expr("pilot").unwrap() { LayoutNode::Pane { agent, .. } => assert_eq!(agent, "pilot"), _ => panic!() }
     }
     #[test] fn parse_case_insensitive() {
         assert!(matches!(parse_layout_expr("row(a 50%, b 50%)").unwrap(), LayoutNode::Row { .. }));
@@ -162,8 +816,8 @@ mod tests {
     }
     #[test] fn serialize_simple_row() {
         let node = LayoutNode::Row { children: vec![
-            LayoutEntry { node: LayoutNode::Pane { agent: "pilot".into() }, percent: Some(50) },
-            LayoutEntry { node: LayoutNode::Pane { agent: "worker1".into() }, percent: Some(50) },
+            LayoutEntry { node: LayoutNode::pane("pilot"), percent: Some(50), size: SizeSpec::default() },
+            LayoutEntry { node: LayoutNode::pane("worker1"), percent: Some(50), size: SizeSpec::default() },
         ]};
         assert_eq!(serialize_layout_expr(&node), "ROW(pilot 50%, worker1 50%)");
     }
@@ -173,4 +827,138 @@ mod tests {
         let node = parse_layout_expr(expr).unwrap();
         assert_eq!(node, parse_layout_expr(&serialize_layout_expr(&node)).unwrap());
     }
+    #[test] fn parse_cells_and_bounds() {
+        let node = parse_layout_expr("ROW(sidebar 40c, COL(main *, logs >=10r <=20r), chat 30% <=80c)").unwrap();
+        let LayoutNode::Row { children } = &node else { panic!() };
+        assert_eq!(children[0].size.cells, Some(40));
+        assert_eq!(children[0].node, LayoutNode::pane("sidebar"));
+        let LayoutNode::Col { children: inner } = &children[1].node else { panic!() };
+        assert_eq!((inner[0].percent, inner[0].size.is_empty()), (None, true));
+        assert!(inner[0].size.fill);
+        assert_eq!((inner[1].size.min, inner[1].size.max), (Some(10), Some(20)));
+        assert_eq!((children[2].percent, children[2].size.max), (Some(30), Some(80)));
+    }
+    #[test] fn round_trip_sizes() {
+        let expr = "ROW(sidebar 40c, COL(main, logs >=10r) 70%, chat 30% <=80c)";
+        assert_eq!(serialize_layout_expr(&parse_layout_expr(expr).unwrap()), expr);
+        let expr = "ROW(a 20c, b *, COL(c, d * >=5r))";
+        assert_eq!(serialize_layout_expr(&parse_layout_expr(expr).unwrap()), "ROW(a 20c, b *, COL(c, d >=5r *))");
+        assert!(parse_layout_expr("ROW(a 20c *, b)").is_err());
+        assert!(parse_layout_expr("ROW(a * 50%, b)").is_err());
+    }
+    #[test] fn size_unit_must_match_direction() {
+        assert!(parse_layout_expr("ROW(a 10r, b)").is_err());
+        assert!(parse_layout_expr("COL(a >=5c, b)").is_err());
+        assert!(parse_layout_expr("ROW(a 30% 40c, b)").is_err());
+        assert!(parse_layout_expr("ROW(COL(a, b) 40c junk, c)").is_err());
+    }
+    #[test] fn error_points_at_token() {
+        let input = "ROW(a 30%,\n    COL(b, c) junk)";
+        let error = parse_spanned(input).unwrap_err();
+        assert_eq!(error.message, "unexpected 'junk' after COL(...)");
+        assert_eq!(error.line_column(input), (2, 15));
+        assert_eq!(error.snippet(input), "      COL(b, c) junk)\n                ^^^^");
+    }
+    #[test] fn unclosed_and_unknown_splits() {
+        let error = parse_spanned("ROW(a, COL(b, c)").unwrap_err();
+        assert_eq!((error.message.as_str(), error.start), ("'(' of ROW is never closed", 3));
+        let error = parse_spanned("GRID(a, b)").unwrap_err();
+        assert_eq!(error.message, "unknown split 'GRID', expected ROW or COL");
+        assert!(parse_spanned("ROW( , )").unwrap_err().message.contains("no children"));
+        let rendered = parse_layout_expr("ROW(a) b").unwrap_err();
+        assert_eq!(rendered, "error: unexpected 'b' after ROW(...) at line 1, column 8\n  ROW(a) b\n         ^");
+    }
+    #[test] fn validate_percent_sums() {
+        let found = validate("ROW(a 30%, COL(b 50%, c 60%) 60%)", &|_| true);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].message, "percentages in ROW add up to 90%, not 100%");
+        assert_eq!(found[1].message, "percentages in COL add up to 110%, not 100%");
+        assert_eq!(found[1].start, 11);
+        assert!(validate("ROW(a 33%, b 33%, c 33%)", &|_| true).is_empty());
+        let found = validate("ROW(a 60%, b 40%, c)", &|_| true);
+        assert!(found[0].message.contains("leaving no room"));
+    }
+    #[test] fn pane_attributes_round_trip() {
+        let expr = r#"ROW(worker[cmd="cargo watch", cwd="~/my proj", title="build"] 40%, pm[focus])"#;
+        let layout = parse_layout_expr(expr).unwrap();
+        let LayoutNode::Row { children } = &layout else { panic!() };
+        let LayoutNode::Pane { agent, options } = &children[0].node else { panic!() };
+        assert_eq!(agent, "worker");
+        assert_eq!(options.cmd.as_deref(), Some("cargo watch"));
+        assert_eq!(options.cwd.as_deref(), Some("~/my proj"));
+        assert_eq!(children[0].percent, Some(40));
+        assert!(layout.pane_options()[1].focus);
+        assert_eq!(serialize_layout_expr(&layout), expr);
+        let quoted = parse_layout_expr(r#"ROW(a[cmd="echo \"hi\" \\ there"], b)"#).unwrap();
+        assert_eq!(quoted.pane_options()[0].cmd.as_deref(), Some(r#"echo "hi" \ there"#));
+        assert_eq!(serialize_layout_expr(&quoted), r#"ROW(a[cmd="echo \"hi\" \\ there"], b)"#);
+        let bare = parse_layout_expr("ROW(a[cwd=/tmp, title=logs], b)").unwrap();
+        assert_eq!(bare.pane_options()[0].title.as_deref(), Some("logs"));
+    }
+    #[test] fn attribute_errors() {
+        let message = |expr: &str| parse_spanned(expr).unwrap_err().message;
+        assert_eq!(message("ROW(a[color=red], b)"), "unknown attribute 'color', expected cmd, cwd, title or focus");
+        assert_eq!(message("ROW(a[cmd=\"make], b)"), "string is never closed");
+        assert_eq!(message("ROW(a[cmd=x, cmd=y], b)"), "'cmd' is given more than once");
+        assert_eq!(message("ROW(a[cmd x], b)"), "expected '=' after 'cmd'");
+        assert_eq!(message("ROW(a[focus] junk, b)"), "unexpected 'junk' after a[...]");
+        assert_eq!(message("ROW(a[focus"), "'[' of a is never closed");
+    }
+    #[test] fn windows_expression() {
+        let expr = "WIN(editor: ROW(pm[focus] 30%, worker 70%), logs: tail)";
+        let windows = parse_windows_expr(expr).unwrap();
+        assert_eq!(windows.len(), 2);
+        assert_eq!((windows[1].index, windows[1].name.as_str()), (1, "logs"));
+        assert_eq!(windows[1].layout, LayoutNode::pane("tail"));
+        assert_eq!(serialize_windows_expr(&windows), expr);
+        assert!(is_windows_expr(expr) && !is_windows_expr("ROW(a, b)"));
+        let single = parse_windows_expr("ROW(a, b)").unwrap();
+        assert_eq!((single.len(), single[0].name.as_str()), (1, ""));
+        assert_eq!(serialize_windows_expr(&single), "ROW(a, b)");
+        assert!(parse_layout_expr(expr).unwrap_err().contains("describes a session's windows"));
+        assert!(parse_layout_expr("ROW(a, WIN(x: b))").unwrap_err().contains("only allowed around"));
+        assert!(parse_windows_expr("WIN(ROW(a, b))").unwrap_err().contains("expected 'name:'"));
+        assert!(parse_windows_expr("WIN(: a)").unwrap_err().contains("no name"));
+        assert!(parse_windows_expr("WIN(x: a").unwrap_err().contains("never closed"));
+    }
+    #[test] fn colons_belong_to_names_outside_window_headers() {
+        let expr = "ROW(pm:lead 30%, w:1[title=\"a:b\"] 70%)";
+        assert_eq!(serialize_layout_expr(&parse_layout_expr(expr).unwrap()), expr);
+        let windows = parse_windows_expr("WIN(main:COL(pm:lead, x), logs: tail:f)").unwrap();
+        assert_eq!((windows[0].name.as_str(), windows[1].name.as_str()), ("main", "logs"));
+        assert_eq!(serialize_layout_expr(&windows[0].layout), "COL(pm:lead, x)");
+        assert_eq!(windows[1].layout, LayoutNode::pane("tail:f"));
+    }
+    #[test] fn validate_windows_and_focus() {
+        let found = validate("WIN(a: ROW(x[focus], y[focus]), a: z[focus])", &|_| true);
+        let messages: Vec<&str> = found.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec!["another pane in this window already has focus", "window 'a' appears more than once"]
+        );
+        assert_eq!(found[0].start, 21);
+    }
+    #[test] fn validate_names_and_empty_entries() {
+        let found = validate("ROW(pm, , ghost, pm)", &|name| name == "pm");
+        let messages: Vec<&str> = found.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec!["empty entry", "'ghost' is not a part in the catalog", "'pm' appears more than once"]
+        );
+        assert!(found.iter().all(|d| d.severity == Severity::Warning));
+        assert_eq!(validate("ROW(a", &|_| true)[0].severity, Severity::Error);
+    }
+    #[test] fn untagged_panes_round_trip() {
+        let pane = |percent| LayoutEntry { node: LayoutNode::pane(""), percent: Some(percent), size: SizeSpec::default() };
+        let node = LayoutNode::Row { children: vec![
+            pane(30),
+            LayoutEntry { node: LayoutNode::Col { children: vec![pane(50), pane(50)] }, percent: Some(70), size: SizeSpec::default() },
+        ] };
+        let s = serialize_layout_expr(&node);
+        assert_eq!(s, "ROW(_ 30%, COL(_ 50%, _ 50%) 70%)");
+        assert_eq!(parse_layout_expr(&s).unwrap(), node);
+        let titled = LayoutNode::Pane { agent: String::new(), options: PaneOptions { title: Some("logs".into()), ..PaneOptions::default() } };
+        assert_eq!(parse_layout_expr(&serialize_layout_expr(&titled)).unwrap(), titled);
+        assert!(validate("ROW(_, _, pm)", &|name| name == "pm").is_empty());
+    }
 }
@@ -4,17 +4,65 @@
 //! (multi-pane layouts), and sessions (complete templates referencing other parts).
 //!
 //! Format: markdown with `##` headings for names.
-//!   - `role: <name>` → Agent
+//!   - `role: <name>` → Agent, optionally with `type: claude|console|ssh`
+//!     and `host: <user@box[:port]>` saying how to start it
 //!   - `ROW(...)` / `COL(...)` → Composition or Session (auto-classified)
+//!   - `WIN(name: ..., ...)` → Session with one window per entry
+//!
+//! `add`, `update`, `remove`, and `rename` edit a registry in place and
+//! `save` writes it back. Everything that is not a part definition — the H1
+//! title, prose, HTML comments, `###` notes — is kept as written.
+//!
+//! The catalog is read in layers (`catalog_layers`): a system file, the
+//! user's, and the project's `.mux/parts.md`. A part defined in a later
+//! layer replaces one of the same name from an earlier layer.
 
-use crate::data::layout_expr::parse_layout_expr;
-use crate::types::session::LayoutNode;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::data::layout_expr::{
+    is_size_token, is_windows_expr, leaf_spans, parse_layout_expr, parse_windows_expr, validate,
+    Diagnostic, Severity, UNNAMED,
+};
+use crate::layout::preview::{render, BoxStyle};
+use crate::types::agent::AgentType;
+use crate::types::config::MuxSettings;
+use crate::types::session::{LayoutNode, SessionSnapshot, WindowSnapshot};
 use crate::types::tiles::{Tile, TileKind};
 
+/// Size of the box diagrams `to_json` attaches for the overlay's catalog zone.
+const PREVIEW_WIDTH: usize = 32;
+const PREVIEW_HEIGHT: usize = 8;
+
+/// Agent part fields; lines starting with these are a part's definition.
+const AGENT_KEYS: &[&str] = &["role:", "type:", "host:"];
+
+/// The system catalog when neither settings nor `MUX_SYSTEM_PARTS_PATH`
+/// name one.
+const SYSTEM_CATALOG: &str = "/etc/muxux/parts.md";
+
+/// The project catalog, relative to `project_root`.
+const PROJECT_CATALOG: &str = ".mux/parts.md";
+
 /// A registry of parsed parts, grouped by kind.
 #[derive(Debug, Clone, Default)]
 pub struct PartRegistry {
     pub parts: Vec<Tile>,
+    /// The markdown the parts came from, for writing edits back.
+    source: Vec<Block>,
+    /// The file each part was read from, for registries built by
+    /// `from_layers`.
+    origins: HashMap<String, PathBuf>,
+}
+
+/// A stretch of parts markdown, kept verbatim.
+#[derive(Debug, Clone)]
+enum Block {
+    /// Text outside any part: what comes before the first `##` heading,
+    /// and each `#` section up to the next part.
+    Text(Vec<String>),
+    /// A `## name` heading and the lines under it, `###` notes included.
+    Part { name: String, lines: Vec<String> },
 }
 
 impl PartRegistry {
@@ -49,27 +97,30 @@ impl PartRegistry {
             }
         }
 
-        // Classify: distinguish compositions from sessions.
-        // A part whose layout leaves are all agent names is a composition.
-        // A part whose layout references other parts is a session.
-        let agent_names: Vec<String> = parts
-            .iter()
-            .filter(|t| t.kind == TileKind::Agent)
-            .map(|t| t.name.clone())
-            .collect();
-        let all_names: Vec<String> = parts.iter().map(|t| t.name.clone()).collect();
+        classify(&mut parts);
+        PartRegistry {
+            parts,
+            source: blocks(input),
+            origins: HashMap::new(),
+        }
+    }
 
-        for tile in &mut parts {
-            if tile.kind == TileKind::Composition {
-                if let Some(ref layout) = tile.layout {
-                    if layout_references_parts(layout, &agent_names, &all_names) {
-                        tile.kind = TileKind::Session;
-                    }
+    /// Load each catalog in `paths` in turn. A part from a later file
+    /// replaces an earlier one of the same name; missing files are skipped.
+    /// Layouts may use parts from any layer.
+    pub fn from_layers(paths: &[PathBuf]) -> PartRegistry {
+        let mut registry = PartRegistry::default();
+        for path in paths {
+            for tile in PartRegistry::from_file(path).parts {
+                registry.origins.insert(tile.name.clone(), path.clone());
+                match registry.parts.iter_mut().find(|t| t.name == tile.name) {
+                    Some(existing) => *existing = tile,
+                    None => registry.parts.push(tile),
                 }
             }
         }
-
-        PartRegistry { parts }
+        classify(&mut registry.parts);
+        registry
     }
 
     /// Load parts from a file path. Returns empty registry if file doesn't exist.
@@ -80,14 +131,159 @@ impl PartRegistry {
         }
     }
 
-    /// Load from the default location: `~/.config/skd/skd-library/parts.md`.
-    pub fn from_default_path() -> PartRegistry {
-        if let Some(home) = dirs_next_home() {
-            let path = home.join(".config/skd/skd-library/parts.md");
-            Self::from_file(&path)
-        } else {
-            PartRegistry::default()
+    /// The default user catalog: `~/.config/skd/skd-library/parts.md`.
+    pub fn default_path() -> Option<std::path::PathBuf> {
+        dirs_next_home().map(|home| home.join(".config/skd/skd-library/parts.md"))
+    }
+
+    /// The layered catalog `settings` describe; see `catalog_layers`.
+    pub fn from_settings(settings: &MuxSettings) -> PartRegistry {
+        Self::from_layers(&catalog_layers(settings))
+    }
+
+    /// The file part `name` was read from, when loaded by `from_layers`.
+    pub fn origin(&self, name: &str) -> Option<&Path> {
+        self.origins.get(name).map(|p| p.as_path())
+    }
+
+    /// Add a part. `body` is what goes under its heading: `role:` (and
+    /// `type:`, `host:`) lines, or a layout expression. Returns the layout's
+    /// warnings, e.g. leaves that are not parts.
+    pub fn add(&mut self, name: &str, body: &str) -> Result<Vec<Diagnostic>, String> {
+        check_name(name)?;
+        if self.section(name).is_some() {
+            return Err(format!("part '{}' already exists", name));
+        }
+        let (lines, warnings) = self.definition(name, body)?;
+        if let Some(last) = self.source.last_mut() {
+            let lines = match last {
+                Block::Text(lines) | Block::Part { lines, .. } => lines,
+            };
+            if lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                lines.push(String::new());
+            }
+        }
+        self.source.push(Block::Part {
+            name: name.to_string(),
+            lines,
+        });
+        self.reload();
+        Ok(warnings)
+    }
+
+    /// Replace a part's definition with `body`, as for `add`. Notes and
+    /// other text under its heading are kept.
+    pub fn update(&mut self, name: &str, body: &str) -> Result<Vec<Diagnostic>, String> {
+        let index = self
+            .section(name)
+            .ok_or_else(|| format!("part '{}' not found", name))?;
+        let (new_lines, warnings) = self.definition(name, body)?;
+        if let Block::Part { lines, .. } = &mut self.source[index] {
+            let old = definition_lines(lines);
+            let at = old.first().copied().or(first_body_line(lines)).unwrap_or(0);
+            for &i in old.iter().rev() {
+                lines.remove(i);
+            }
+            lines.splice(at..at, new_lines);
         }
+        self.reload();
+        Ok(warnings)
+    }
+
+    /// Delete a part. Refuses while another part's layout uses it.
+    pub fn remove(&mut self, name: &str) -> Result<(), String> {
+        let index = self
+            .section(name)
+            .ok_or_else(|| format!("part '{}' not found", name))?;
+        let users = self.users(name);
+        if !users.is_empty() {
+            return Err(format!(
+                "part '{}' is used by {}",
+                name,
+                users
+                    .iter()
+                    .map(|u| format!("'{}'", u))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        self.source.remove(index);
+        self.reload();
+        Ok(())
+    }
+
+    /// Rename a part, and every use of it in other parts' layouts. Returns
+    /// the number of parts whose layouts changed.
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<usize, String> {
+        check_name(to)?;
+        let index = self
+            .section(from)
+            .ok_or_else(|| format!("part '{}' not found", from))?;
+        if self.section(to).is_some() {
+            return Err(format!("part '{}' already exists", to));
+        }
+        let mut updated = 0;
+        for (i, block) in self.source.iter_mut().enumerate() {
+            let Block::Part { name, lines } = block else {
+                continue;
+            };
+            if i == index {
+                *name = to.to_string();
+                continue;
+            }
+            let Some(first) = first_body_line(lines) else {
+                continue;
+            };
+            if let Some(renamed) = rename_in_expr(&lines[first], from, to) {
+                lines[first] = renamed;
+                updated += 1;
+            }
+        }
+        self.reload();
+        Ok(updated)
+    }
+
+    /// The registry as parts markdown, with untouched text as it was read.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        for block in &self.source {
+            let lines = match block {
+                Block::Text(lines) => lines,
+                Block::Part { name, lines } => {
+                    out.push_str("## ");
+                    out.push_str(name);
+                    out.push('\n');
+                    lines
+                }
+            };
+            for line in lines {
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    /// Write `to_markdown` to `path` through a temporary file in the same
+    /// directory, renamed over the old file, so readers never see half a
+    /// catalog.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let dir = path.parent().filter(|d| !d.as_os_str().is_empty());
+        if let Some(dir) = dir {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("cannot create {}: {}", dir.display(), e))?;
+        }
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .ok_or_else(|| format!("{} is not a file path", path.display()))?;
+        let temp = path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()));
+        std::fs::write(&temp, self.to_markdown())
+            .map_err(|e| format!("cannot write {}: {}", temp.display(), e))?;
+        std::fs::rename(&temp, path).map_err(|e| {
+            let _ = std::fs::remove_file(&temp);
+            format!("cannot replace {}: {}", path.display(), e)
+        })
     }
 
     /// Find a part by name.
@@ -95,6 +291,11 @@ impl PartRegistry {
         self.parts.iter().find(|t| t.name == name)
     }
 
+    /// The command that starts the named agent part, if it declares one.
+    pub fn launch_command(&self, name: &str) -> Option<String> {
+        self.get(name)?.launch_command()
+    }
+
     /// Get all parts of a given kind.
     pub fn by_kind(&self, kind: TileKind) -> Vec<&Tile> {
         self.parts.iter().filter(|t| t.kind == kind).collect()
@@ -105,30 +306,67 @@ impl PartRegistry {
         serde_json::json!({
             "agents": self.by_kind(TileKind::Agent)
                 .iter()
-                .map(|t| serde_json::json!({ "name": t.name, "role": t.role }))
+                .map(|t| serde_json::json!({
+                    "name": t.name,
+                    "role": t.role,
+                    "source": self.origin_text(&t.name),
+                }))
                 .collect::<Vec<_>>(),
             "compositions": self.by_kind(TileKind::Composition)
                 .iter()
-                .map(|t| serde_json::json!({ "name": t.name }))
+                .map(|t| serde_json::json!({
+                    "name": t.name,
+                    "preview": self.preview(&t.name),
+                    "source": self.origin_text(&t.name),
+                }))
                 .collect::<Vec<_>>(),
             "sessions": self.by_kind(TileKind::Session)
                 .iter()
-                .map(|t| serde_json::json!({ "name": t.name }))
+                .map(|t| serde_json::json!({
+                    "name": t.name,
+                    "preview": self.preview(&t.name),
+                    "source": self.origin_text(&t.name),
+                }))
                 .collect::<Vec<_>>(),
         })
         .to_string()
     }
 
+    fn origin_text(&self, name: &str) -> Option<String> {
+        self.origin(name).map(|p| p.display().to_string())
+    }
+
+    /// Small box diagrams of a part's expanded windows, for catalog
+    /// listings. Named windows get their name above their diagram.
+    pub fn preview(&self, name: &str) -> Option<String> {
+        let diagrams: Vec<String> = self
+            .expand_windows(name)?
+            .iter()
+            .map(|w| {
+                let diagram = render(&w.layout, PREVIEW_WIDTH, PREVIEW_HEIGHT, BoxStyle::Unicode);
+                if w.name.is_empty() {
+                    diagram
+                } else {
+                    format!("{}:\n{}", w.name, diagram)
+                }
+            })
+            .collect();
+        Some(diagrams.join("\n"))
+    }
+
     /// Recursively expand a part name into a fully resolved LayoutNode.
     ///
     /// Agent names become Pane nodes; compositions and sessions have their
-    /// layout expressions expanded recursively.
+    /// layout expressions expanded recursively. A `WIN(...)` part with more
+    /// than one window has no single layout and gives `None`; see
+    /// `expand_windows`.
     pub fn expand(&self, name: &str) -> Option<LayoutNode> {
         let tile = self.get(name)?;
+        if tile.windows.len() > 1 {
+            return None;
+        }
         match tile.kind {
-            TileKind::Agent => Some(LayoutNode::Pane {
-                agent: name.to_string(),
-            }),
+            TileKind::Agent => Some(LayoutNode::pane(name)),
             TileKind::Composition | TileKind::Session => {
                 let layout = tile.layout.as_ref()?;
                 Some(self.expand_node(layout))
@@ -136,10 +374,134 @@ impl PartRegistry {
         }
     }
 
-    /// Recursively expand layout references in a LayoutNode.
+    /// Every window of a part, expanded: the windows of a `WIN(...)` part,
+    /// or any other part's layout as one unnamed window.
+    pub fn expand_windows(&self, name: &str) -> Option<Vec<WindowSnapshot>> {
+        let tile = self.get(name)?;
+        if tile.windows.is_empty() {
+            return Some(vec![WindowSnapshot {
+                index: 0,
+                name: String::new(),
+                layout: self.expand(name)?,
+                tmux_layout: None,
+            }]);
+        }
+        let windows = tile
+            .windows
+            .iter()
+            .map(|w| WindowSnapshot {
+                layout: self.expand_node(&w.layout),
+                ..w.clone()
+            })
+            .collect();
+        Some(windows)
+    }
+
+    /// Build a session snapshot from a part.
+    ///
+    /// A `WIN(...)` part keeps its own windows. Another session part becomes
+    /// one window per top-level child of its layout, named after the part
+    /// the child references (`w<i>` for inline layouts). Any other part
+    /// becomes a single window named after itself.
+    pub fn session_snapshot(&self, name: &str) -> Option<SessionSnapshot> {
+        let tile = self.get(name)?;
+        if !tile.windows.is_empty() {
+            return Some(SessionSnapshot {
+                session: name.to_string(),
+                windows: self.expand_windows(name)?,
+                timestamp_ms: 0,
+            });
+        }
+        let children = match (&tile.kind, tile.layout.as_ref()) {
+            (TileKind::Session, Some(LayoutNode::Row { children }))
+            | (TileKind::Session, Some(LayoutNode::Col { children })) => children,
+            _ => {
+                return Some(SessionSnapshot {
+                    session: name.to_string(),
+                    windows: vec![WindowSnapshot {
+                        index: 0,
+                        name: name.to_string(),
+                        layout: self.expand(name)?,
+                        tmux_layout: None,
+                    }],
+                    timestamp_ms: 0,
+                })
+            }
+        };
+        let windows = children
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| WindowSnapshot {
+                index: i as u32,
+                name: match &entry.node {
+                    LayoutNode::Pane { agent, .. } => agent.clone(),
+                    _ => format!("w{}", i),
+                },
+                layout: self.expand_node(&entry.node),
+                tmux_layout: None,
+            })
+            .collect();
+        Some(SessionSnapshot {
+            session: name.to_string(),
+            windows,
+            timestamp_ms: 0,
+        })
+    }
+
+    /// Index of the `## name` block, whether or not its body parsed.
+    fn section(&self, name: &str) -> Option<usize> {
+        self.source
+            .iter()
+            .position(|b| matches!(b, Block::Part { name: n, .. } if n == name))
+    }
+
+    /// Parts other than `name` whose layouts have a leaf called `name`.
+    fn users(&self, name: &str) -> Vec<String> {
+        self.parts
+            .iter()
+            .filter(|t| t.name != name)
+            .filter(|t| {
+                let mut layouts: Vec<&LayoutNode> = t.windows.iter().map(|w| &w.layout).collect();
+                layouts.extend(t.layout.as_ref());
+                layouts.iter().any(|l| has_leaf(l, name))
+            })
+            .map(|t| t.name.clone())
+            .collect()
+    }
+
+    /// Check `body` as the definition of part `name`: its lines, trimmed,
+    /// and the layout's warnings.
+    fn definition(&self, name: &str, body: &str) -> Result<(Vec<String>, Vec<Diagnostic>), String> {
+        let lines: Vec<String> = body
+            .lines()
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty())
+            .collect();
+        let mut warnings = Vec::new();
+        if let Some(line) = lines.first().filter(|l| is_layout_line(l)) {
+            warnings = validate(line, &|name| self.get(name).is_some());
+            if let Some(error) = warnings.iter().find(|d| d.severity == Severity::Error) {
+                return Err(error.render(line));
+            }
+        }
+        if parse_part_body(name, &lines.join("\n")).is_none() {
+            return Err(
+                "a part is 'role: <name>' lines or a ROW(...), COL(...) or WIN(...) expression"
+                    .into(),
+            );
+        }
+        Ok((lines, warnings))
+    }
+
+    fn reload(&mut self) {
+        *self = PartRegistry::from_markdown(&self.to_markdown());
+    }
+
+    /// Recursively expand layout references in a LayoutNode. Attributes on
+    /// a leaf that names a composition are dropped with the leaf.
     fn expand_node(&self, node: &LayoutNode) -> LayoutNode {
         match node {
-            LayoutNode::Pane { agent } => {
+            LayoutNode::Pane { agent, .. } => {
                 // If this pane name is a known composition/session, expand it
                 if let Some(tile) = self.get(agent) {
                     if tile.layout.is_some() {
@@ -149,9 +511,7 @@ impl PartRegistry {
                     }
                 }
                 // Otherwise keep as pane (it's a role name)
-                LayoutNode::Pane {
-                    agent: agent.clone(),
-                }
+                node.clone()
             }
             LayoutNode::Row { children } => LayoutNode::Row {
                 children: children
@@ -159,6 +519,7 @@ impl PartRegistry {
                     .map(|e| crate::types::session::LayoutEntry {
                         node: self.expand_node(&e.node),
                         percent: e.percent,
+                        size: e.size,
                     })
                     .collect(),
             },
@@ -168,6 +529,7 @@ impl PartRegistry {
                     .map(|e| crate::types::session::LayoutEntry {
                         node: self.expand_node(&e.node),
                         percent: e.percent,
+                        size: e.size,
                     })
                     .collect(),
             },
@@ -185,16 +547,37 @@ fn parse_part_body(name: &str, body: &str) -> Option<Tile> {
     // Check for `role: <name>` → Agent
     if let Some(role_line) = trimmed.lines().find(|l| l.trim().starts_with("role:")) {
         let role = role_line.trim().strip_prefix("role:")?.trim().to_string();
+        let field = |key: &str| {
+            trimmed
+                .lines()
+                .find_map(|l| l.trim().strip_prefix(key).map(|v| v.trim().to_string()))
+                .filter(|v| !v.is_empty())
+        };
         return Some(Tile {
             name: name.to_string(),
             kind: TileKind::Agent,
             role: Some(role),
             layout: None,
+            agent_type: field("type:").and_then(|t| AgentType::parse(&t)),
+            host: field("host:"),
+            windows: Vec::new(),
         });
     }
 
-    // Try parsing as layout expression — must start with ROW( or COL(
+    // Try parsing as layout expression — must start with ROW(, COL( or WIN(
     let first_non_empty = trimmed.lines().find(|l| !l.trim().is_empty())?;
+    if is_windows_expr(first_non_empty) {
+        let windows = parse_windows_expr(first_non_empty.trim()).ok()?;
+        return Some(Tile {
+            name: name.to_string(),
+            kind: TileKind::Session,
+            role: None,
+            layout: windows.first().map(|w| w.layout.clone()),
+            agent_type: None,
+            host: None,
+            windows,
+        });
+    }
     let upper = first_non_empty.trim().to_uppercase();
     if !upper.starts_with("ROW(") && !upper.starts_with("COL(") {
         return None; // not a valid part definition
@@ -205,11 +588,171 @@ fn parse_part_body(name: &str, body: &str) -> Option<Tile> {
             kind: TileKind::Composition, // may be reclassified to Session later
             role: None,
             layout: Some(layout),
+            agent_type: None,
+            host: None,
+            windows: Vec::new(),
         }),
         Err(_) => None, // unparseable body — skip
     }
 }
 
+/// The catalog files to layer, lowest precedence first:
+///
+/// 1. system: `settings.system_parts_path`, else `$MUX_SYSTEM_PARTS_PATH`,
+///    else `/etc/muxux/parts.md`
+/// 2. user: `user_catalog_path`
+/// 3. project: `<project_root>/.mux/parts.md`, when a project root is set
+pub fn catalog_layers(settings: &MuxSettings) -> Vec<PathBuf> {
+    let system = setting_or_env(&settings.system_parts_path, "MUX_SYSTEM_PARTS_PATH")
+        .unwrap_or_else(|| PathBuf::from(SYSTEM_CATALOG));
+    let mut layers = vec![system];
+    layers.extend(user_catalog_path(settings));
+    if !settings.project_root.is_empty() {
+        layers.push(Path::new(&settings.project_root).join(PROJECT_CATALOG));
+    }
+    layers
+}
+
+/// The user's catalog, which `parts add` and friends edit by default:
+/// `settings.parts_path`, else `$MUX_PARTS_PATH`, else
+/// `PartRegistry::default_path`.
+pub fn user_catalog_path(settings: &MuxSettings) -> Option<PathBuf> {
+    setting_or_env(&settings.parts_path, "MUX_PARTS_PATH").or_else(PartRegistry::default_path)
+}
+
+fn setting_or_env(setting: &str, var: &str) -> Option<PathBuf> {
+    if !setting.is_empty() {
+        return Some(PathBuf::from(setting));
+    }
+    std::env::var(var)
+        .ok()
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Distinguish compositions from sessions. A part whose layout leaves are
+/// all agent names is a composition; one whose layout references other
+/// parts is a session. `WIN(...)` parts are always sessions.
+fn classify(parts: &mut [Tile]) {
+    let agent_names: Vec<String> = parts
+        .iter()
+        .filter(|t| t.kind == TileKind::Agent)
+        .map(|t| t.name.clone())
+        .collect();
+    let all_names: Vec<String> = parts.iter().map(|t| t.name.clone()).collect();
+
+    for tile in parts.iter_mut() {
+        if tile.kind == TileKind::Agent || !tile.windows.is_empty() {
+            continue;
+        }
+        tile.kind = match &tile.layout {
+            Some(layout) if layout_references_parts(layout, &agent_names, &all_names) => {
+                TileKind::Session
+            }
+            _ => TileKind::Composition,
+        };
+    }
+}
+
+/// Split parts markdown into blocks, in order.
+fn blocks(input: &str) -> Vec<Block> {
+    let mut blocks: Vec<Block> = Vec::new();
+    for line in input.lines() {
+        if let Some(heading) = line.strip_prefix("## ") {
+            blocks.push(Block::Part {
+                name: heading.trim().to_string(),
+                lines: Vec::new(),
+            });
+            continue;
+        }
+        if line.starts_with("# ") || blocks.is_empty() {
+            blocks.push(Block::Text(Vec::new()));
+        }
+        if let Some(Block::Text(lines) | Block::Part { lines, .. }) = blocks.last_mut() {
+            lines.push(line.to_string());
+        }
+    }
+    blocks
+}
+
+/// Whether `line` starts a ROW, COL, or WIN expression.
+fn is_layout_line(line: &str) -> bool {
+    let upper = line.trim().to_uppercase();
+    ["ROW", "COL", "WIN"].iter().any(|k| {
+        upper
+            .strip_prefix(k)
+            .is_some_and(|r| r.trim_start().starts_with('('))
+    })
+}
+
+/// Indices of the lines of a part body that define it: agent fields, or the
+/// layout expression on its first non-blank line.
+fn definition_lines(lines: &[String]) -> Vec<usize> {
+    let first = first_body_line(lines);
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(i, l)| {
+            let l = l.trim();
+            AGENT_KEYS.iter().any(|k| l.starts_with(k)) || (Some(*i) == first && is_layout_line(l))
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Index of the first line of a part body that is not blank or a `###` note.
+fn first_body_line(lines: &[String]) -> Option<usize> {
+    lines
+        .iter()
+        .position(|l| !l.trim().is_empty() && !l.starts_with("### "))
+}
+
+/// Names a layout expression can refer to: letters, digits, '-', '_' and
+/// '.', and not something the expression would read as a size or as the
+/// unnamed pane `_`.
+fn check_name(name: &str) -> Result<(), String> {
+    let good = |c: char| c.is_alphanumeric() || "-_.".contains(c);
+    if name.is_empty() || !name.chars().all(good) {
+        return Err(format!(
+            "'{}' is not a part name: use letters, digits, '-', '_' or '.'",
+            name
+        ));
+    }
+    if name == UNNAMED || is_size_token(name) {
+        let reads_as = if name == UNNAMED { "a pane without an agent" } else { "a size" };
+        return Err(format!("'{}' is not a part name: layouts read it as {}", name, reads_as));
+    }
+    Ok(())
+}
+
+fn has_leaf(node: &LayoutNode, name: &str) -> bool {
+    match node {
+        LayoutNode::Pane { agent, .. } => agent == name,
+        LayoutNode::Row { children } | LayoutNode::Col { children } => {
+            children.iter().any(|e| has_leaf(&e.node, name))
+        }
+    }
+}
+
+/// `line` with leaves named `from` renamed, if it is a layout expression
+/// that uses `from`. Only the names change; spacing, sizes, and attributes
+/// stay exactly as written.
+fn rename_in_expr(line: &str, from: &str, to: &str) -> Option<String> {
+    if !is_layout_line(line) {
+        return None;
+    }
+    let spans = leaf_spans(line).ok()?;
+    let mut renamed = line.to_string();
+    let mut found = false;
+    for (name, start, end) in spans.iter().rev() {
+        if name == from {
+            renamed.replace_range(start..end, to);
+            found = true;
+        }
+    }
+    found.then_some(renamed)
+}
+
 /// Check if a layout's leaf names reference known parts (not just agents).
 fn layout_references_parts(
     node: &LayoutNode,
@@ -217,7 +760,7 @@ fn layout_references_parts(
     all_names: &[String],
 ) -> bool {
     match node {
-        LayoutNode::Pane { agent } => {
+        LayoutNode::Pane { agent, .. } => {
             // If the leaf is a known part name but NOT an agent, it's a part reference
             all_names.contains(agent) && !agent_names.contains(agent)
         }
@@ -227,6 +770,70 @@ fn layout_references_parts(
     }
 }
 
+/// A problem in one part's layout expression, from `check_markdown`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartIssue {
+    pub part: String,
+    /// 1-based line of the expression in the parts file.
+    pub line: usize,
+    /// The expression's line, as written.
+    pub source: String,
+    pub diagnostic: Diagnostic,
+}
+
+impl PartIssue {
+    /// `file:line:column: warning: <message> (part 'x')` and a caret snippet.
+    pub fn render(&self, file: &str) -> String {
+        let (_, column) = self.diagnostic.line_column(&self.source);
+        format!(
+            "{}:{}:{}: {}: {} (part '{}')\n{}",
+            file,
+            self.line,
+            column,
+            self.diagnostic.severity,
+            self.diagnostic.message,
+            self.part,
+            self.diagnostic.snippet(&self.source)
+        )
+    }
+}
+
+/// Validate the layout expression of every part in parts markdown, with
+/// leaf names checked against the parts it defines and those in `others`,
+/// such as the other catalog layers.
+pub fn check_markdown(input: &str, others: &PartRegistry) -> Vec<PartIssue> {
+    let registry = PartRegistry::from_markdown(input);
+    let mut issues = Vec::new();
+    let mut section: Option<(String, bool)> = None;
+    for (i, line) in input.lines().enumerate() {
+        if let Some(heading) = line.strip_prefix("## ") {
+            section = Some((heading.trim().to_string(), false));
+            continue;
+        }
+        if line.starts_with("# ") || line.starts_with("### ") || line.trim().is_empty() {
+            continue;
+        }
+        // Only the first line of a section can hold its layout.
+        let Some((part, seen)) = section.as_mut().filter(|(_, seen)| !*seen) else {
+            continue;
+        };
+        *seen = true;
+        if !is_layout_line(line) {
+            continue;
+        }
+        let known = |name: &str| registry.get(name).or_else(|| others.get(name)).is_some();
+        for diagnostic in validate(line, &known) {
+            issues.push(PartIssue {
+                part: part.clone(),
+                line: i + 1,
+                source: line.to_string(),
+                diagnostic,
+            });
+        }
+    }
+    issues
+}
+
 /// Get home directory (pure function to avoid platform dependency).
 fn dirs_next_home() -> Option<std::path::PathBuf> {
     std::env::var("HOME").ok().map(std::path::PathBuf::from)
@@ -241,6 +848,7 @@ mod tests {
 
 ## pm
 role: pm
+type: claude
 
 ## worker
 role: worker
@@ -250,6 +858,9 @@ role: curator
 
 ## remote
 role: remote
+type: ssh
+host: me@gpu1
+
 
 ## rig
 COL(remote 70%, worker 30%)
@@ -326,7 +937,7 @@ COL(rig 80%, curator 20%)
                 assert_eq!(children.len(), 2);
                 // Second child should be a Pane referencing dev-pair
                 match &children[1].node {
-                    LayoutNode::Pane { agent } => assert_eq!(agent, "dev-pair"),
+                    LayoutNode::Pane { agent, .. } => assert_eq!(agent, "dev-pair"),
                     _ => panic!("second child should be a Pane reference"),
                 }
             }
@@ -341,6 +952,9 @@ COL(rig 80%, curator 20%)
         assert_eq!(json["agents"].as_array().unwrap().len(), 4);
         assert_eq!(json["compositions"].as_array().unwrap().len(), 2);
         assert_eq!(json["sessions"].as_array().unwrap().len(), 2);
+        let preview = json["compositions"][0]["preview"].as_str().unwrap();
+        assert_eq!(preview.lines().count(), PREVIEW_HEIGHT);
+        assert!(preview.starts_with('┌'));
     }
 
     #[test]
@@ -348,7 +962,7 @@ COL(rig 80%, curator 20%)
         let reg = PartRegistry::from_markdown(SAMPLE_PARTS);
         let node = reg.expand("pm").unwrap();
         match node {
-            LayoutNode::Pane { agent } => assert_eq!(agent, "pm"),
+            LayoutNode::Pane { agent, .. } => assert_eq!(agent, "pm"),
             _ => panic!("agent should expand to Pane"),
         }
     }
@@ -362,7 +976,7 @@ COL(rig 80%, curator 20%)
                 assert_eq!(children.len(), 2);
                 // Leaves should still be agent names (remote, worker)
                 match &children[0].node {
-                    LayoutNode::Pane { agent } => assert_eq!(agent, "remote"),
+                    LayoutNode::Pane { agent, .. } => assert_eq!(agent, "remote"),
                     _ => panic!("expected Pane"),
                 }
             }
@@ -381,7 +995,7 @@ COL(rig 80%, curator 20%)
             LayoutNode::Col { children } => {
                 assert_eq!(children.len(), 2);
                 match &children[0].node {
-                    LayoutNode::Pane { agent } => assert_eq!(agent, "pm"),
+                    LayoutNode::Pane { agent, .. } => assert_eq!(agent, "pm"),
                     _ => panic!("first child should be pm pane"),
                 }
                 match &children[1].node {
@@ -395,6 +1009,53 @@ COL(rig 80%, curator 20%)
         }
     }
 
+    #[test]
+    fn win_part_keeps_its_windows() {
+        let input = format!(
+            "{}\n## studio\nWIN(code: dev-pair, ops: COL(pm[focus], remote[cwd=/srv]))\n",
+            SAMPLE_PARTS
+        );
+        let reg = PartRegistry::from_markdown(&input);
+        let tile = reg.get("studio").unwrap();
+        assert_eq!(tile.kind, TileKind::Session);
+        assert_eq!(tile.windows.len(), 2);
+        let snap = reg.session_snapshot("studio").unwrap();
+        let names: Vec<&str> = snap.windows.iter().map(|w| w.name.as_str()).collect();
+        assert_eq!(names, vec!["code", "ops"]);
+        assert!(matches!(snap.windows[0].layout, LayoutNode::Row { .. }));
+        let options = snap.windows[1].layout.pane_options();
+        assert!(options[0].focus);
+        assert_eq!(options[1].cwd.as_deref(), Some("/srv"));
+        assert!(reg.expand("studio").is_none());
+        let preview = reg.preview("studio").unwrap();
+        assert!(preview.starts_with("code:\n") && preview.contains("\nops:\n"));
+    }
+
+    #[test]
+    fn session_part_becomes_windows() {
+        let reg = PartRegistry::from_markdown(SAMPLE_PARTS);
+        let snap = reg.session_snapshot("dev-station").unwrap();
+        let names: Vec<&str> = snap.windows.iter().map(|w| w.name.as_str()).collect();
+        assert_eq!(names, vec!["pm", "dev-pair"]);
+        assert_eq!(snap.windows[1].index, 1);
+        assert!(matches!(snap.windows[1].layout, LayoutNode::Row { .. }));
+
+        let single = reg.session_snapshot("rig").unwrap();
+        assert_eq!(single.windows.len(), 1);
+        assert_eq!(single.windows[0].name, "rig");
+        assert!(reg.session_snapshot("missing").is_none());
+    }
+
+    #[test]
+    fn agent_launch_fields() {
+        let reg = PartRegistry::from_markdown(SAMPLE_PARTS);
+        assert_eq!(reg.get("pm").unwrap().agent_type, Some(AgentType::Claude));
+        assert_eq!(reg.launch_command("pm").as_deref(), Some("claude"));
+        assert_eq!(reg.launch_command("remote").as_deref(), Some("ssh me@gpu1"));
+        assert_eq!(reg.launch_command("worker"), None);
+        assert_eq!(reg.launch_command("rig"), None);
+    }
+
     #[test]
     fn get_nonexistent() {
         let reg = PartRegistry::from_markdown(SAMPLE_PARTS);
@@ -427,4 +1088,177 @@ COL(rig 80%, curator 20%)
         let reg = PartRegistry::from_markdown(SAMPLE_PARTS);
         assert_eq!(reg.parts.len(), 8); // 4 agents + 2 compositions + 2 sessions
     }
+
+    #[test]
+    fn check_reports_file_positions() {
+        let input = "## pm\nrole: pm\n\n## pair\nROW(pm 30%, ghost 60%)\n\n## bad\nCOL(pm,\n";
+        let issues = check_markdown(input, &PartRegistry::default());
+        assert_eq!(issues.len(), 3);
+        assert_eq!((issues[0].part.as_str(), issues[0].line), ("pair", 5));
+        assert!(issues[0].diagnostic.message.contains("add up to 90%"));
+        assert_eq!(
+            issues[1].render("parts.md"),
+            "parts.md:5:13: warning: 'ghost' is not a part in the catalog (part 'pair')\n  \
+             ROW(pm 30%, ghost 60%)\n              ^^^^^"
+        );
+        assert_eq!(issues[2].diagnostic.severity, crate::data::layout_expr::Severity::Error);
+        let sample = check_markdown(SAMPLE_PARTS, &PartRegistry::default());
+        assert!(sample.iter().all(|i| i.part == "dev-pair"));
+    }
+
+    const NOTED_PARTS: &str = "# Parts\n\n<!-- team agents -->\n\n\
+        ## pm\n### notes\nruns standups\nrole: pm\ntype: claude\n\n\
+        # Layouts\n\n## pair\nROW(pm, pm)\n";
+
+    #[test]
+    fn untouched_markdown_round_trips() {
+        let reg = PartRegistry::from_markdown(SAMPLE_PARTS);
+        assert_eq!(reg.to_markdown(), SAMPLE_PARTS);
+        let reg = PartRegistry::from_markdown(NOTED_PARTS);
+        assert_eq!(reg.to_markdown(), NOTED_PARTS);
+    }
+
+    #[test]
+    fn add_appends_a_section() {
+        let mut reg = PartRegistry::from_markdown(NOTED_PARTS);
+        let warnings = reg.add("duo", "ROW(pm, ghost)").unwrap();
+        assert_eq!(warnings[0].message, "'ghost' is not a part in the catalog");
+        reg.add("qa", "role: qa\ntype: codex").unwrap();
+        assert!(reg.to_markdown().ends_with(
+            "## pair\nROW(pm, pm)\n\n## duo\nROW(pm, ghost)\n\n## qa\nrole: qa\ntype: codex\n"
+        ));
+        assert_eq!(reg.get("duo").unwrap().kind, TileKind::Composition);
+        assert_eq!(reg.get("qa").unwrap().role.as_deref(), Some("qa"));
+    }
+
+    #[test]
+    fn add_rejects_bad_parts() {
+        let mut reg = PartRegistry::from_markdown(SAMPLE_PARTS);
+        assert_eq!(reg.add("pm", "role: pm").unwrap_err(), "part 'pm' already exists");
+        assert!(reg.add("two words", "role: x").is_err());
+        assert!(reg.add("a:b", "role: x").is_err());
+        for bad in ["50%", "a/b", "*", "40c", "12r", "_", "x!"] {
+            let err = reg.add(bad, "role: x").unwrap_err();
+            assert!(err.starts_with(&format!("'{}' is not a part name", bad)), "{}", err);
+        }
+        assert!(reg.rename("pm", "50%").is_err());
+        assert!(reg.add("new", "ROW(pm,").unwrap_err().starts_with("error: "));
+        assert!(reg.add("new", "just text").is_err());
+        assert_eq!(reg.to_markdown(), SAMPLE_PARTS);
+    }
+
+    #[test]
+    fn update_keeps_notes() {
+        let mut reg = PartRegistry::from_markdown(NOTED_PARTS);
+        reg.update("pm", "role: lead\nhost: me@box").unwrap();
+        reg.update("pair", "COL(pm 40%, pm 60%)").unwrap();
+        assert_eq!(
+            reg.to_markdown(),
+            NOTED_PARTS
+                .replace("role: pm\ntype: claude", "role: lead\nhost: me@box")
+                .replace("ROW(pm, pm)", "COL(pm 40%, pm 60%)")
+        );
+        assert_eq!(reg.get("pm").unwrap().host.as_deref(), Some("me@box"));
+        assert!(reg.update("ghost", "role: x").is_err());
+    }
+
+    #[test]
+    fn remove_refuses_parts_in_use() {
+        let mut reg = PartRegistry::from_markdown(SAMPLE_PARTS);
+        assert_eq!(
+            reg.remove("worker").unwrap_err(),
+            "part 'worker' is used by 'rig', 'dev-pair'"
+        );
+        reg.remove("dev-station").unwrap();
+        assert!(reg.get("dev-station").is_none());
+        assert!(!reg.to_markdown().contains("dev-station"));
+        assert!(reg.remove("dev-station").is_err());
+    }
+
+    #[test]
+    fn rename_rewrites_references() {
+        let mut reg = PartRegistry::from_markdown(SAMPLE_PARTS);
+        assert_eq!(reg.rename("worker", "coder").unwrap(), 2);
+        let text = reg.to_markdown();
+        assert!(text.contains("## coder\nrole: worker\n"));
+        assert!(text.contains("## rig\nCOL(remote 70%, coder 30%)\n"));
+        assert!(text.contains("## dev-pair\nROW(coder, coder)\n"));
+        assert_eq!(reg.get("dev-station").unwrap().kind, TileKind::Session);
+        assert!(reg.rename("coder", "pm").is_err());
+        assert!(reg.rename("ghost", "spirit").is_err());
+    }
+
+    #[test]
+    fn rename_keeps_the_rest_of_the_line() {
+        let line = "  WIN( main :row(worker  33%,COL( pm , worker[cmd=\"a, b\"] >=5r) * ), logs: tail)";
+        let input = format!("## worker\nrole: worker\n\n## pm\nrole: pm\n\n## desk\n{}\n", line);
+        let mut reg = PartRegistry::from_markdown(&input);
+        assert_eq!(reg.rename("worker", "coder").unwrap(), 1);
+        let expected = line.replace("worker", "coder");
+        assert!(reg.to_markdown().contains(&format!("## desk\n{}\n", expected)));
+    }
+
+    #[test]
+    fn later_layers_override_earlier_ones() {
+        let dir = std::env::temp_dir().join(format!("muxux-layers-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let system = dir.join("system.md");
+        let user = dir.join("user.md");
+        let project = dir.join("project.md");
+        std::fs::write(&system, "## pm\nrole: pm\n\n## pair\nROW(pm, pm)\n").unwrap();
+        std::fs::write(&user, "## pm\nrole: lead\ntype: claude\n").unwrap();
+        std::fs::write(&project, "## station\nCOL(pair 60%, pm 40%)\n").unwrap();
+        let layers = [system.clone(), dir.join("missing.md"), user.clone(), project.clone()];
+        let reg = PartRegistry::from_layers(&layers);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(reg.parts.len(), 3);
+        assert_eq!(reg.get("pm").unwrap().role.as_deref(), Some("lead"));
+        assert_eq!(reg.origin("pm"), Some(user.as_path()));
+        assert_eq!(reg.origin("pair"), Some(system.as_path()));
+        // A layout may use parts from another layer.
+        assert_eq!(reg.get("station").unwrap().kind, TileKind::Session);
+        let json: serde_json::Value = serde_json::from_str(&reg.to_json()).unwrap();
+        assert_eq!(json["sessions"][0]["source"], project.display().to_string());
+        assert!(PartRegistry::from_markdown(SAMPLE_PARTS).origin("pm").is_none());
+    }
+
+    #[test]
+    fn catalog_layers_follow_settings() {
+        let settings = MuxSettings {
+            project_root: "/repo".into(),
+            parts_path: "/home/me/parts.md".into(),
+            system_parts_path: "/opt/mux/parts.md".into(),
+            ..MuxSettings::default()
+        };
+        assert_eq!(
+            catalog_layers(&settings),
+            vec![
+                PathBuf::from("/opt/mux/parts.md"),
+                PathBuf::from("/home/me/parts.md"),
+                PathBuf::from("/repo/.mux/parts.md"),
+            ]
+        );
+        let settings = MuxSettings {
+            project_root: String::new(),
+            ..settings
+        };
+        assert_eq!(catalog_layers(&settings).len(), 2);
+        assert_eq!(user_catalog_path(&settings), Some(PathBuf::from("/home/me/parts.md")));
+    }
+
+    #[test]
+    fn save_replaces_the_file() {
+        let dir = std::env::temp_dir().join(format!("muxux-parts-{}", std::process::id()));
+        let path = dir.join("lib").join("parts.md");
+        let mut reg = PartRegistry::from_markdown(SAMPLE_PARTS);
+        reg.save(&path).unwrap();
+        reg.remove("gpu-station").unwrap();
+        reg.save(&path).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(saved, reg.to_markdown());
+        let files = std::fs::read_dir(path.parent().unwrap()).unwrap().count();
+        assert_eq!(files, 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
@@ -0,0 +1,205 @@
+//! Watch events — what the daemon tells `mux watch` clients.
+//!
+//! Every event is stamped with a monotonic sequence number and kept in a
+//! bounded `EventLog`, so a watcher that passes `--since <seq>` receives
+//! exactly the events it missed. Watchers are sent a JSON array of
+//! `EventRecord`s.
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::agent::AgentStatus;
+
+
+/// How many events the daemon keeps for `--since` replay.
+const LOG_CAPACITY: usize = 1_000;
+
+
+/// Something that happened in the daemon or in tmux.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MuxEvent {
+    /// A command was executed; `command` is its JSON form.
+    CommandExecuted { command: serde_json::Value, ok: bool },
+    /// A session's captured layout changed.
+    LayoutChanged { session: String, layout: String },
+    /// A tmux session appeared.
+    SessionCreated { session: String },
+    /// A tmux session went away.
+    SessionClosed { session: String },
+    /// The active pane of a session changed.
+    PaneFocused { session: String, pane: String },
+    /// An agent's status changed (e.g. its pane was closed).
+    AgentStatusChanged { agent: String, status: AgentStatus },
+    /// A changed layout could not be written to the history.
+    HistoryFailed { session: String, error: String },
+}
+
+
+impl MuxEvent {
+    /// The `type` tag this event serializes with.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            MuxEvent::CommandExecuted { .. } => "command_executed",
+            MuxEvent::LayoutChanged { .. } => "layout_changed",
+            MuxEvent::SessionCreated { .. } => "session_created",
+            MuxEvent::SessionClosed { .. } => "session_closed",
+            MuxEvent::PaneFocused { .. } => "pane_focused",
+            MuxEvent::AgentStatusChanged { .. } => "agent_status_changed",
+            MuxEvent::HistoryFailed { .. } => "history_failed",
+        }
+    }
+}
+
+
+/// An event as sent to watchers.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EventRecord {
+    pub seq: u64,
+    pub timestamp_ms: u64,
+    #[serde(flatten)]
+    pub event: MuxEvent,
+}
+
+
+/// Bounded, sequence-numbered history of recent events.
+#[derive(Debug)]
+pub struct EventLog {
+    next_seq: u64,
+    records: VecDeque<EventRecord>,
+}
+
+
+impl Default for EventLog {
+    fn default() -> Self {
+        EventLog::new()
+    }
+}
+
+
+impl EventLog {
+    /// An empty log; the first event gets sequence number 1.
+    pub fn new() -> EventLog {
+        EventLog {
+            next_seq: 1,
+            records: VecDeque::new(),
+        }
+    }
+
+    /// Stamp `event` with the next sequence number and keep it.
+    pub fn push(&mut self, event: MuxEvent, now_ms: u64) -> EventRecord {
+        let record = EventRecord {
+            seq: self.next_seq,
+            timestamp_ms: now_ms,
+            event,
+        };
+        self.next_seq += 1;
+        if self.records.len() == LOG_CAPACITY {
+            self.records.pop_front();
+        }
+        self.records.push_back(record.clone());
+        record
+    }
+
+    /// Events with a sequence number greater than `seq`, oldest first.
+    pub fn since(&self, seq: u64) -> Vec<EventRecord> {
+        self.records.iter().filter(|r| r.seq > seq).cloned().collect()
+    }
+
+    /// Sequence number of the newest event, 0 when none.
+    pub fn last_seq(&self) -> u64 {
+        self.next_seq - 1
+    }
+}
+
+
+/// Current Unix time in milliseconds, the clock events are stamped with.
+pub fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+
+/// Serialize records as the JSON array sent to watchers.
+pub fn records_json(records: &[EventRecord]) -> String {
+    serde_json::to_string(records).unwrap_or_else(|_| "[]".into())
+}
+
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changed(session: &str) -> MuxEvent {
+        MuxEvent::LayoutChanged {
+            session: session.into(),
+            layout: "ROW(a, b)".into(),
+        }
+    }
+
+    #[test]
+    fn sequence_numbers_are_monotonic() {
+        let mut log = EventLog::new();
+        assert_eq!(log.last_seq(), 0);
+        assert_eq!(log.push(changed("a"), 10).seq, 1);
+        assert_eq!(log.push(changed("b"), 10).seq, 2);
+        assert_eq!(log.last_seq(), 2);
+    }
+
+    #[test]
+    fn since_returns_missed_events() {
+        let mut log = EventLog::new();
+        for s in ["a", "b", "c"] {
+            log.push(changed(s), 0);
+        }
+        let missed: Vec<u64> = log.since(1).iter().map(|r| r.seq).collect();
+        assert_eq!(missed, vec![2, 3]);
+        assert!(log.since(3).is_empty());
+    }
+
+    #[test]
+    fn log_is_bounded() {
+        let mut log = EventLog::new();
+        for _ in 0..LOG_CAPACITY + 5 {
+            log.push(changed("a"), 0);
+        }
+        let all = log.since(0);
+        assert_eq!(all.len(), LOG_CAPACITY);
+        assert_eq!(all[0].seq, 6);
+    }
+
+    #[test]
+    fn record_json_is_flat() {
+        let record = EventRecord {
+            seq: 7,
+            timestamp_ms: 99,
+            event: MuxEvent::SessionClosed {
+                session: "work".into(),
+            },
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&records_json(std::slice::from_ref(&record))).unwrap();
+        assert_eq!(json[0]["seq"], 7);
+        assert_eq!(json[0]["type"], "session_closed");
+        assert_eq!(json[0]["session"], "work");
+        let back: Vec<EventRecord> = serde_json::from_value(json).unwrap();
+        assert_eq!(back, vec![record]);
+    }
+
+    #[test]
+    fn kind_matches_serialized_tag() {
+        let event = MuxEvent::AgentStatusChanged {
+            agent: "pm".into(),
+            status: AgentStatus::Dead,
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], event.kind());
+    }
+}
@@ -20,27 +20,49 @@ fn overview() -> String {
     "\
 mux — MuxUX command-line interface (Structure App)
 
-Usage: mux <command> [args...]
+Usage: mux [--local|--daemon] <command> [args...]
+
+Commands are sent to the running daemon (mux.sock) when one exists and
+executed in-process otherwise. --local / --daemon force either path.
 
 Commands:
-  status [--json]             Show MuxUX status
-  view <name>                Look up a session or layout by name
+  status [--json]             Show daemon, tmux, hook, and catalog status
+  view <name> [--diagram]    Look up a session, agent, or part by name
   help [topic]               Show help
 
 Layout commands:
   layout row <session> [--percent <n>]     Split session horizontally
   layout column <session> [--percent <n>]  Split session vertically
-  layout merge <session>                   Merge all panes into one
+  layout merge <session> [--keep <pane>] [--break]
+                                           Merge the current window's panes
+  layout apply <session> <part|layout>     Reconcile panes toward a layout
   layout place <pane> <agent>              Place an agent in a pane
-  layout capture <session>                 Capture pane contents
+  layout capture <session>                 Snapshot every window as JSON
+  layout restore <session> --part <name> | --file <snapshot.json> | --at <time|index>
+                                           Recreate a session's windows
+  layout history <session>                 List saved layouts of a session
   layout session <name> [--cwd <path>]     Create a new tmux session
 
+Parts commands:
+  parts list                 List the parts catalog as JSON
+  parts show <name> [--width <n>] [--height <n>] [--ascii]
+                             Preview a part as a box diagram
+  parts check [--file <path>]
+                             Validate the layout expressions of every layer
+  parts add <name> <layout> | --role <r> [--type <t>] [--host <h>]
+                             Add a part to the catalog
+  parts update <name> <layout> | --role <r> [--type <t>] [--host <h>]
+                             Replace a part's definition
+  parts rm <name>            Delete a part no other part uses
+  parts mv <old> <new>       Rename a part and every use of it
+
 Client commands:
   client next                Switch to next client view
   client prev                Switch to previous client view
 
 Watch command:
-  watch [--since <ms>] [--timeout <ms>]  Stream state changes
+  watch [--since <seq>] [--session <s>] [--type <t,..>] [--agent <a>] [--json]
+                                         Stream state changes
 
 Daemon commands:
   daemon run                 Start MuxUX daemon in foreground
@@ -62,18 +84,106 @@ Layout commands — manage tmux sessions and pane layout
   layout column <session> [--percent <n>]
     Split the session with a vertical divider. Default 50%.
 
-  layout merge <session>
-    Merge all panes in a session into a single pane.
+  layout merge <session> [--keep <pane>] [--break]
+    Merge the panes of the session's current window into a single pane.
+    Keeps the active pane, or the pane given by --keep (ID like %3 or
+    index). Other windows are left alone. The other panes are
+    killed; with --break they move to background windows instead, so their
+    processes and scrollback survive.
+
+  layout apply <session> <part|layout>
+    Reconcile the session's current window toward a catalog part or a
+    layout expression such as 'ROW(pilot 30%, COL(w1, w2) 70%)'. Sizes may
+    also be exact cells ('sidebar 40c' in a ROW, 'logs 12r' in a COL),
+    bounds ('>=10r', '<=80c'), or '*' for whatever is left; they are solved
+    for the window's current size. Panes that already match are kept; only
+    the missing splits, surplus panes, agent swaps, and size corrections are
+    applied. Each pane is then tagged with its agent (@mux_agent) and the
+    agent is started according to its part's type: line (claude, console,
+    ssh). Agents only start in panes back at a shell; a pane busy with
+    another agent or program is skipped and reported. Re-applying is a
+    no-op. A name may carry attributes before its sizes, as in
+    'worker[cmd=\"cargo watch\", cwd=~/proj, title=build, focus] 40%': cmd runs
+    instead of the agent's own command, cwd is entered first, title names
+    the pane, and focus selects it. A leaf named _ is a pane left without
+    an agent, as captures write untagged panes: 'ROW(pm 70%, _ 30%)'.
 
   layout place <pane> <agent>
-    Place an agent into a specific tmux pane (e.g. %3).
+    Place an agent into a specific tmux pane (e.g. %3): tag the pane with
+    @mux_agent and start the agent if its part declares a type. A pane busy
+    with another agent or program is left alone and reported.
 
   layout capture <session>
-    Capture the current content of all panes in a session.
+    Snapshot every window of a session (index, name, layout tree, agents)
+    and print it as JSON.
+
+  layout restore <session> --part <name> | --file <snapshot.json> | --at <time|index>
+    Recreate a session from a snapshot file, a catalog part, or its layout
+    history. A session part written WIN(editor: ROW(...), logs: tail) gets
+    those windows; any other session part becomes one window per top-level
+    child. The first window with a focused pane is selected. Missing
+    windows are created, existing ones renamed and reconciled, and agents
+    started. Windows not in the snapshot are left alone. --at takes a
+    history index (0 oldest, -1 newest), an age such as 15m or 2h, or a Unix
+    time in milliseconds.
+
+  layout history <session>
+    List the layouts recorded for a session, oldest first. Every capture
+    that changed the layout is kept in <config dir>/history/<session>.jsonl.
 
   layout session <name> [--cwd <path>]
     Create a new tmux session. Uses project_root as default cwd.",
 
+        "parts" => "\
+Parts commands — inspect and edit the parts catalog
+
+  The catalog is layered from three files, each overriding parts of the
+  same name in the ones before it:
+    system   $MUX_SYSTEM_PARTS_PATH, or /etc/muxux/parts.md
+    user     $MUX_PARTS_PATH, or ~/.config/skd/skd-library/parts.md
+    project  <project root>/.mux/parts.md
+  The system_parts_path and parts_path keys of settings.json in the config
+  directory take precedence over the environment. The project root is
+  $MUX_PROJECT_ROOT, or the directory mux is run from; the daemon uses the
+  one sent with each command.
+
+  parts list
+    Print the catalog's agents, compositions, and sessions as JSON, each
+    with the file it came from as its source.
+
+  parts show <name> [--width <n>] [--height <n>] [--ascii]
+    Print a part's expanded layout expression and draw it as boxes, each
+    pane sized by its percentage and labelled with its agent. The diagram
+    is 48x12 characters unless --width / --height say otherwise, up to
+    500x200. --ascii draws the borders with +-| instead of box-drawing
+    characters.
+
+  parts check [--file <path>]
+    Parse every layout expression in each catalog layer (or in path) and
+    report problems as file:line:column with a caret under the offending text.
+    Syntax errors fail the check. Warnings cover percentages that do not
+    add up to 100%, agents used twice in one layout, empty entries, and
+    names that are not parts in the catalog.
+
+  parts add <name> (<layout> | --role <r> [--type <t>] [--host <h>])
+    Append a part to the catalog: a ROW(...), COL(...) or WIN(...) layout,
+    or an agent with the given role, type, and host. Fails if the name is
+    taken or the layout does not parse; prints the layout's warnings.
+
+  parts update <name> (<layout> | --role <r> [--type <t>] [--host <h>])
+    Replace a part's definition. Notes under its heading are kept.
+
+  parts rm <name>
+    Delete a part. Refuses while another part's layout uses it.
+
+  parts mv <old> <new>
+    Rename a part and rewrite the layouts that use it.
+
+  All four edit the user catalog, or the file given with --file <path>.
+  The file is rewritten in one step, keeping its headings, comments, and
+  notes. When another layer now supplies the part, they say which, with a
+  warning if that layer overrides the edit.",
+
         "client" => "\
 Client commands — navigate between client views
 
@@ -86,14 +196,24 @@ Client commands — navigate between client views
         "watch" => "\
 Watch command — stream state changes
 
-  watch [--since <ms>] [--timeout <ms>]
-    Stream state change events to stdout as they occur.",
+  watch [--since <seq>] [--timeout <ms>] [--session <name>]
+        [--type <type,...>] [--agent <name>] [--json]
+    Stream events from the daemon, one line each, until interrupted. Every
+    event has a monotonic seq number and a type: command_executed,
+    layout_changed, session_created, session_closed, pane_focused,
+    agent_status_changed, or history_failed. --since replays the events after <seq> that the
+    daemon still holds. --session, --type and --agent keep only matching
+    events. --json prints NDJSON instead of readable lines. --timeout sets
+    how long each poll waits (default 30000 ms); a quiet poll just starts
+    the next one. Needs a running daemon.",
 
         "daemon" => "\
 Daemon commands — manage the MuxUX daemon process
 
   daemon run
-    Start the MuxUX daemon in the foreground.
+    Start the MuxUX daemon in the foreground. Listens on mux.sock in the
+    config directory, writes mux.pid, and exits on daemon stop, SIGTERM,
+    or SIGINT.
 
   daemon stop
     Stop the running MuxUX daemon gracefully.",
@@ -106,18 +226,28 @@ Daemon commands — manage the MuxUX daemon process
 
 fn command_help(command: &str) -> Option<String> {
     let text = match command {
-        "status" => "mux status — show MuxUX status\n\nUsage: mux status [--json]",
-        "view" => "mux view — look up a session or layout\n\nUsage: mux view <name>",
+        "status" => "mux status — show MuxUX status\n\nUsage: mux status [--json]\n\nReports the daemon (pid, uptime), config dir, tmux session/window/pane\ncounts, each session's last layout capture, whether the right-click hook\nis installed, and parts-catalog counts. --json prints the versioned\nStatusReport schema (schema_version 1) documented in core/src/status.rs.",
+        "view" => "mux view — look up a session, agent, or part\n\nUsage: mux view <name> [--diagram]\n\nA live tmux session prints each window's layout expression and panes.\nAn agent prints the panes tagged with it and whether each is running or\nidle. A part prints its expanded layout tree. --diagram also draws the\nlayout as boxes sized by its percentages.",
         "help" => "mux help — show help\n\nUsage: mux help [topic]",
         "layout.row" => "mux layout row — horizontal split\n\nUsage: mux layout row <session> [--percent <n>]",
         "layout.column" => "mux layout column — vertical split\n\nUsage: mux layout column <session> [--percent <n>]",
-        "layout.merge" => "mux layout merge — merge panes\n\nUsage: mux layout merge <session>",
+        "layout.merge" => "mux layout merge — merge panes\n\nUsage: mux layout merge <session> [--keep <pane>] [--break]",
+        "layout.apply" => "mux layout apply — reconcile toward a layout\n\nUsage: mux layout apply <session> <part|layout>",
         "layout.place" => "mux layout place — place agent in pane\n\nUsage: mux layout place <pane> <agent>",
-        "layout.capture" => "mux layout capture — capture pane contents\n\nUsage: mux layout capture <session>",
+        "layout.capture" => "mux layout capture — snapshot a session\n\nUsage: mux layout capture <session>",
+        "layout.restore" => "mux layout restore — recreate a session\n\nUsage: mux layout restore <session> --part <name> | --file <snapshot.json> | --at <time|index>",
+        "layout.history" => "mux layout history — list saved layouts\n\nUsage: mux layout history <session>",
         "layout.session" => "mux layout session — create tmux session\n\nUsage: mux layout session <name> [--cwd <path>]",
+        "parts.list" => "mux parts list — list the parts catalog\n\nUsage: mux parts list",
+        "parts.check" => "mux parts check — validate the catalog\n\nUsage: mux parts check [--file <path>]",
+        "parts.show" => "mux parts show — preview a part\n\nUsage: mux parts show <name> [--width <n>] [--height <n>] [--ascii]",
+        "parts.add" => "mux parts add — add a part to the catalog\n\nUsage: mux parts add <name> (<layout> | --role <r> [--type <t>] [--host <h>]) [--file <path>]",
+        "parts.update" => "mux parts update — replace a part's definition\n\nUsage: mux parts update <name> (<layout> | --role <r> [--type <t>] [--host <h>]) [--file <path>]",
+        "parts.rm" => "mux parts rm — delete an unused part\n\nUsage: mux parts rm <name> [--file <path>]",
+        "parts.mv" => "mux parts mv — rename a part and its uses\n\nUsage: mux parts mv <old> <new> [--file <path>]",
         "client.next" => "mux client next — switch to next view\n\nUsage: mux client next",
         "client.prev" => "mux client prev — switch to previous view\n\nUsage: mux client prev",
-        "watch" => "mux watch — stream state changes\n\nUsage: mux watch [--since <ms>] [--timeout <ms>]",
+        "watch" => "mux watch — stream state changes\n\nUsage: mux watch [--since <seq>] [--timeout <ms>] [--session <name>] [--type <type,...>] [--agent <name>] [--json]",
         "daemon.run" => "mux daemon run — start daemon\n\nUsage: mux daemon run",
         "daemon.stop" => "mux daemon stop — stop daemon\n\nUsage: mux daemon stop",
         _ => return None,
@@ -0,0 +1,221 @@
+//! Executing tmux backend — runs actions through a `CommandRunner`.
+//!
+//! `ExecutingTmuxBackend` shares the action-to-command translation with
+//! `TmuxBackend` but hands every command to a runner instead of buffering it,
+//! and answers session and capture queries from live tmux output. With
+//! `ShellRunner` it drives the real tmux server; with `MockRunner` it is fully
+//! deterministic for tests.
+
+use cmx_utils::response::Action;
+
+use super::runner::CommandRunner;
+use super::tmux::{action_commands, parse_list_sessions, TmuxCommandBuilder};
+use super::SessionBackend;
+
+
+/// Outcome of one tmux command run on behalf of an action.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandOutcome {
+    pub command: String,
+    pub result: Result<String, String>,
+}
+
+
+/// A `SessionBackend` that executes tmux commands as it goes.
+pub struct ExecutingTmuxBackend<R: CommandRunner> {
+    builder: TmuxCommandBuilder,
+    runner: R,
+}
+
+
+impl<R: CommandRunner> ExecutingTmuxBackend<R> {
+    pub fn new(runner: R) -> Self {
+        ExecutingTmuxBackend {
+            builder: TmuxCommandBuilder::new(),
+            runner,
+        }
+    }
+
+    /// The runner commands are executed through.
+    pub fn runner(&self) -> &R {
+        &self.runner
+    }
+
+    /// Execute every action in order and report each tmux command's result.
+    ///
+    /// Unlike `execute_action`, a failing command does not stop the batch;
+    /// callers inspect the outcomes to decide how to surface errors.
+    pub fn run_actions(&mut self, actions: &[Action]) -> Vec<CommandOutcome> {
+        run_actions(&self.runner, actions)
+    }
+}
+
+
+impl<R: CommandRunner> SessionBackend for ExecutingTmuxBackend<R> {
+    fn execute_action(&mut self, action: &Action) -> Result<(), String> {
+        for command in action_commands(&self.builder, action) {
+            self.runner
+                .run(&command)
+                .map_err(|e| format!("{} (cmd: {})", e.trim(), command))?;
+        }
+        Ok(())
+    }
+
+    fn session_exists(&self, name: &str) -> bool {
+        self.runner.run(&self.builder.has_session(name)).is_ok()
+    }
+
+    fn list_sessions(&self) -> Vec<String> {
+        // No tmux server means no sessions, not an error.
+        match self.runner.run(&self.builder.list_sessions()) {
+            Ok(output) => parse_list_sessions(&output),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn capture_pane(&self, target: &str) -> Result<String, String> {
+        self.runner.run(&self.builder.capture_pane(target))
+    }
+}
+
+
+/// Execute every action in order through `runner`, for callers that only
+/// borrow their runner, and report each tmux command's result.
+pub fn run_actions(runner: &dyn CommandRunner, actions: &[Action]) -> Vec<CommandOutcome> {
+    let builder = TmuxCommandBuilder::new();
+    let mut outcomes = Vec::new();
+    for action in actions {
+        for command in action_commands(&builder, action) {
+            let result = runner.run(&command);
+            outcomes.push(CommandOutcome { command, result });
+        }
+    }
+    outcomes
+}
+
+
+/// Collect the failed commands from a batch of outcomes as readable messages.
+pub fn failures(outcomes: &[CommandOutcome]) -> Vec<String> {
+    outcomes
+        .iter()
+        .filter_map(|o| match &o.result {
+            Ok(_) => None,
+            Err(e) => Some(format!("{} (cmd: {})", e.trim(), o.command)),
+        })
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::runner::MockRunner;
+    use cmx_utils::response::Direction;
+
+    #[test]
+    fn execute_action_runs_command() {
+        let mut backend = ExecutingTmuxBackend::new(MockRunner::new());
+        backend
+            .execute_action(&Action::CreateSession {
+                name: "work".into(),
+                cwd: "/tmp".into(),
+            })
+            .unwrap();
+        assert_eq!(
+            backend.runner().executed_commands(),
+            vec!["tmux new-session -d -s work -c /tmp"]
+        );
+    }
+
+    #[test]
+    fn execute_action_reports_error() {
+        let runner = MockRunner::with_responses(vec![Err("no server running\n".into())]);
+        let mut backend = ExecutingTmuxBackend::new(runner);
+        let err = backend
+            .execute_action(&Action::KillSession { name: "gone".into() })
+            .unwrap_err();
+        assert!(err.contains("no server running"));
+        assert!(err.contains("kill-session -t gone"));
+    }
+
+    #[test]
+    fn logical_actions_run_nothing() {
+        let mut backend = ExecutingTmuxBackend::new(MockRunner::new());
+        backend
+            .execute_action(&Action::KillAgent { name: "w1".into() })
+            .unwrap();
+        assert!(backend.runner().executed_commands().is_empty());
+    }
+
+    #[test]
+    fn run_actions_continues_after_failure() {
+        let runner = MockRunner::with_responses(vec![Err("bad target".into()), Ok(String::new())]);
+        let mut backend = ExecutingTmuxBackend::new(runner);
+        let outcomes = backend.run_actions(&[
+            Action::SplitPane {
+                session: "nope".into(),
+                direction: Direction::Vertical,
+                percent: 50,
+            },
+            Action::SendKeys {
+                target: "work:0.0".into(),
+                keys: "ls".into(),
+            },
+        ]);
+        assert_eq!(outcomes.len(), 2);
+        assert!(outcomes[0].result.is_err());
+        assert!(outcomes[1].result.is_ok());
+        let failed = failures(&outcomes);
+        assert_eq!(failed.len(), 1);
+        assert!(failed[0].contains("split-window -t nope"));
+    }
+
+    #[test]
+    fn run_actions_through_a_borrowed_runner() {
+        let runner: Box<dyn CommandRunner> = Box::new(MockRunner::new());
+        let outcomes = run_actions(runner.as_ref(), &[Action::SendKeys {
+            target: "work:0.0".into(),
+            keys: "ls".into(),
+        }]);
+        assert_eq!(outcomes.len(), 1);
+        assert!(outcomes[0].command.contains("send-keys -t work:0.0"));
+    }
+
+    #[test]
+    fn list_sessions_parses_live_output() {
+        let runner = MockRunner::with_responses(vec![Ok("main\nwork\n".into())]);
+        let backend = ExecutingTmuxBackend::new(runner);
+        assert_eq!(backend.list_sessions(), vec!["main", "work"]);
+        assert!(backend.runner().executed_commands()[0].contains("list-sessions"));
+    }
+
+    #[test]
+    fn list_sessions_without_server_is_empty() {
+        let runner = MockRunner::with_responses(vec![Err("no server running".into())]);
+        let backend = ExecutingTmuxBackend::new(runner);
+        assert!(backend.list_sessions().is_empty());
+    }
+
+    #[test]
+    fn session_exists_uses_has_session() {
+        let runner = MockRunner::with_responses(vec![Ok(String::new()), Err("can't find session".into())]);
+        let backend = ExecutingTmuxBackend::new(runner);
+        assert!(backend.session_exists("main"));
+        assert!(!backend.session_exists("missing"));
+        assert_eq!(
+            backend.runner().executed_commands(),
+            vec!["tmux has-session -t main", "tmux has-session -t missing"]
+        );
+    }
+
+    #[test]
+    fn capture_pane_returns_live_content() {
+        let runner = MockRunner::with_responses(vec![Ok("$ cargo test\n".into())]);
+        let backend = ExecutingTmuxBackend::new(runner);
+        assert_eq!(backend.capture_pane("work:0.1").unwrap(), "$ cargo test\n");
+        assert_eq!(
+            backend.runner().executed_commands(),
+            vec!["tmux capture-pane -t work:0.1 -p"]
+        );
+    }
+}
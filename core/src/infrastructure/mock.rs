@@ -59,10 +59,8 @@ impl SessionBackend for MockBackend {
     fn execute_action(&mut self, action: &Action) -> Result<(), String> {
         // Track session creation and destruction logically.
         match action {
-            Action::CreateSession { name, .. } => {
-                if !self.sessions.contains(name) {
-                    self.sessions.push(name.clone());
-                }
+            Action::CreateSession { name, .. } if !self.sessions.contains(name) => {
+                self.sessions.push(name.clone());
             }
             Action::KillSession { name } => {
                 self.sessions.retain(|s| s != name);
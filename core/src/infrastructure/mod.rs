@@ -2,8 +2,10 @@
 //!
 //! Provides the `SessionBackend` trait and implementations for tmux (production)
 //! and mock (testing). The tmux backend builds command strings without executing
-//! them, keeping this crate free of process-spawning side effects.
+//! them, keeping this crate free of process-spawning side effects. The executing
+//! backend runs those same commands through a `CommandRunner`.
 
+pub mod executing;
 pub mod mock;
 pub mod runner;
 pub mod tmux;
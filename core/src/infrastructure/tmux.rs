@@ -2,13 +2,16 @@
 //!
 //! `TmuxBackend` implements `SessionBackend` by building tmux CLI command
 //! strings. It never spawns processes — the caller is responsible for executing
-//! the commands. This keeps the core crate pure and testable.
+//! the commands. This keeps the core crate pure and testable. The one
+//! exception is `realize_layout`, which needs the pane IDs tmux assigns and so
+//! runs its splits through a caller-supplied `CommandRunner`.
 
 use std::collections::HashMap;
 
 use cmx_utils::response::{Action, Direction};
-use crate::types::session::{LayoutNode, TmuxPane, TmuxWindow};
+use crate::types::session::{LayoutEntry, LayoutNode, TmuxPane, TmuxWindow};
 
+use super::runner::CommandRunner;
 use super::SessionBackend;
 
 // ---------------------------------------------------------------------------
@@ -32,6 +35,29 @@ impl TmuxCommandBuilder {
         )
     }
 
+    /// `tmux new-window -d -P -F '#{window_index}' -t <target> -n <name> -c <cwd>`
+    /// — `target` is `session:` to append or `session:index` for a specific
+    /// slot. An empty `name` leaves naming to tmux. Prints the new window's
+    /// index.
+    pub fn new_window(&self, target: &str, name: &str, cwd: &str) -> String {
+        let name = if name.is_empty() {
+            String::new()
+        } else {
+            format!(" -n {}", shell_escape(name))
+        };
+        format!(
+            "tmux new-window -d -P -F '#{{window_index}}' -t {}{} -c {}",
+            shell_escape(target),
+            name,
+            shell_escape(cwd)
+        )
+    }
+
+    /// `tmux has-session -t <name>`
+    pub fn has_session(&self, name: &str) -> String {
+        format!("tmux has-session -t {}", shell_escape(name))
+    }
+
     /// `tmux kill-session -t <name>`
     pub fn kill_session(&self, name: &str) -> String {
         format!("tmux kill-session -t {}", shell_escape(name))
@@ -51,6 +77,18 @@ impl TmuxCommandBuilder {
         )
     }
 
+    /// `tmux split-window -t <target> [-h|-v] -p <percent> -P -F '#{pane_id}'`
+    /// — split and print the ID of the new pane.
+    pub fn split_pane_print_id(&self, target: &str, direction: &Direction, percent: u32) -> String {
+        format!("{} -P -F '#{{pane_id}}'", self.split_pane(target, direction, percent))
+    }
+
+    /// `tmux display-message -p -t <target> '#{pane_id}'` — resolve a target
+    /// to the ID of the pane it names.
+    pub fn pane_id(&self, target: &str) -> String {
+        format!("tmux display-message -p -t {} '#{{pane_id}}'", shell_escape(target))
+    }
+
     /// `tmux send-keys -t <target> <keys> Enter`
     pub fn send_keys(&self, target: &str, keys: &str) -> String {
         format!(
@@ -79,23 +117,55 @@ impl TmuxCommandBuilder {
         )
     }
 
+    /// `tmux resize-pane -t <target> -x <width>` or `-y <height>` — set an
+    /// absolute size along one axis (`Horizontal` sets the width).
+    pub fn resize_pane_to(&self, target: &str, direction: &Direction, cells: u32) -> String {
+        let flag = match direction {
+            Direction::Horizontal => "-x",
+            Direction::Vertical => "-y",
+        };
+        format!(
+            "tmux resize-pane -t {} {} {}",
+            shell_escape(target),
+            flag,
+            cells
+        )
+    }
+
     /// `tmux list-sessions -F '#{session_name}'`
     pub fn list_sessions(&self) -> String {
         "tmux list-sessions -F '#{session_name}'".to_string()
     }
 
-    /// `tmux list-panes -t <session> -F '#{pane_id}:#{pane_index}:#{pane_width}:#{pane_height}:#{pane_top}:#{pane_left}'`
+    /// `tmux list-panes -a -F '#{session_name}:#{window_index}:#{pane_id}'` —
+    /// one line per pane across every session.
+    pub fn list_all_panes(&self) -> String {
+        "tmux list-panes -a -F '#{session_name}:#{window_index}:#{pane_id}'".to_string()
+    }
+
+    /// `tmux list-panes -a -F '#{pane_id}<TAB>#{@mux_agent}<TAB>#{pane_current_command}<TAB>#{window_index}<TAB>#{session_name}'`
+    /// — every pane with its agent tag. Fields are tab-separated because
+    /// agent tags and session names may contain `:`.
+    pub fn list_agent_panes(&self) -> String {
+        "tmux list-panes -a -F '#{pane_id}\t#{@mux_agent}\t#{pane_current_command}\t#{window_index}\t#{session_name}'"
+            .to_string()
+    }
+
+    /// `tmux list-panes -t <session> -F '#{pane_id}<TAB>#{pane_index}<TAB>#{pane_width}<TAB>#{pane_height}<TAB>#{pane_top}<TAB>#{pane_left}<TAB>#{pane_active}<TAB>#{@mux_agent}<TAB>#{pane_current_command}<TAB>#{pane_title}'`
+    ///
+    /// Fields are tab-separated because agent tags, commands and titles may
+    /// contain `:`.
     pub fn list_panes(&self, session: &str) -> String {
         format!(
-            "tmux list-panes -t {} -F '#{{pane_id}}:#{{pane_index}}:#{{pane_width}}:#{{pane_height}}:#{{pane_top}}:#{{pane_left}}'",
+            "tmux list-panes -t {} -F '#{{pane_id}}\t#{{pane_index}}\t#{{pane_width}}\t#{{pane_height}}\t#{{pane_top}}\t#{{pane_left}}\t#{{pane_active}}\t#{{@mux_agent}}\t#{{pane_current_command}}\t#{{pane_title}}'",
             shell_escape(session)
         )
     }
 
-    /// `tmux list-windows -t <session> -F '#{window_index}:#{window_name}:#{window_panes}'`
+    /// `tmux list-windows -t <session> -F '#{window_index}:#{window_name}:#{window_panes}:#{window_layout}'`
     pub fn list_windows(&self, session: &str) -> String {
         format!(
-            "tmux list-windows -t {} -F '#{{window_index}}:#{{window_name}}:#{{window_panes}}'",
+            "tmux list-windows -t {} -F '#{{window_index}}:#{{window_name}}:#{{window_panes}}:#{{window_layout}}'",
             shell_escape(session)
         )
     }
@@ -105,6 +175,15 @@ impl TmuxCommandBuilder {
         format!("tmux select-pane -t {}", shell_escape(target))
     }
 
+    /// `tmux select-pane -t <target> -T <title>`
+    pub fn set_pane_title(&self, target: &str, title: &str) -> String {
+        format!(
+            "tmux select-pane -t {} -T {}",
+            shell_escape(target),
+            shell_escape(title)
+        )
+    }
+
     /// `tmux select-window -t <target>`
     pub fn select_window(&self, target: &str) -> String {
         format!("tmux select-window -t {}", shell_escape(target))
@@ -137,6 +216,11 @@ impl TmuxCommandBuilder {
         )
     }
 
+    /// `tmux list-keys -T root MouseDown3Pane` — shows the right-click binding.
+    pub fn list_mouse_hook(&self) -> String {
+        "tmux list-keys -T root MouseDown3Pane".to_string()
+    }
+
     /// Generate the tmux command to unbind the right-click hook.
     pub fn unbind_mouse_hook(&self) -> String {
         "tmux unbind -n MouseDown3Pane".to_string()
@@ -153,11 +237,47 @@ impl TmuxCommandBuilder {
         format!("tmux swap-pane -t {} {}", shell_escape(target), flag)
     }
 
+    /// `tmux set-option -p -t <pane> @mux_agent <name>` — tag a pane with the
+    /// agent it runs so captures can map it back.
+    pub fn tag_pane(&self, pane: &str, agent: &str) -> String {
+        format!(
+            "tmux set-option -p -t {} @mux_agent {}",
+            shell_escape(pane),
+            shell_escape(agent)
+        )
+    }
+
+    /// `tmux swap-pane -d -s <source> -t <target>` — exchange two specific
+    /// panes, keeping focus where it was.
+    pub fn swap_panes(&self, source: &str, target: &str) -> String {
+        format!(
+            "tmux swap-pane -d -s {} -t {}",
+            shell_escape(source),
+            shell_escape(target)
+        )
+    }
+
     /// `tmux break-pane -t <target> -d`
     pub fn break_pane(&self, target: &str) -> String {
         format!("tmux break-pane -t {} -d", shell_escape(target))
     }
 
+    /// `tmux break-pane -d -s <pane>` — move a specific pane into its own
+    /// background window without switching to it.
+    pub fn break_pane_to_window(&self, pane: &str) -> String {
+        format!("tmux break-pane -d -s {}", shell_escape(pane))
+    }
+
+    /// `tmux select-layout -t <target> <layout>` — apply a tmux layout string
+    /// (see `layout::tmux_layout`).
+    pub fn select_layout(&self, target: &str, layout: &str) -> String {
+        format!(
+            "tmux select-layout -t {} {}",
+            shell_escape(target),
+            shell_escape(layout)
+        )
+    }
+
     /// `tmux select-layout -t <target> tiled`
     pub fn select_layout_tiled(&self, target: &str) -> String {
         format!("tmux select-layout -t {} tiled", shell_escape(target))
@@ -208,15 +328,16 @@ impl Default for TmuxCommandBuilder {
 
 /// Parse the output of `list_panes` into `TmuxPane` structs.
 ///
-/// Expected line format: `%id:index:width:height:top:left`
+/// Expected line format, tab-separated:
+/// `%id index width height top left [active [agent command title]]`.
+/// An empty agent field (no `@mux_agent` tag) parses as `None`.
 pub fn parse_list_panes(output: &str) -> Vec<TmuxPane> {
     let mut panes = Vec::new();
     for line in output.lines() {
-        let line = line.trim();
-        if line.is_empty() {
+        if line.trim().is_empty() {
             continue;
         }
-        let parts: Vec<&str> = line.splitn(6, ':').collect();
+        let parts: Vec<&str> = line.trim_end_matches('\r').splitn(10, '\t').collect();
         if parts.len() < 6 {
             continue;
         }
@@ -226,6 +347,13 @@ pub fn parse_list_panes(output: &str) -> Vec<TmuxPane> {
         let height = parts[3].parse::<u32>().unwrap_or(0);
         let top = parts[4].parse::<u32>().unwrap_or(0);
         let left = parts[5].parse::<u32>().unwrap_or(0);
+        let active = parts.get(6).map(|a| a.trim() == "1").unwrap_or(false);
+        let agent = parts
+            .get(7)
+            .map(|a| a.trim())
+            .filter(|a| !a.is_empty())
+            .map(|a| a.to_string());
+        let field = |i: usize| parts.get(i).map(|f| f.to_string()).unwrap_or_default();
         panes.push(TmuxPane {
             id,
             index,
@@ -233,7 +361,10 @@ pub fn parse_list_panes(output: &str) -> Vec<TmuxPane> {
             height,
             top,
             left,
-            agent: None,
+            agent,
+            active,
+            command: field(8),
+            title: field(9),
         });
     }
     panes
@@ -241,7 +372,7 @@ pub fn parse_list_panes(output: &str) -> Vec<TmuxPane> {
 
 /// Parse the output of `list_windows` into `TmuxWindow` structs.
 ///
-/// Expected line format: `index:name:pane_count`
+/// Expected line format: `index:name:pane_count[:layout]`
 pub fn parse_list_windows(output: &str) -> Vec<TmuxWindow> {
     let mut windows = Vec::new();
     for line in output.lines() {
@@ -249,7 +380,7 @@ pub fn parse_list_windows(output: &str) -> Vec<TmuxWindow> {
         if line.is_empty() {
             continue;
         }
-        let parts: Vec<&str> = line.splitn(3, ':').collect();
+        let parts: Vec<&str> = line.splitn(4, ':').collect();
         if parts.len() < 3 {
             continue;
         }
@@ -260,6 +391,7 @@ pub fn parse_list_windows(output: &str) -> Vec<TmuxWindow> {
             index,
             name,
             panes: Vec::new(),
+            layout: parts.get(3).map(|l| l.to_string()).unwrap_or_default(),
         });
     }
     windows
@@ -280,61 +412,130 @@ pub fn parse_list_sessions(output: &str) -> Vec<String> {
 // Layout realization
 // ---------------------------------------------------------------------------
 
-/// Convert a `LayoutNode` tree into a sequence of tmux commands that will
-/// build the described layout inside the given session.
+/// A leaf of a realized layout and the pane that now holds it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PanePlacement {
+    pub pane_id: String,
+    pub agent: String,
+}
+
+
+/// Outcome of `realize_layout`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Realization {
+    /// One entry per layout leaf, in depth-first order.
+    pub placements: Vec<PanePlacement>,
+    /// Every tmux command run, in order.
+    pub commands: Vec<String>,
+}
+
+
+/// Build the layout described by `layout` inside the single pane `target`.
 ///
-/// The first pane is assumed to already exist (it is the default pane created
-/// with the session). Subsequent panes are created via `split-window`.
+/// Each `split-window` prints the ID of the pane it creates, so every nested
+/// subtree splits exactly the pane that owns its space. Siblings are created
+/// first, then each child is realized inside its own pane. Percentages are
+/// shares of the parent and are converted to the remaining-space percentage
+/// tmux expects at each split; children without a percentage share what the
+/// others leave.
 ///
-/// Returns a vec of command strings in execution order.
-pub fn realize_layout(session: &str, layout: &LayoutNode) -> Vec<String> {
+/// For windows that may already hold other panes, use `layout::reconcile`.
+pub fn realize_layout(
+    runner: &dyn CommandRunner,
+    target: &str,
+    layout: &LayoutNode,
+) -> Result<Realization, String> {
     let builder = TmuxCommandBuilder::new();
-    let mut commands = Vec::new();
-    let target_base = format!("{}:0", session);
-    realize_node(&builder, &target_base, layout, &mut commands, true);
-    commands
+    let mut realization = Realization {
+        placements: Vec::new(),
+        commands: Vec::new(),
+    };
+    let root = run_logged(runner, &builder.pane_id(target), &mut realization.commands)?;
+    realize_node(runner, &builder, &root, layout, &mut realization)?;
+    Ok(realization)
 }
 
-/// Recursive helper that walks the layout tree. `is_first` tracks whether
-/// the current node can reuse an existing pane or must split.
+
+/// Realize `node` inside `pane`, which covers exactly the node's space.
 fn realize_node(
+    runner: &dyn CommandRunner,
     builder: &TmuxCommandBuilder,
-    target: &str,
+    pane: &str,
     node: &LayoutNode,
-    commands: &mut Vec<String>,
-    is_first: bool,
-) {
-    match node {
-        LayoutNode::Pane { agent } => {
-            if !is_first {
-                // We have already split to create this pane; nothing else to do.
-            }
-            // Annotate: send a comment so pane can be identified.
-            let _ = agent; // agent binding used for documentation only in command gen
-        }
-        LayoutNode::Row { children } => {
-            for (i, entry) in children.iter().enumerate() {
-                if i == 0 {
-                    realize_node(builder, target, &entry.node, commands, is_first);
-                } else {
-                    let pct = entry.percent.unwrap_or(50);
-                    commands.push(builder.split_pane(target, &Direction::Horizontal, pct));
-                    realize_node(builder, target, &entry.node, commands, false);
-                }
-            }
+    realization: &mut Realization,
+) -> Result<(), String> {
+    let (children, direction) = match node {
+        LayoutNode::Pane { agent, .. } => {
+            realization.placements.push(PanePlacement {
+                pane_id: pane.to_string(),
+                agent: agent.clone(),
+            });
+            return Ok(());
         }
-        LayoutNode::Col { children } => {
-            for (i, entry) in children.iter().enumerate() {
-                if i == 0 {
-                    realize_node(builder, target, &entry.node, commands, is_first);
-                } else {
-                    let pct = entry.percent.unwrap_or(50);
-                    commands.push(builder.split_pane(target, &Direction::Vertical, pct));
-                    realize_node(builder, target, &entry.node, commands, false);
-                }
-            }
-        }
-    }
+        LayoutNode::Row { children } => (children, Direction::Horizontal),
+        LayoutNode::Col { children } => (children, Direction::Vertical),
+    };
+
+    let weights = child_weights(children);
+    let mut panes = vec![pane.to_string()];
+    for i in 1..children.len() {
+        // The last pane holds children i-1.., the new one takes children i..
+        let rest: f64 = weights[i..].iter().sum();
+        let from: f64 = weights[i - 1..].iter().sum();
+        let percent = ((100.0 * rest / from).round() as u32).clamp(1, 99);
+        let current = &panes[panes.len() - 1];
+        let split = builder.split_pane_print_id(current, &direction, percent);
+        let created = run_logged(runner, &split, &mut realization.commands)?;
+        panes.push(created);
+    }
+    for (entry, child_pane) in children.iter().zip(&panes) {
+        realize_node(runner, builder, child_pane, &entry.node, realization)?;
+    }
+    Ok(())
+}
+
+
+/// Resolve each child's share of its parent. Children without a percentage
+/// split whatever the others leave (or share equally if nothing is left).
+/// Layouts resolved by `layout::solver` carry exact cells and are weighted
+/// by them instead.
+pub(crate) fn child_weights(children: &[LayoutEntry]) -> Vec<f64> {
+    if let Some(cells) = children
+        .iter()
+        .map(|e| e.size.cells.map(f64::from))
+        .collect::<Option<Vec<f64>>>()
+    {
+        return cells.into_iter().map(|c| c.max(1.0)).collect();
+    }
+    let given: u32 = children.iter().filter_map(|e| e.percent).sum();
+    let unset = children.iter().filter(|e| e.percent.is_none()).count();
+    let share = if unset > 0 && given < 100 {
+        f64::from(100 - given) / unset as f64
+    } else {
+        100.0 / children.len().max(1) as f64
+    };
+    children
+        .iter()
+        .map(|e| e.percent.map(f64::from).unwrap_or(share).max(1.0))
+        .collect()
+}
+
+
+/// Run a command that prints a pane ID, recording it, and return the ID.
+fn run_logged(
+    runner: &dyn CommandRunner,
+    command: &str,
+    log: &mut Vec<String>,
+) -> Result<String, String> {
+    log.push(command.to_string());
+    let output = runner
+        .run(command)
+        .map_err(|e| format!("{} (cmd: {})", e.trim(), command))?;
+    let id = output.trim();
+    if id.is_empty() {
+        return Err(format!("no pane ID reported (cmd: {})", command));
+    }
+    Ok(id.to_string())
 }
 
 // ---------------------------------------------------------------------------
@@ -360,6 +561,39 @@ pub fn shell_escape(s: &str) -> String {
     format!("'{}'", escaped)
 }
 
+// ---------------------------------------------------------------------------
+// Action translation
+// ---------------------------------------------------------------------------
+
+/// Translate an abstract action into the tmux commands that carry it out.
+///
+/// Logical actions with no tmux counterpart (agent bookkeeping, assignments)
+/// produce no commands. Shared by `TmuxBackend` and `ExecutingTmuxBackend`.
+pub fn action_commands(builder: &TmuxCommandBuilder, action: &Action) -> Vec<String> {
+    match action {
+        Action::CreateSession { name, cwd } => vec![builder.new_session(name, cwd)],
+        Action::KillSession { name } => vec![builder.kill_session(name)],
+        Action::SplitPane {
+            session,
+            direction,
+            percent,
+        } => vec![builder.split_pane(session, direction, *percent)],
+        Action::SendKeys { target, keys } => vec![builder.send_keys(target, keys)],
+        Action::CreateAgent { .. } => {
+            // Agent creation is a logical operation; no tmux command needed.
+            Vec::new()
+        }
+        Action::KillAgent { .. } => Vec::new(),
+        Action::PlaceAgent { pane_id, agent } => vec![builder.tag_pane(pane_id, agent)],
+        Action::ConnectSsh { agent, host, port } => {
+            // Build an ssh command to send into the agent's pane.
+            let ssh_cmd = format!("ssh -p {} {}", port, host);
+            vec![builder.send_keys(agent, &ssh_cmd)]
+        }
+        Action::UpdateAssignment { .. } => Vec::new(),
+    }
+}
+
 // ---------------------------------------------------------------------------
 // TmuxBackend (SessionBackend implementation)
 // ---------------------------------------------------------------------------
@@ -412,46 +646,15 @@ impl Default for TmuxBackend {
 impl SessionBackend for TmuxBackend {
     fn execute_action(&mut self, action: &Action) -> Result<(), String> {
         match action {
-            Action::CreateSession { name, cwd } => {
-                self.commands.push(self.builder.new_session(name, cwd));
-                if !self.sessions.contains(name) {
-                    self.sessions.push(name.clone());
-                }
+            Action::CreateSession { name, .. } if !self.sessions.contains(name) => {
+                self.sessions.push(name.clone());
             }
             Action::KillSession { name } => {
-                self.commands.push(self.builder.kill_session(name));
                 self.sessions.retain(|s| s != name);
             }
-            Action::SplitPane {
-                session,
-                direction,
-                percent,
-            } => {
-                self.commands
-                    .push(self.builder.split_pane(session, direction, *percent));
-            }
-            Action::SendKeys { target, keys } => {
-                self.commands.push(self.builder.send_keys(target, keys));
-            }
-            Action::CreateAgent { name, role, path } => {
-                // Agent creation is a logical operation; no tmux command needed.
-                let _ = (name, role, path);
-            }
-            Action::KillAgent { name } => {
-                let _ = name;
-            }
-            Action::PlaceAgent { pane_id, agent } => {
-                let _ = (pane_id, agent);
-            }
-            Action::ConnectSsh { agent, host, port } => {
-                // Build an ssh command to send into the agent's pane.
-                let ssh_cmd = format!("ssh -p {} {}", port, host);
-                self.commands.push(self.builder.send_keys(agent, &ssh_cmd));
-            }
-            Action::UpdateAssignment { agent, task } => {
-                let _ = (agent, task);
-            }
+            _ => {}
         }
+        self.commands.extend(action_commands(&self.builder, action));
         Ok(())
     }
 
@@ -478,8 +681,8 @@ impl SessionBackend for TmuxBackend {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use cmx_utils::response::Direction;
-    use crate::types::session::LayoutEntry;
+    use crate::infrastructure::runner::MockRunner;
+    use crate::types::session::SizeSpec;
 
     #[test]
     fn cmd_new_session() {
@@ -496,6 +699,12 @@ mod tests {
         assert!(cmd.contains("'/tmp/my proj'"));
     }
 
+    #[test]
+    fn cmd_has_session() {
+        let b = TmuxCommandBuilder::new();
+        assert_eq!(b.has_session("work"), "tmux has-session -t work");
+    }
+
     #[test]
     fn cmd_kill_session() {
         let b = TmuxCommandBuilder::new();
@@ -606,7 +815,7 @@ mod tests {
 
     #[test]
     fn parse_panes_basic() {
-        let output = "%0:0:120:40:0:0\n%1:1:60:40:0:60\n";
+        let output = "%0\t0\t120\t40\t0\t0\n%1\t1\t60\t40\t0\t60\n";
         let panes = parse_list_panes(output);
         assert_eq!(panes.len(), 2);
         assert_eq!(panes[0].id, "%0");
@@ -615,6 +824,36 @@ mod tests {
         assert_eq!(panes[1].left, 60);
     }
 
+    #[test]
+    fn parse_panes_active_flag() {
+        let output = "%0\t0\t60\t40\t0\t0\t0\n%1\t1\t59\t40\t0\t61\t1\n";
+        let panes = parse_list_panes(output);
+        assert!(!panes[0].active);
+        assert!(panes[1].active);
+    }
+
+    #[test]
+    fn parse_panes_agent_command_title() {
+        let output = "%0\t0\t60\t40\t0\t0\t1\tpm\tclaude\tpm: planning\n%1\t1\t59\t40\t0\t61\t0\t\tzsh\thost\n";
+        let panes = parse_list_panes(output);
+        assert_eq!(panes[0].agent.as_deref(), Some("pm"));
+        assert_eq!(panes[0].command, "claude");
+        assert_eq!(panes[0].title, "pm: planning");
+        assert_eq!(panes[1].agent, None);
+        assert_eq!(panes[1].command, "zsh");
+        assert_eq!(panes[1].title, "host");
+    }
+
+    #[test]
+    fn parse_panes_colons_stay_inside_fields() {
+        let output = "%0\t0\t80\t24\t0\t0\t1\tpm:lead\tnode:v20\tbuild: 3/4 done\n";
+        let panes = parse_list_panes(output);
+        assert_eq!(panes[0].agent.as_deref(), Some("pm:lead"));
+        assert_eq!(panes[0].command, "node:v20");
+        assert_eq!(panes[0].title, "build: 3/4 done");
+        assert!(panes[0].active);
+    }
+
     #[test]
     fn parse_panes_empty() {
         assert!(parse_list_panes("").is_empty());
@@ -623,7 +862,7 @@ mod tests {
 
     #[test]
     fn parse_panes_malformed_line() {
-        let output = "%0:0:120\nbadline\n%1:1:60:40:0:60\n";
+        let output = "%0\t0\t120\nbadline\n%1\t1\t60\t40\t0\t60\n";
         let panes = parse_list_panes(output);
         assert_eq!(panes.len(), 1);
         assert_eq!(panes[0].id, "%1");
@@ -640,6 +879,32 @@ mod tests {
         assert_eq!(windows[1].name, "editor");
     }
 
+    #[test]
+    fn parse_windows_layout() {
+        let output = "0:main:2:4f3d,200x50,0,0{100x50,0,0,1,99x50,101,0,2}\n";
+        let windows = parse_list_windows(output);
+        assert_eq!(windows[0].layout, "4f3d,200x50,0,0{100x50,0,0,1,99x50,101,0,2}");
+        assert_eq!(parse_list_windows("1:editor:1\n")[0].layout, "");
+    }
+
+    #[test]
+    fn cmd_new_window() {
+        let b = TmuxCommandBuilder::new();
+        assert_eq!(
+            b.new_window("work:2", "logs", "/tmp"),
+            "tmux new-window -d -P -F '#{window_index}' -t work:2 -n logs -c /tmp"
+        );
+    }
+
+    #[test]
+    fn cmd_select_layout() {
+        let b = TmuxCommandBuilder::new();
+        assert_eq!(
+            b.select_layout("work:0", "b25d,80x24,0,0,0"),
+            "tmux select-layout -t work:0 'b25d,80x24,0,0,0'"
+        );
+    }
+
     #[test]
     fn parse_windows_empty() {
         assert!(parse_list_windows("").is_empty());
@@ -686,76 +951,140 @@ mod tests {
 
     // -- Layout realization tests --
 
+    fn leaf(agent: &str, percent: Option<u32>) -> LayoutEntry {
+        LayoutEntry {
+            node: LayoutNode::pane(agent),
+            percent,
+            size: SizeSpec::default(),
+        }
+    }
+
+    #[test]
+    fn cmd_split_pane_print_id() {
+        let b = TmuxCommandBuilder::new();
+        assert_eq!(
+            b.split_pane_print_id("%1", &Direction::Horizontal, 70),
+            "tmux split-window -t %1 -h -p 70 -P -F '#{pane_id}'"
+        );
+    }
+
     #[test]
     fn realize_single_pane() {
-        let layout = LayoutNode::Pane {
-            agent: "pilot".into(),
-        };
-        let cmds = realize_layout("work", &layout);
-        assert!(cmds.is_empty()); // first pane exists, no splits needed
+        let runner = MockRunner::with_responses(vec![Ok("%0\n".into())]);
+        let layout = LayoutNode::pane("pilot");
+        let realized = realize_layout(&runner, "work", &layout).unwrap();
+        // The target pane already exists, so only its ID is looked up.
+        assert_eq!(
+            realized.commands,
+            vec!["tmux display-message -p -t work '#{pane_id}'"]
+        );
+        assert_eq!(
+            realized.placements,
+            vec![PanePlacement {
+                pane_id: "%0".into(),
+                agent: "pilot".into(),
+            }]
+        );
     }
 
     #[test]
     fn realize_two_column_row() {
+        let runner = MockRunner::with_responses(vec![Ok("%0\n".into()), Ok("%1\n".into())]);
         let layout = LayoutNode::Row {
-            children: vec![
-                LayoutEntry {
-                    node: LayoutNode::Pane {
-                        agent: "pilot".into(),
-                    },
-                    percent: Some(30),
-                },
-                LayoutEntry {
-                    node: LayoutNode::Pane {
-                        agent: "worker".into(),
-                    },
-                    percent: Some(70),
-                },
-            ],
+            children: vec![leaf("pilot", Some(30)), leaf("worker", Some(70))],
         };
-        let cmds = realize_layout("work", &layout);
-        assert_eq!(cmds.len(), 1);
-        assert!(cmds[0].contains("split-window"));
-        assert!(cmds[0].contains("-h"));
-        assert!(cmds[0].contains("-p 70"));
+        let realized = realize_layout(&runner, "work", &layout).unwrap();
+        assert_eq!(
+            realized.commands[1],
+            "tmux split-window -t %0 -h -p 70 -P -F '#{pane_id}'"
+        );
+        let ids: Vec<&str> = realized.placements.iter().map(|p| p.pane_id.as_str()).collect();
+        assert_eq!(ids, vec!["%0", "%1"]);
+    }
+
+    #[test]
+    fn realize_three_way_uses_remaining_space() {
+        let runner = MockRunner::with_responses(vec![
+            Ok("%0".into()),
+            Ok("%1".into()),
+            Ok("%2".into()),
+        ]);
+        let layout = LayoutNode::Row {
+            children: vec![leaf("a", Some(20)), leaf("b", Some(30)), leaf("c", Some(50))],
+        };
+        let realized = realize_layout(&runner, "%0", &layout).unwrap();
+        // 80% of the whole goes right of a; then 50/80 of that right of b.
+        assert_eq!(
+            realized.commands[1..],
+            [
+                "tmux split-window -t %0 -h -p 80 -P -F '#{pane_id}'",
+                "tmux split-window -t %1 -h -p 63 -P -F '#{pane_id}'",
+            ]
+        );
     }
 
     #[test]
-    fn realize_nested_layout() {
+    fn realize_nested_layout_targets_owning_pane() {
+        let runner = MockRunner::with_responses(vec![
+            Ok("%0".into()),
+            Ok("%1".into()),
+            Ok("%2".into()),
+        ]);
         let layout = LayoutNode::Row {
             children: vec![
-                LayoutEntry {
-                    node: LayoutNode::Pane {
-                        agent: "pilot".into(),
-                    },
-                    percent: Some(30),
-                },
+                leaf("pilot", Some(30)),
                 LayoutEntry {
                     node: LayoutNode::Col {
-                        children: vec![
-                            LayoutEntry {
-                                node: LayoutNode::Pane {
-                                    agent: "w1".into(),
-                                },
-                                percent: Some(50),
-                            },
-                            LayoutEntry {
-                                node: LayoutNode::Pane {
-                                    agent: "w2".into(),
-                                },
-                                percent: Some(50),
-                            },
-                        ],
+                        children: vec![leaf("w1", Some(50)), leaf("w2", Some(50))],
                     },
                     percent: Some(70),
+                    size: SizeSpec::default(),
                 },
             ],
         };
-        let cmds = realize_layout("work", &layout);
-        // 1 horizontal split (row child 2), then 1 vertical split (col child 2)
-        assert_eq!(cmds.len(), 2);
-        assert!(cmds[0].contains("-h"));
-        assert!(cmds[1].contains("-v"));
+        let realized = realize_layout(&runner, "work", &layout).unwrap();
+        assert_eq!(
+            realized.commands[1..],
+            [
+                "tmux split-window -t %0 -h -p 70 -P -F '#{pane_id}'",
+                "tmux split-window -t %1 -v -p 50 -P -F '#{pane_id}'",
+            ]
+        );
+        let placed: Vec<(&str, &str)> = realized
+            .placements
+            .iter()
+            .map(|p| (p.pane_id.as_str(), p.agent.as_str()))
+            .collect();
+        assert_eq!(placed, vec![("%0", "pilot"), ("%1", "w1"), ("%2", "w2")]);
+    }
+
+    #[test]
+    fn realize_unset_percentages_share_the_rest() {
+        let runner = MockRunner::with_responses(vec![
+            Ok("%0".into()),
+            Ok("%1".into()),
+            Ok("%2".into()),
+        ]);
+        let layout = LayoutNode::Col {
+            children: vec![leaf("a", Some(40)), leaf("b", None), leaf("c", None)],
+        };
+        let realized = realize_layout(&runner, "%0", &layout).unwrap();
+        assert!(realized.commands[1].contains("-v -p 60"));
+        assert!(realized.commands[2].contains("-v -p 50"));
+    }
+
+    #[test]
+    fn realize_stops_on_failed_split() {
+        let runner = MockRunner::with_responses(vec![
+            Ok("%0".into()),
+            Err("no space for new pane".into()),
+        ]);
+        let layout = LayoutNode::Row {
+            children: vec![leaf("a", None), leaf("b", None), leaf("c", None)],
+        };
+        let err = realize_layout(&runner, "%0", &layout).unwrap_err();
+        assert!(err.contains("no space for new pane"));
+        assert_eq!(runner.executed_commands().len(), 2);
     }
 
     // -- Backend trait tests --
@@ -893,6 +1222,50 @@ mod tests {
         assert_eq!(b.break_pane("%5"), "tmux break-pane -t %5 -d");
     }
 
+    #[test]
+    fn cmd_break_pane_to_window() {
+        let b = TmuxCommandBuilder::new();
+        assert_eq!(b.break_pane_to_window("%5"), "tmux break-pane -d -s %5");
+    }
+
+    #[test]
+    fn cmd_tag_pane() {
+        let b = TmuxCommandBuilder::new();
+        assert_eq!(b.tag_pane("%3", "pm"), "tmux set-option -p -t %3 @mux_agent pm");
+    }
+
+    #[test]
+    fn place_agent_tags_pane() {
+        let b = TmuxCommandBuilder::new();
+        let cmds = action_commands(
+            &b,
+            &Action::PlaceAgent {
+                pane_id: "%2".into(),
+                agent: "worker".into(),
+            },
+        );
+        assert_eq!(cmds, vec!["tmux set-option -p -t %2 @mux_agent worker"]);
+    }
+
+    #[test]
+    fn cmd_swap_panes() {
+        let b = TmuxCommandBuilder::new();
+        assert_eq!(b.swap_panes("%1", "%4"), "tmux swap-pane -d -s %1 -t %4");
+    }
+
+    #[test]
+    fn cmd_resize_pane_to() {
+        let b = TmuxCommandBuilder::new();
+        assert_eq!(
+            b.resize_pane_to("%2", &Direction::Horizontal, 60),
+            "tmux resize-pane -t %2 -x 60"
+        );
+        assert_eq!(
+            b.resize_pane_to("%2", &Direction::Vertical, 12),
+            "tmux resize-pane -t %2 -y 12"
+        );
+    }
+
     #[test]
     fn cmd_select_layout_tiled() {
         let b = TmuxCommandBuilder::new();
@@ -0,0 +1,346 @@
+//! Jobs — commands that need live tmux state.
+//!
+//! Most commands become `Action`s that the backend runs without looking at
+//! the result. Merging, applying, capturing and restoring layouts have to
+//! read tmux between steps, so `Sys` resolves everything it can up front
+//! (parts, snapshots, history) into a `Job`, and whoever owns the backend
+//! runs it through the same runner that executes actions.
+
+use std::collections::HashMap;
+
+use crate::data::parts::PartRegistry;
+use crate::event::now_ms;
+use crate::infrastructure::runner::CommandRunner;
+use crate::infrastructure::tmux::{parse_list_panes, TmuxCommandBuilder};
+use crate::layout::history::HistoryStore;
+use crate::layout::merge::{merge_session, MergeMode};
+use crate::layout::reconcile::{apply_layout, place_agents};
+use crate::layout::restore::{capture_snapshot, restore_session};
+use crate::status::{self, DaemonStatus};
+use crate::types::session::{LayoutNode, SessionSnapshot};
+use cmx_utils::response::Response;
+
+
+/// A queued command that reads tmux while it runs.
+#[derive(Debug, Clone)]
+pub enum Job {
+    Status {
+        daemon: DaemonStatus,
+        config_dir: String,
+        catalog: PartRegistry,
+        catalog_path: Option<String>,
+        last_captures: HashMap<String, u64>,
+        history: Option<HistoryStore>,
+        json: bool,
+        now_ms: u64,
+    },
+    View {
+        catalog: PartRegistry,
+        name: String,
+        diagram: bool,
+    },
+    Merge {
+        session: String,
+        keep: Option<String>,
+        mode: MergeMode,
+    },
+    Apply {
+        session: String,
+        /// The part name or expression as the user wrote it.
+        layout: String,
+        desired: LayoutNode,
+        catalog: PartRegistry,
+    },
+    Place {
+        pane: String,
+        agent: String,
+        /// Starts the agent; `None` when its part declares no type.
+        launch: Option<String>,
+    },
+    Capture {
+        session: String,
+        history: Option<HistoryStore>,
+    },
+    Restore {
+        session: String,
+        snapshot: SessionSnapshot,
+        cwd: String,
+        catalog: PartRegistry,
+    },
+}
+
+
+impl Job {
+    /// Run the job against tmux and produce the command's response.
+    pub fn run(self, runner: &dyn CommandRunner) -> Response {
+        let result = match self {
+            Job::Status {
+                daemon,
+                config_dir,
+                catalog,
+                catalog_path,
+                last_captures,
+                history,
+                json,
+                now_ms,
+            } => {
+                let last_capture = |session: &str| {
+                    last_captures.get(session).copied().or_else(|| {
+                        history
+                            .as_ref()
+                            .and_then(|h| h.entries(session).last().map(|e| e.timestamp_ms))
+                    })
+                };
+                let report = status::collect(
+                    runner,
+                    daemon,
+                    &config_dir,
+                    &catalog,
+                    catalog_path,
+                    &last_capture,
+                );
+                Ok(if json {
+                    serde_json::to_string_pretty(&report).unwrap_or_default()
+                } else {
+                    status::format_text(&report, now_ms)
+                })
+            }
+            Job::View { catalog, name, diagram } => {
+                crate::view::view(runner, &catalog, &name, diagram)
+            }
+            Job::Merge { session, keep, mode } => run_merge(runner, &session, keep, mode),
+            Job::Apply { session, layout, desired, catalog } => {
+                run_apply(runner, &session, &layout, &desired, &catalog)
+            }
+            Job::Place { pane, agent, launch } => run_place(runner, &pane, &agent, launch),
+            Job::Capture { session, history } => run_capture(runner, &session, history),
+            Job::Restore { session, snapshot, cwd, catalog } => {
+                run_restore(runner, &session, &snapshot, &cwd, &catalog)
+            }
+        };
+        match result {
+            Ok(output) => Response::Ok { output },
+            Err(message) => Response::Error { message },
+        }
+    }
+}
+
+
+fn run_merge(
+    runner: &dyn CommandRunner,
+    session: &str,
+    keep: Option<String>,
+    mode: MergeMode,
+) -> Result<String, String> {
+    let plan = merge_session(runner, session, keep.as_deref(), mode)?;
+    if plan.removed.is_empty() {
+        return Ok(format!(
+            "Session '{}' already has a single pane ({})",
+            session, plan.keep
+        ));
+    }
+    let verb = match mode {
+        MergeMode::Break => "broke out",
+        MergeMode::Kill => "killed",
+    };
+    Ok(format!(
+        "Merged '{}' into pane {}; {} {}",
+        session,
+        plan.keep,
+        verb,
+        plan.removed.join(", ")
+    ))
+}
+
+
+/// Reconcile the session's current window toward `desired`, keeping panes
+/// that already match, then start the agents it names.
+fn run_apply(
+    runner: &dyn CommandRunner,
+    session: &str,
+    layout: &str,
+    desired: &LayoutNode,
+    catalog: &PartRegistry,
+) -> Result<String, String> {
+    let report = apply_layout(runner, session, desired)?;
+    let launch = |agent: &str| catalog.launch_command(agent);
+    let placement = place_agents(runner, session, desired, &launch)?;
+    let started = placement_note(&placement.placed, &placement.conflicts);
+    if report.commands.is_empty() {
+        return Ok(format!("Session '{}' already matches {}{}", session, layout, started));
+    }
+    Ok(format!(
+        "Applied {} to '{}' ({} commands in {} passes{}){}",
+        layout,
+        session,
+        report.commands.len(),
+        report.passes,
+        if report.converged { "" } else { "; sizes approximate" },
+        started
+    ))
+}
+
+
+/// Tag `pane` with `agent` and start it, unless the pane is busy with
+/// another agent or program.
+fn run_place(
+    runner: &dyn CommandRunner,
+    pane: &str,
+    agent: &str,
+    launch: Option<String>,
+) -> Result<String, String> {
+    let builder = TmuxCommandBuilder::new();
+    let output = runner
+        .run(&builder.list_panes(pane))
+        .map_err(|e| format!("cannot list pane '{}': {}", pane, e.trim()))?;
+    let live = parse_list_panes(&output)
+        .into_iter()
+        .find(|p| p.id == pane)
+        .ok_or_else(|| format!("no pane '{}'", pane))?;
+    if live.agent.as_deref() == Some(agent) {
+        return Ok(format!("Agent '{}' is already in pane {}", agent, pane));
+    }
+    if !live.at_shell() {
+        return Err(format!(
+            "cannot place {}: {} runs {}",
+            agent,
+            pane,
+            live.agent.as_deref().unwrap_or(&live.command)
+        ));
+    }
+    let mut commands = vec![builder.tag_pane(pane, agent)];
+    if let Some(keys) = launch {
+        commands.push(builder.send_keys(pane, &keys));
+    }
+    for command in &commands {
+        runner
+            .run(command)
+            .map_err(|e| format!("{} (cmd: {})", e.trim(), command))?;
+    }
+    Ok(format!("Agent '{}' placed in pane {}", agent, pane))
+}
+
+
+fn run_capture(
+    runner: &dyn CommandRunner,
+    session: &str,
+    history: Option<HistoryStore>,
+) -> Result<String, String> {
+    let snapshot = capture_snapshot(runner, session, now_ms())?;
+    if let Some(history) = history {
+        history.append(&snapshot)?;
+    }
+    Ok(serde_json::to_string(&snapshot).unwrap_or_default())
+}
+
+
+fn run_restore(
+    runner: &dyn CommandRunner,
+    session: &str,
+    snapshot: &SessionSnapshot,
+    cwd: &str,
+    catalog: &PartRegistry,
+) -> Result<String, String> {
+    let launch = |agent: &str| catalog.launch_command(agent);
+    let report = restore_session(runner, snapshot, session, cwd, &launch)?;
+    let placed = placement_note(&report.placed, &report.conflicts);
+    Ok(format!(
+        "Restored '{}' with {} windows{}{}",
+        session,
+        report.windows.len(),
+        if report.created_session { " (new session)" } else { "" },
+        placed
+    ))
+}
+
+
+/// `; placed a, b; skipped c (%1 runs d)`, or nothing when no agent moved.
+fn placement_note(placed: &[String], conflicts: &[String]) -> String {
+    let mut note = String::new();
+    if !placed.is_empty() {
+        note.push_str(&format!("; placed {}", placed.join(", ")));
+    }
+    if !conflicts.is_empty() {
+        note.push_str(&format!("; skipped {}", conflicts.join(", ")));
+    }
+    note
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::layout_expr::parse_layout_expr;
+    use crate::infrastructure::runner::MockRunner;
+
+    #[test]
+    fn apply_launches_new_agents_from_the_catalog() {
+        let listing = "%0\t0\t40\t24\t0\t0\t1\ta\tzsh\t\n%1\t1\t39\t24\t0\t41\t0\t\tzsh\t\n";
+        let runner = MockRunner::with_responses(vec![Ok(listing.into()), Ok(listing.into())]);
+        let job = Job::Apply {
+            session: "work".into(),
+            layout: "ROW(a, b)".into(),
+            desired: parse_layout_expr("ROW(a, b)").unwrap(),
+            catalog: PartRegistry::from_markdown("## b\nrole: b\ntype: claude\n"),
+        };
+        match job.run(&runner) {
+            Response::Ok { output } => assert!(output.ends_with("placed b")),
+            Response::Error { message } => panic!("Unexpected error: {}", message),
+        }
+        let commands = runner.executed_commands();
+        assert_eq!(commands[2], "tmux set-option -p -t %1 @mux_agent b");
+        assert_eq!(commands[3], "tmux send-keys -t %1 claude Enter");
+    }
+
+    #[test]
+    fn apply_reports_busy_panes() {
+        let listing = "%0\t0\t40\t24\t0\t0\t1\ta\tzsh\t\n%1\t1\t39\t24\t0\t41\t0\tc\tvim\t\n";
+        let runner = MockRunner::with_responses(vec![Ok(listing.into()), Ok(listing.into())]);
+        let job = Job::Apply {
+            session: "work".into(),
+            layout: "ROW(a, b)".into(),
+            desired: parse_layout_expr("ROW(a, b)").unwrap(),
+            catalog: PartRegistry::from_markdown("## b\nrole: b\ntype: claude\n"),
+        };
+        match job.run(&runner) {
+            Response::Ok { output } => assert!(output.ends_with("skipped b (%1 runs c)")),
+            Response::Error { message } => panic!("Unexpected error: {}", message),
+        }
+        assert_eq!(runner.executed_commands().len(), 2);
+    }
+
+    #[test]
+    fn place_tags_and_launches_at_a_shell() {
+        let listing = "%0\t0\t40\t24\t0\t0\t1\ta\tzsh\t\n%3\t1\t39\t24\t0\t41\t0\t\tbash\t\n";
+        let runner = MockRunner::with_responses(vec![Ok(listing.into())]);
+        let job = Job::Place {
+            pane: "%3".into(),
+            agent: "w1".into(),
+            launch: Some("claude".into()),
+        };
+        match job.run(&runner) {
+            Response::Ok { output } => assert_eq!(output, "Agent 'w1' placed in pane %3"),
+            Response::Error { message } => panic!("Unexpected error: {}", message),
+        }
+        assert_eq!(
+            runner.executed_commands()[1..],
+            ["tmux set-option -p -t %3 @mux_agent w1", "tmux send-keys -t %3 claude Enter"]
+        );
+    }
+
+    #[test]
+    fn place_refuses_busy_panes() {
+        let listing = "%3\t0\t80\t24\t0\t0\t1\t\tvim\t\n";
+        let runner = MockRunner::with_responses(vec![Ok(listing.into())]);
+        let job = Job::Place {
+            pane: "%3".into(),
+            agent: "w1".into(),
+            launch: Some("claude".into()),
+        };
+        match job.run(&runner) {
+            Response::Error { message } => assert_eq!(message, "cannot place w1: %3 runs vim"),
+            Response::Ok { output } => panic!("Unexpected success: {}", output),
+        }
+        assert_eq!(runner.executed_commands().len(), 1);
+    }
+}
@@ -0,0 +1,621 @@
+//! Layout algebra — pure transformations of `LayoutNode` trees.
+//!
+//! Whole-tree operations return a new tree: `normalize` flattens nested
+//! splits in the same direction and writes out every share as a percentage,
+//! `transpose`, `rotate`, and `mirror` move panes around without changing
+//! their sizes, and `equalize` gives every child of a split the same share.
+//!
+//! Edits address one node by its path of child indices from the root, as
+//! found by `find_leaf`: `[1, 0]` is the first child of the root's second
+//! child. `replace_leaf`, `insert_sibling`, and `remove_leaf` keep the rest
+//! of the layout's geometry as it was.
+
+use cmx_utils::response::Direction;
+
+use crate::infrastructure::tmux::child_weights;
+use crate::layout::solver::round_to;
+use crate::types::session::{LayoutEntry, LayoutNode, SizeSpec};
+
+
+/// Flatten splits nested in the same direction and give every child an
+/// explicit percentage, keeping each pane's share of the window. A split
+/// with a single child is replaced by that child.
+///
+/// Splits that size a child in cells are left as written, since their
+/// shares depend on the window size; their children are still normalized.
+pub fn normalize(node: &LayoutNode) -> LayoutNode {
+    let Some((row, children)) = split(node) else {
+        return node.clone();
+    };
+    let entries: Vec<LayoutEntry> = children
+        .iter()
+        .map(|e| LayoutEntry {
+            node: normalize(&e.node),
+            ..e.clone()
+        })
+        .collect();
+    if let [only] = entries.as_slice() {
+        if only.size.is_empty() {
+            return only.node.clone();
+        }
+    }
+    if entries.iter().any(|e| e.size.cells.is_some()) {
+        return group(row, entries);
+    }
+
+    let weights = child_weights(&entries);
+    let total: f64 = weights.iter().sum();
+    let mut flat: Vec<(LayoutEntry, f64)> = Vec::new();
+    for (entry, weight) in entries.into_iter().zip(weights) {
+        let share = weight / total;
+        match split(&entry.node) {
+            Some((inner_row, inner))
+                if inner_row == row
+                    && entry.size.is_empty()
+                    && inner.iter().all(|e| e.size.cells.is_none()) =>
+            {
+                let inner_weights = child_weights(inner);
+                let inner_total: f64 = inner_weights.iter().sum();
+                for (e, w) in inner.iter().zip(inner_weights) {
+                    flat.push((e.clone(), share * w / inner_total));
+                }
+            }
+            _ => flat.push((entry, share)),
+        }
+    }
+    let shares: Vec<f64> = flat.iter().map(|(_, s)| s * 100.0).collect();
+    let entries = flat
+        .into_iter()
+        .zip(round_to(&shares, 100))
+        .map(|((e, _), p)| LayoutEntry {
+            percent: Some(p),
+            ..e
+        })
+        .collect();
+    group(row, entries)
+}
+
+
+/// Swap ROW and COL throughout, so side-by-side panes become stacked ones.
+/// Shares and cell sizes keep their numbers.
+pub fn transpose(node: &LayoutNode) -> LayoutNode {
+    match node {
+        LayoutNode::Pane { .. } => node.clone(),
+        LayoutNode::Row { children } => LayoutNode::Col {
+            children: map_entries(children, transpose),
+        },
+        LayoutNode::Col { children } => LayoutNode::Row {
+            children: map_entries(children, transpose),
+        },
+    }
+}
+
+
+/// Turn the layout a quarter turn. Clockwise, the left pane of a ROW ends
+/// up on top and the top pane of a COL ends up on the right.
+pub fn rotate(node: &LayoutNode, clockwise: bool) -> LayoutNode {
+    let turn = |n: &LayoutNode| rotate(n, clockwise);
+    match node {
+        LayoutNode::Pane { .. } => node.clone(),
+        LayoutNode::Row { children } => {
+            let mut children = map_entries(children, turn);
+            if !clockwise {
+                children.reverse();
+            }
+            LayoutNode::Col { children }
+        }
+        LayoutNode::Col { children } => {
+            let mut children = map_entries(children, turn);
+            if clockwise {
+                children.reverse();
+            }
+            LayoutNode::Row { children }
+        }
+    }
+}
+
+
+/// Mirror the layout: `Horizontal` swaps left and right (reversing every
+/// ROW), `Vertical` swaps top and bottom (reversing every COL).
+pub fn mirror(node: &LayoutNode, axis: &Direction) -> LayoutNode {
+    let flip = |n: &LayoutNode| mirror(n, axis);
+    match node {
+        LayoutNode::Pane { .. } => node.clone(),
+        LayoutNode::Row { children } => {
+            let mut children = map_entries(children, flip);
+            if matches!(axis, Direction::Horizontal) {
+                children.reverse();
+            }
+            LayoutNode::Row { children }
+        }
+        LayoutNode::Col { children } => {
+            let mut children = map_entries(children, flip);
+            if matches!(axis, Direction::Vertical) {
+                children.reverse();
+            }
+            LayoutNode::Col { children }
+        }
+    }
+}
+
+
+/// Give every child of every split the same percentage. Cell sizes are
+/// dropped; bounds are kept.
+pub fn equalize(node: &LayoutNode) -> LayoutNode {
+    let Some((row, children)) = split(node) else {
+        return node.clone();
+    };
+    let equal = vec![100.0 / children.len() as f64; children.len()];
+    let entries = children
+        .iter()
+        .zip(round_to(&equal, 100))
+        .map(|(e, p)| LayoutEntry {
+            node: equalize(&e.node),
+            percent: Some(p),
+            size: SizeSpec {
+                cells: None,
+                ..e.size
+            },
+        })
+        .collect();
+    group(row, entries)
+}
+
+
+/// Path to the first leaf showing `agent`, in reading order.
+pub fn find_leaf(node: &LayoutNode, agent: &str) -> Option<Vec<usize>> {
+    match node {
+        LayoutNode::Pane { agent: name, .. } => (name == agent).then(Vec::new),
+        LayoutNode::Row { children } | LayoutNode::Col { children } => {
+            children.iter().enumerate().find_map(|(i, e)| {
+                let mut path = find_leaf(&e.node, agent)?;
+                path.insert(0, i);
+                Some(path)
+            })
+        }
+    }
+}
+
+
+/// The node at `path`, if there is one.
+pub fn node_at<'a>(node: &'a LayoutNode, path: &[usize]) -> Option<&'a LayoutNode> {
+    match path.split_first() {
+        None => Some(node),
+        Some((&i, rest)) => node_at(&split(node)?.1.get(i)?.node, rest),
+    }
+}
+
+
+/// Put `with` where the leaf at `path` is. It takes over the leaf's share.
+pub fn replace_leaf(
+    node: &LayoutNode,
+    path: &[usize],
+    with: &LayoutNode,
+) -> Result<LayoutNode, String> {
+    let mut out = node.clone();
+    let target = node_at_mut(&mut out, path)?;
+    if !matches!(target, LayoutNode::Pane { .. }) {
+        return Err(format!("{} is a split, not a leaf", describe(path)));
+    }
+    *target = with.clone();
+    Ok(out)
+}
+
+
+/// Add `sibling` next to the node at `path`, before or after it. The new
+/// child takes half of that node's percentage or cell size, so the other
+/// children keep theirs.
+pub fn insert_sibling(
+    node: &LayoutNode,
+    path: &[usize],
+    sibling: &LayoutNode,
+    after: bool,
+) -> Result<LayoutNode, String> {
+    let (&index, parent_path) = path
+        .split_last()
+        .ok_or_else(|| "the root has no siblings".to_string())?;
+    let mut out = node.clone();
+    let children = children_mut(node_at_mut(&mut out, parent_path)?, path)?;
+    let target = children
+        .get_mut(index)
+        .ok_or_else(|| format!("no node at {}", describe(path)))?;
+    let half = |n: Option<u32>| n.map(|n| n / 2).filter(|h| *h > 0);
+    let entry = LayoutEntry {
+        node: sibling.clone(),
+        percent: half(target.percent),
+        size: SizeSpec {
+            cells: half(target.size.cells),
+            ..SizeSpec::default()
+        },
+    };
+    if let Some(taken) = entry.percent {
+        target.percent = target.percent.map(|p| p - taken);
+    }
+    if let Some(taken) = entry.size.cells {
+        target.size.cells = target.size.cells.map(|c| c - taken);
+    }
+    children.insert(if after { index + 1 } else { index }, entry);
+    Ok(out)
+}
+
+
+/// Take out the leaf at `path`. Its percentage goes to its siblings in
+/// proportion to theirs, and a split left with one child is replaced by it.
+/// A split whose only child is the leaf goes with it.
+pub fn remove_leaf(node: &LayoutNode, path: &[usize]) -> Result<LayoutNode, String> {
+    match node_at(node, path) {
+        Some(LayoutNode::Pane { .. }) => {}
+        Some(_) => return Err(format!("{} is a split, not a leaf", describe(path))),
+        None => return Err(format!("no node at {}", describe(path))),
+    }
+    let mut path = path;
+    while let Some((_, parent_path)) = path.split_last() {
+        match node_at(node, parent_path).and_then(split) {
+            Some((_, siblings)) if siblings.len() == 1 => path = parent_path,
+            _ => break,
+        }
+    }
+    let (&index, parent_path) = path
+        .split_last()
+        .ok_or_else(|| "cannot remove the only pane of a layout".to_string())?;
+    let mut out = node.clone();
+    let parent = node_at_mut(&mut out, parent_path)?;
+    let children = children_mut(parent, path)?;
+    let removed = children.remove(index);
+    let kept: Option<Vec<f64>> = children.iter().map(|e| e.percent.map(f64::from)).collect();
+    if let (Some(freed), Some(kept)) = (removed.percent, kept) {
+        let sum: f64 = kept.iter().sum();
+        let total = sum + f64::from(freed);
+        if sum > 0.0 {
+            let scaled: Vec<f64> = kept.iter().map(|p| p * total / sum).collect();
+            for (entry, p) in children.iter_mut().zip(round_to(&scaled, total as u32)) {
+                entry.percent = Some(p);
+            }
+        }
+    }
+    if children.len() == 1 {
+        *parent = children.remove(0).node;
+    }
+    Ok(out)
+}
+
+
+/// Direction and children of a ROW (`true`) or COL (`false`).
+pub(crate) fn split(node: &LayoutNode) -> Option<(bool, &Vec<LayoutEntry>)> {
+    match node {
+        LayoutNode::Row { children } => Some((true, children)),
+        LayoutNode::Col { children } => Some((false, children)),
+        LayoutNode::Pane { .. } => None,
+    }
+}
+
+
+fn group(row: bool, children: Vec<LayoutEntry>) -> LayoutNode {
+    if row {
+        LayoutNode::Row { children }
+    } else {
+        LayoutNode::Col { children }
+    }
+}
+
+
+fn map_entries(
+    children: &[LayoutEntry],
+    f: impl Fn(&LayoutNode) -> LayoutNode,
+) -> Vec<LayoutEntry> {
+    children
+        .iter()
+        .map(|e| LayoutEntry {
+            node: f(&e.node),
+            ..e.clone()
+        })
+        .collect()
+}
+
+
+fn node_at_mut<'a>(node: &'a mut LayoutNode, path: &[usize]) -> Result<&'a mut LayoutNode, String> {
+    let mut current = node;
+    for (depth, &i) in path.iter().enumerate() {
+        current = match current {
+            LayoutNode::Row { children } | LayoutNode::Col { children } => children
+                .get_mut(i)
+                .map(|e| &mut e.node)
+                .ok_or_else(|| format!("no node at {}", describe(&path[..=depth])))?,
+            LayoutNode::Pane { .. } => {
+                return Err(format!(
+                    "{} is a leaf, not a split",
+                    describe(&path[..depth])
+                ))
+            }
+        };
+    }
+    Ok(current)
+}
+
+
+/// Children of the split `parent`; `path` names the child being edited.
+fn children_mut<'a>(
+    parent: &'a mut LayoutNode,
+    path: &[usize],
+) -> Result<&'a mut Vec<LayoutEntry>, String> {
+    match parent {
+        LayoutNode::Row { children } | LayoutNode::Col { children } => Ok(children),
+        LayoutNode::Pane { .. } => Err(format!("no node at {}", describe(path))),
+    }
+}
+
+
+/// `root`, or a path written `1.0`.
+pub(crate) fn describe(path: &[usize]) -> String {
+    if path.is_empty() {
+        return "root".to_string();
+    }
+    let parts: Vec<String> = path.iter().map(|i| i.to_string()).collect();
+    parts.join(".")
+}
+
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::layout_expr::{parse_layout_expr, serialize_layout_expr};
+    use crate::types::session::PaneOptions;
+
+    fn layout(expr: &str) -> LayoutNode {
+        parse_layout_expr(expr).unwrap()
+    }
+
+    fn expr(node: &LayoutNode) -> String {
+        serialize_layout_expr(node)
+    }
+
+    fn agents(node: &LayoutNode) -> Vec<String> {
+        match node {
+            LayoutNode::Pane { agent, .. } => vec![agent.clone()],
+            LayoutNode::Row { children } | LayoutNode::Col { children } => {
+                children.iter().flat_map(|e| agents(&e.node)).collect()
+            }
+        }
+    }
+
+    #[test]
+    fn normalize_flattens_and_fills_percents() {
+        let node = layout("ROW(a 40%, ROW(b, c 75%), COL(d))");
+        assert_eq!(expr(&normalize(&node)), "ROW(a 40%, b 8%, c 22%, d 30%)");
+        assert_eq!(
+            expr(&normalize(&layout("ROW(a, b, c)"))),
+            "ROW(a 34%, b 33%, c 33%)"
+        );
+        assert_eq!(expr(&normalize(&layout("COL(ROW(a))"))), "a");
+    }
+
+    #[test]
+    fn normalize_leaves_cell_sizes_alone() {
+        let node = layout("ROW(side 40c, ROW(a, b), COL(c, COL(d, e)))");
+        assert_eq!(
+            expr(&normalize(&node)),
+            "ROW(side 40c, ROW(a 50%, b 50%), COL(c 50%, d 25%, e 25%))"
+        );
+    }
+
+    #[test]
+    fn transpose_rotate_and_mirror() {
+        let node = layout("ROW(a 30%, COL(b, c) 70%)");
+        assert_eq!(expr(&transpose(&node)), "COL(a 30%, ROW(b, c) 70%)");
+        assert_eq!(expr(&rotate(&node, true)), "COL(a 30%, ROW(c, b) 70%)");
+        assert_eq!(expr(&rotate(&node, false)), "COL(ROW(b, c) 70%, a 30%)");
+        assert_eq!(
+            expr(&mirror(&node, &Direction::Horizontal)),
+            "ROW(COL(b, c) 70%, a 30%)"
+        );
+        assert_eq!(
+            expr(&mirror(&node, &Direction::Vertical)),
+            "ROW(a 30%, COL(c, b) 70%)"
+        );
+    }
+
+    #[test]
+    fn equalize_every_split() {
+        let node = layout("COL(a 10%, ROW(b 20c, c >=5c) 90%, d)");
+        assert_eq!(
+            expr(&equalize(&node)),
+            "COL(a 34%, ROW(b 50%, c 50% >=5c) 33%, d 33%)"
+        );
+    }
+
+    #[test]
+    fn find_and_replace_leaf() {
+        let node = layout("ROW(a, COL(b, c) 60%)");
+        let path = find_leaf(&node, "c").unwrap();
+        assert_eq!(path, vec![1, 1]);
+        assert_eq!(node_at(&node, &path), Some(&LayoutNode::pane("c")));
+        let replaced = replace_leaf(&node, &path, &layout("ROW(x, y)")).unwrap();
+        assert_eq!(expr(&replaced), "ROW(a, COL(b, ROW(x, y)) 60%)");
+        assert!(find_leaf(&node, "z").is_none());
+        assert!(replace_leaf(&node, &[1], &LayoutNode::pane("x")).is_err());
+        assert!(replace_leaf(&node, &[0, 2], &LayoutNode::pane("x")).is_err());
+    }
+
+    #[test]
+    fn insert_sibling_halves_the_share() {
+        let node = layout("ROW(a 30%, b 70%)");
+        let grown = insert_sibling(&node, &[1], &LayoutNode::pane("c"), true).unwrap();
+        assert_eq!(expr(&grown), "ROW(a 30%, b 35%, c 35%)");
+        let grown = insert_sibling(
+            &layout("ROW(a 41c, b)"),
+            &[0],
+            &LayoutNode::pane("c"),
+            false,
+        )
+        .unwrap();
+        assert_eq!(expr(&grown), "ROW(c 20c, a 21c, b)");
+        assert!(insert_sibling(&node, &[], &LayoutNode::pane("c"), true).is_err());
+    }
+
+    #[test]
+    fn remove_leaf_redistributes_and_collapses() {
+        let node = layout("ROW(a 20%, b 30%, c 50%)");
+        assert_eq!(
+            expr(&remove_leaf(&node, &[0]).unwrap()),
+            "ROW(b 38%, c 62%)"
+        );
+        let node = layout("ROW(a 40%, COL(b, c) 60%)");
+        assert_eq!(
+            expr(&remove_leaf(&node, &[1, 0]).unwrap()),
+            "ROW(a 40%, c 60%)"
+        );
+        assert_eq!(expr(&remove_leaf(&layout("ROW(a, b)"), &[1]).unwrap()), "a");
+        assert_eq!(
+            expr(&remove_leaf(&layout("ROW(a, COL(b))"), &[1, 0]).unwrap()),
+            "a"
+        );
+        assert!(remove_leaf(&node, &[1]).is_err());
+        assert!(remove_leaf(&layout("ROW(COL(a))"), &[0, 0]).is_err());
+    }
+
+    // -- Property tests over generated layouts ------------------------------
+
+    /// xorshift64, so every run checks the same layouts.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: u64) -> u64 {
+            self.next() % n
+        }
+
+        fn chance(&mut self, percent: u64) -> bool {
+            self.below(100) < percent
+        }
+    }
+
+    const NAMES: &[&str] = &["pm", "worker", "logs", "build", "shell two", "w1"];
+
+    fn gen_node(rng: &mut Rng, depth: u32) -> LayoutNode {
+        if depth == 0 || rng.chance(35) {
+            let mut options = PaneOptions::default();
+            if rng.chance(15) {
+                options.cmd = Some("echo \"a, b\" \\ ok".into());
+            }
+            if rng.chance(10) {
+                options.focus = true;
+            }
+            return LayoutNode::Pane {
+                agent: NAMES[rng.below(NAMES.len() as u64) as usize].into(),
+                options,
+            };
+        }
+        let count = 1 + rng.below(4) as usize;
+        let children = (0..count)
+            .map(|_| {
+                let percent = rng.chance(60).then(|| 1 + rng.below(100) as u32);
+                let mut size = SizeSpec::default();
+                if percent.is_none() && rng.chance(20) {
+                    size.cells = Some(1 + rng.below(80) as u32);
+                }
+                if rng.chance(10) {
+                    size.min = Some(1 + rng.below(20) as u32);
+                }
+                if rng.chance(10) {
+                    size.max = Some(20 + rng.below(80) as u32);
+                }
+                LayoutEntry {
+                    node: gen_node(rng, depth - 1),
+                    percent,
+                    size,
+                }
+            })
+            .collect();
+        group(rng.chance(50), children)
+    }
+
+    fn layouts() -> Vec<LayoutNode> {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        (0..300).map(|_| gen_node(&mut rng, 4)).collect()
+    }
+
+    fn assert_round_trips(node: &LayoutNode) {
+        let text = expr(node);
+        assert_eq!(&layout(&text), node, "{} does not round-trip", text);
+    }
+
+    #[test]
+    fn every_operation_round_trips() {
+        for node in layouts() {
+            assert_round_trips(&node);
+            assert_round_trips(&normalize(&node));
+            assert_round_trips(&transpose(&node));
+            assert_round_trips(&rotate(&node, true));
+            assert_round_trips(&mirror(&node, &Direction::Vertical));
+            assert_round_trips(&equalize(&node));
+            let many = agents(&node).len() > 1;
+            if let Some(path) = find_leaf(&node, "pm").filter(|_| many) {
+                assert_round_trips(&remove_leaf(&node, &path).unwrap());
+                let extra = LayoutNode::pane("extra");
+                assert_round_trips(&insert_sibling(&node, &path, &extra, true).unwrap());
+                assert_round_trips(&replace_leaf(&node, &path, &layout("COL(x, y)")).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn normalize_is_idempotent_and_keeps_panes() {
+        for node in layouts() {
+            let once = normalize(&node);
+            assert_eq!(normalize(&once), once, "{}", expr(&node));
+            assert_eq!(agents(&once), agents(&node));
+        }
+    }
+
+    #[test]
+    fn symmetries_undo_themselves() {
+        for node in layouts() {
+            assert_eq!(transpose(&transpose(&node)), node);
+            assert_eq!(rotate(&rotate(&node, true), false), node);
+            let quarter = |n: &LayoutNode| rotate(n, true);
+            assert_eq!(quarter(&quarter(&quarter(&quarter(&node)))), node);
+            for axis in [Direction::Horizontal, Direction::Vertical] {
+                assert_eq!(mirror(&mirror(&node, &axis), &axis), node);
+            }
+        }
+    }
+
+    #[test]
+    fn edits_change_one_pane() {
+        for node in layouts() {
+            let many = agents(&node).len() > 1;
+            let Some(path) = find_leaf(&node, "pm").filter(|_| many) else {
+                continue;
+            };
+            let mut expected = agents(&node);
+            let at = expected.iter().position(|a| a == "pm").unwrap();
+            expected.remove(at);
+            assert_eq!(agents(&remove_leaf(&node, &path).unwrap()), expected);
+            expected.insert(at, "pm".into());
+            expected.insert(at + 1, "extra".into());
+            let grown = insert_sibling(&node, &path, &LayoutNode::pane("extra"), true).unwrap();
+            assert_eq!(agents(&grown), expected);
+        }
+    }
+
+    #[test]
+    fn equalized_splits_add_up_to_100() {
+        fn check(node: &LayoutNode) {
+            if let Some((_, children)) = split(node) {
+                assert_eq!(children.iter().filter_map(|e| e.percent).sum::<u32>(), 100);
+                children.iter().for_each(|e| check(&e.node));
+            }
+        }
+        layouts().iter().for_each(|node| check(&equalize(node)));
+    }
+}
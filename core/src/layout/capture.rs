@@ -2,13 +2,17 @@
 //!
 //! Wires together the tmux pane parser, the snapshot reconstruction algorithm,
 //! and the layout expression serializer to produce a `CaptureResult` that
-//! indicates whether the layout has changed since the last capture.
+//! says how the layout has changed since the last capture, ignoring
+//! percentage jitter up to a tolerance. When tmux's own `#{window_layout}`
+//! string is available, `capture_window` uses it instead of the geometry
+//! heuristics, so the capture is exact.
 
 use std::collections::HashMap;
 
 use crate::data::layout_expr;
 use crate::infrastructure::tmux;
-use crate::layout::snapshot;
+use crate::layout::snapshot::{self, LayoutDiff};
+use crate::layout::tmux_layout;
 use crate::types::session::LayoutNode;
 
 
@@ -18,20 +22,27 @@ pub struct CaptureResult {
     pub session: String,
     pub layout: LayoutNode,
     pub layout_expr: String,
+    /// Whether `diff` found anything, or there was no previous layout.
     pub changed: bool,
+    /// What differs from the previous layout; `None` when there was none.
+    pub diff: Option<LayoutDiff>,
     pub timestamp_ms: u64,
+    /// The native tmux layout string, when the capture was made from one.
+    pub tmux_layout: Option<String>,
 }
 
 
 /// Capture the current tmux layout for a session.
 ///
 /// Parses pane geometry from `pane_output`, reconstructs the ROW/COL tree,
-/// serializes it to a layout expression string, and compares against the
-/// previous known layout expression.
+/// serializes it to a layout expression string, and diffs it against the
+/// previous known layout, ignoring share changes of up to `tolerance`
+/// percentage points.
 pub fn capture_session(
     session: &str,
     pane_output: &str,
-    previous_expr: Option<&str>,
+    previous: Option<&LayoutNode>,
+    tolerance: u32,
     now_ms: u64,
 ) -> Result<CaptureResult, String> {
     // 1. Parse tmux list-panes output into TmuxPane structs
@@ -40,20 +51,58 @@ pub fn capture_session(
         return Err(format!("No panes found for session '{}'", session));
     }
     // 2. Reconstruct layout tree
-    let layout = snapshot::from_panes(&panes);
+    let layout = snapshot::from_panes(&panes)
+        .map_err(|e| format!("cannot reconstruct the layout of '{}': {}", session, e))?;
     // 3. Serialize to expression string
     let layout_expr_str = layout_expr::serialize_layout_expr(&layout);
     // 4. Compare against previous
-    let changed = match previous_expr {
-        Some(prev) => prev != layout_expr_str,
-        None => true,
+    let (diff, changed) = compare(previous, &layout, tolerance);
+    Ok(CaptureResult {
+        session: session.to_string(),
+        layout,
+        layout_expr: layout_expr_str,
+        changed,
+        diff,
+        timestamp_ms: now_ms,
+        tmux_layout: None,
+    })
+}
+
+
+/// Capture a window exactly from its `#{window_layout}` string.
+///
+/// The layout string gives the tree and sizes; `pane_output` (from
+/// `list_panes`) maps each pane number back to its agent tag. The change
+/// check diffs against `previous`, as `capture_session` does.
+pub fn capture_window(
+    session: &str,
+    window_layout: &str,
+    pane_output: &str,
+    previous: Option<&LayoutNode>,
+    tolerance: u32,
+    now_ms: u64,
+) -> Result<CaptureResult, String> {
+    let cell = tmux_layout::parse_tmux_layout(window_layout)?;
+    let panes = tmux::parse_list_panes(pane_output);
+    let agent = |number: u32| {
+        let id = format!("%{}", number);
+        panes
+            .iter()
+            .find(|p| p.id == id)
+            .and_then(|p| p.agent.clone())
+            .unwrap_or_default()
     };
+    let layout = tmux_layout::to_layout_node(&cell, &agent);
+    let layout_expr_str = layout_expr::serialize_layout_expr(&layout);
+    let (diff, changed) = compare(previous, &layout, tolerance);
     Ok(CaptureResult {
         session: session.to_string(),
         layout,
         layout_expr: layout_expr_str,
         changed,
+        diff,
         timestamp_ms: now_ms,
+        tmux_layout: Some(window_layout.trim().to_string()),
     })
 }
 
@@ -61,25 +110,38 @@ pub fn capture_session(
 /// Capture all sessions and return results.
 ///
 /// Iterates over the given session names, looks up their pane output and
-/// previous layout expression, and runs `capture_session` for each.
+/// previous layout, and runs `capture_session` for each.
 /// Sessions without pane output are silently skipped.
 pub fn capture_all_sessions(
     sessions: &[String],
     pane_outputs: &HashMap<String, String>,
-    previous_layouts: &HashMap<String, String>,
+    previous_layouts: &HashMap<String, LayoutNode>,
+    tolerance: u32,
     now_ms: u64,
 ) -> Vec<CaptureResult> {
     sessions
         .iter()
         .filter_map(|s| {
             let output = pane_outputs.get(s)?;
-            let prev = previous_layouts.get(s).map(|s| s.as_str());
-            capture_session(s, output, prev, now_ms).ok()
+            let prev = previous_layouts.get(s);
+            capture_session(s, output, prev, tolerance, now_ms).ok()
         })
         .collect()
 }
 
 
+/// Diff `layout` against `previous`; no previous layout counts as a change.
+fn compare(
+    previous: Option<&LayoutNode>,
+    layout: &LayoutNode,
+    tolerance: u32,
+) -> (Option<LayoutDiff>, bool) {
+    let diff = previous.map(|prev| snapshot::diff(prev, layout, tolerance));
+    let changed = diff.as_ref().is_none_or(|d| !d.is_empty());
+    (diff, changed)
+}
+
+
 // ---------------------------------------------------------------------------
 // Tests
 // ---------------------------------------------------------------------------
@@ -90,20 +152,20 @@ mod tests {
     use crate::types::session::LayoutNode;
 
     // Helper: build a pane line in the format parse_list_panes expects
-    // Format: %id:index:width:height:top:left
+    // Format: %id<TAB>index<TAB>width<TAB>height<TAB>top<TAB>left
     fn pane_line(id: &str, index: u32, w: u32, h: u32, top: u32, left: u32) -> String {
-        format!("{}:{}:{}:{}:{}:{}", id, index, w, h, top, left)
+        format!("{}\t{}\t{}\t{}\t{}\t{}", id, index, w, h, top, left)
     }
 
     #[test]
     fn single_pane_session_produces_leaf() {
         let output = pane_line("%0", 0, 120, 40, 0, 0);
-        let result = capture_session("test", &output, None, 1000).unwrap();
+        let result = capture_session("test", &output, None, 2, 1000).unwrap();
         assert!(result.changed);
         assert_eq!(result.session, "test");
         assert_eq!(result.timestamp_ms, 1000);
         match &result.layout {
-            LayoutNode::Pane { agent } => assert_eq!(agent, ""),
+            LayoutNode::Pane { agent, .. } => assert_eq!(agent, ""),
             other => panic!("expected Pane, got {:?}", other),
         }
     }
@@ -115,7 +177,7 @@ mod tests {
             pane_line("%0", 0, 60, 40, 0, 0),
             pane_line("%1", 1, 60, 40, 0, 60),
         );
-        let result = capture_session("test", &output, None, 1000).unwrap();
+        let result = capture_session("test", &output, None, 2, 1000).unwrap();
         assert!(result.changed);
         assert!(result.layout_expr.contains("ROW"));
         match &result.layout {
@@ -131,7 +193,7 @@ mod tests {
             pane_line("%0", 0, 120, 20, 0, 0),
             pane_line("%1", 1, 120, 20, 20, 0),
         );
-        let result = capture_session("test", &output, None, 1000).unwrap();
+        let result = capture_session("test", &output, None, 2, 1000).unwrap();
         assert!(result.changed);
         assert!(result.layout_expr.contains("COL"));
         match &result.layout {
@@ -149,7 +211,7 @@ mod tests {
             pane_line("%1", 1, 60, 20, 0, 60),
             pane_line("%2", 2, 120, 20, 20, 0),
         );
-        let result = capture_session("test", &output, None, 1000).unwrap();
+        let result = capture_session("test", &output, None, 2, 1000).unwrap();
         assert!(result.changed);
         match &result.layout {
             LayoutNode::Col { children } => {
@@ -171,7 +233,7 @@ mod tests {
 
     #[test]
     fn no_panes_returns_error() {
-        let result = capture_session("test", "", None, 1000);
+        let result = capture_session("test", "", None, 2, 1000);
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("No panes"));
     }
@@ -179,9 +241,9 @@ mod tests {
     #[test]
     fn same_layout_changed_is_false() {
         let output = pane_line("%0", 0, 120, 40, 0, 0);
-        let first = capture_session("test", &output, None, 1000).unwrap();
+        let first = capture_session("test", &output, None, 2, 1000).unwrap();
         let second =
-            capture_session("test", &output, Some(&first.layout_expr), 2000).unwrap();
+            capture_session("test", &output, Some(&first.layout), 2, 2000).unwrap();
         assert!(!second.changed);
     }
 
@@ -193,16 +255,55 @@ mod tests {
             pane_line("%0", 0, 60, 40, 0, 0),
             pane_line("%1", 1, 60, 40, 0, 60),
         );
-        let first = capture_session("test", &output1, None, 1000).unwrap();
+        let first = capture_session("test", &output1, None, 2, 1000).unwrap();
         let second =
-            capture_session("test", &output2, Some(&first.layout_expr), 2000).unwrap();
+            capture_session("test", &output2, Some(&first.layout), 2, 2000).unwrap();
         assert!(second.changed);
     }
 
+    #[test]
+    fn resize_within_tolerance_is_not_a_change() {
+        let output1 = format!(
+            "{}\t0\tpm\t\t\n{}\t0\tworker\t\t",
+            pane_line("%0", 0, 60, 40, 0, 0),
+            pane_line("%1", 1, 59, 40, 0, 61),
+        );
+        let output2 = format!(
+            "{}\t0\tpm\t\t\n{}\t0\tworker\t\t",
+            pane_line("%0", 0, 61, 40, 0, 0),
+            pane_line("%1", 1, 58, 40, 0, 62),
+        );
+        let first = capture_session("test", &output1, None, 2, 1000).unwrap();
+        let second = capture_session("test", &output2, Some(&first.layout), 2, 2000).unwrap();
+        assert_ne!(first.layout_expr, second.layout_expr);
+        assert!(!second.changed);
+        assert_eq!(second.diff, Some(LayoutDiff::default()));
+        let strict = capture_session("test", &output2, Some(&first.layout), 0, 2000).unwrap();
+        assert!(strict.changed);
+    }
+
+    #[test]
+    fn added_pane_is_in_the_diff() {
+        let output1 = format!("{}\t1\tpm\t\t", pane_line("%0", 0, 120, 40, 0, 0));
+        let output2 = format!(
+            "{}\t1\tpm\t\t\n{}\t0\tworker\t\t",
+            pane_line("%0", 0, 60, 40, 0, 0),
+            pane_line("%1", 1, 59, 40, 0, 61),
+        );
+        let first = capture_session("test", &output1, None, 2, 1000).unwrap();
+        assert!(first.diff.is_none());
+        let second = capture_session("test", &output2, Some(&first.layout), 2, 2000).unwrap();
+        assert!(second.changed);
+        assert_eq!(
+            second.diff.unwrap().summary(),
+            "pane worker added at 1"
+        );
+    }
+
     #[test]
     fn no_previous_layout_changed_is_true() {
         let output = pane_line("%0", 0, 120, 40, 0, 0);
-        let result = capture_session("test", &output, None, 1000).unwrap();
+        let result = capture_session("test", &output, None, 2, 1000).unwrap();
         assert!(result.changed);
     }
 
@@ -219,7 +320,7 @@ mod tests {
         // s3 has no pane output — should be skipped
         let previous_layouts = HashMap::new();
 
-        let results = capture_all_sessions(&sessions, &pane_outputs, &previous_layouts, 5000);
+        let results = capture_all_sessions(&sessions, &pane_outputs, &previous_layouts, 2, 5000);
         assert_eq!(results.len(), 2);
         assert_eq!(results[0].session, "s1");
         assert_eq!(results[1].session, "s2");
@@ -228,18 +329,53 @@ mod tests {
     #[test]
     fn capture_session_produces_valid_expr() {
         // Single pane capture produces a layout expression string.
-        // Since tmux panes have no agent name, the expression is an
-        // empty string for a single pane — which is the expected
-        // serialization of Pane { agent: "" }.
+        // The pane has no agent tag, so it is written as the `_`
+        // placeholder, which parses back to Pane { agent: "" }.
         let output = pane_line("%0", 0, 120, 40, 0, 0);
-        let result = capture_session("test", &output, None, 1000).unwrap();
+        let result = capture_session("test", &output, None, 2, 1000).unwrap();
         // The layout itself should be correct
         assert_eq!(
             result.layout,
-            LayoutNode::Pane { agent: String::new() }
+            LayoutNode::pane("")
         );
         // The expression string is the serialized form
-        assert_eq!(result.layout_expr, "");
+        assert_eq!(result.layout_expr, "_");
+        assert_eq!(layout_expr::parse_layout_expr(&result.layout_expr).unwrap(), result.layout);
+    }
+
+    #[test]
+    fn tagged_panes_capture_agent_names() {
+        let output = format!(
+            "{}\t1\tpm\tclaude\tplanning\n{}\t0\tworker\tclaude\ttests\n",
+            pane_line("%0", 0, 40, 40, 0, 0),
+            pane_line("%1", 1, 79, 40, 0, 41),
+        );
+        let result = capture_session("test", &output, None, 2, 1000).unwrap();
+        assert_eq!(result.layout_expr, "ROW(pm 34%, worker 66%)");
+    }
+
+    #[test]
+    fn capture_window_is_exact() {
+        // Left column split top/bottom.
+        let body = "160x40,0,0{80x40,0,0[80x20,0,0,0,80x19,0,21,1],79x40,81,0,2}";
+        let layout = format!("{:04x},{}", tmux_layout::checksum(body), body);
+        let panes = format!(
+            "{}\t0\tpm\tclaude\t\n{}\t0\tw1\tclaude\t\n{}\t1\tw2\tclaude\t\n",
+            pane_line("%0", 0, 80, 20, 0, 0),
+            pane_line("%1", 1, 80, 19, 21, 0),
+            pane_line("%2", 2, 79, 40, 0, 81),
+        );
+        let result = capture_window("test", &layout, &panes, None, 2, 1000).unwrap();
+        assert_eq!(result.layout_expr, "ROW(COL(pm 51%, w1 49%) 50%, w2 50%)");
+        assert_eq!(result.tmux_layout.as_deref(), Some(layout.as_str()));
+        // Geometry alone reconstructs the same tree.
+        let geometry = capture_session("test", &panes, None, 2, 1000).unwrap();
+        assert_eq!(geometry.layout_expr, result.layout_expr);
+    }
+
+    #[test]
+    fn capture_window_rejects_bad_layout() {
+        assert!(capture_window("test", "zzzz,80x24,0,0,0", "", None, 2, 1000).is_err());
     }
 
     #[test]
@@ -253,7 +389,7 @@ mod tests {
             pane_line("%0", 0, 60, 40, 0, 0),
             pane_line("%1", 1, 60, 40, 0, 60),
         );
-        let result = capture_session("test", &output, None, 1000).unwrap();
+        let result = capture_session("test", &output, None, 2, 1000).unwrap();
         let reparsed =
             crate::data::layout_expr::parse_layout_expr(&result.layout_expr);
         assert!(reparsed.is_ok(), "layout expr should parse: {}", result.layout_expr);
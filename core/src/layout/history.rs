@@ -0,0 +1,261 @@
+//! Layout history — an append-only on-disk log of each session's layouts.
+//!
+//! Every changed capture is appended as one `SessionSnapshot` per line to
+//! `<config_dir>/history/<session>.jsonl`. Entries are addressed by index
+//! (`0` is the oldest, `-1` the newest) or by time, so a session can be put
+//! back the way it was before something rearranged it.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::data::layout_expr::serialize_layout_expr;
+use crate::types::session::{SessionSnapshot, WindowSnapshot};
+
+
+/// Which history entry `--at` refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum At {
+    /// Position in the log; negative counts back from the newest entry.
+    Index(i64),
+    /// The newest entry at or before this Unix time in milliseconds.
+    Time(u64),
+    /// The newest entry at least this many milliseconds old.
+    Ago(u64),
+}
+
+
+/// Timestamps at or above this are read as Unix milliseconds, not indexes.
+const MIN_TIMESTAMP_MS: i64 = 1_000_000_000_000;
+
+
+impl At {
+    /// Parse `-1`, `3`, `15m`, `2h`, `90s`, `1d`, or a Unix ms timestamp.
+    pub fn parse(spec: &str) -> Result<At, String> {
+        let spec = spec.trim();
+        let unit_ms = match spec.chars().last() {
+            Some('s') => Some(1_000),
+            Some('m') => Some(60_000),
+            Some('h') => Some(3_600_000),
+            Some('d') => Some(86_400_000),
+            _ => None,
+        };
+        if let Some(unit_ms) = unit_ms {
+            let amount: u64 = spec[..spec.len() - 1]
+                .parse()
+                .map_err(|_| format!("invalid time '{}'", spec))?;
+            return Ok(At::Ago(amount * unit_ms));
+        }
+        let n: i64 = spec
+            .parse()
+            .map_err(|_| format!("invalid history position '{}'", spec))?;
+        if n >= MIN_TIMESTAMP_MS {
+            Ok(At::Time(n as u64))
+        } else {
+            Ok(At::Index(n))
+        }
+    }
+}
+
+
+/// Per-session layout logs under one directory.
+#[derive(Debug, Clone)]
+pub struct HistoryStore {
+    dir: PathBuf,
+}
+
+
+impl HistoryStore {
+    /// History kept in `<config_dir>/history`.
+    pub fn new(config_dir: &Path) -> HistoryStore {
+        HistoryStore {
+            dir: config_dir.join("history"),
+        }
+    }
+
+    /// Path of the log for `session`.
+    pub fn path(&self, session: &str) -> PathBuf {
+        let file: String = session
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.dir.join(format!("{}.jsonl", file))
+    }
+
+    /// All entries for `session`, oldest first. A missing log is empty;
+    /// unreadable lines are skipped.
+    pub fn entries(&self, session: &str) -> Vec<SessionSnapshot> {
+        std::fs::read_to_string(self.path(session))
+            .map(|text| {
+                text.lines()
+                    .filter_map(|line| serde_json::from_str(line).ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Append `snapshot` unless it matches the newest entry. Returns whether
+    /// a line was written.
+    pub fn append(&self, snapshot: &SessionSnapshot) -> Result<bool, String> {
+        if let Some(last) = self.entries(&snapshot.session).last() {
+            if same_arrangement(&last.windows, &snapshot.windows) {
+                return Ok(false);
+            }
+        }
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("cannot create {}: {}", self.dir.display(), e))?;
+        let path = self.path(&snapshot.session);
+        let line = serde_json::to_string(snapshot).map_err(|e| e.to_string())?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| format!("cannot open {}: {}", path.display(), e))?;
+        writeln!(file, "{}", line).map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+        Ok(true)
+    }
+
+    /// The entry `at` refers to.
+    pub fn find(&self, session: &str, at: At, now_ms: u64) -> Result<SessionSnapshot, String> {
+        let entries = self.entries(session);
+        if entries.is_empty() {
+            return Err(format!("no layout history for '{}'", session));
+        }
+        let found = match at {
+            At::Index(i) if i < 0 => entries.len().checked_sub(i.unsigned_abs() as usize),
+            At::Index(i) => Some(i as usize).filter(|&i| i < entries.len()),
+            At::Time(ms) => entries.iter().rposition(|e| e.timestamp_ms <= ms),
+            At::Ago(ms) => entries
+                .iter()
+                .rposition(|e| e.timestamp_ms <= now_ms.saturating_sub(ms)),
+        };
+        found
+            .map(|i| entries[i].clone())
+            .ok_or_else(|| format!("no history entry for '{}' at {:?}", session, at))
+    }
+}
+
+
+/// One line per history entry: index, timestamp, and each window's layout.
+pub fn format_history(entries: &[SessionSnapshot]) -> String {
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let windows: Vec<String> = entry
+                .windows
+                .iter()
+                .map(|w| {
+                    let expr = serialize_layout_expr(&w.layout);
+                    if w.name.is_empty() {
+                        expr
+                    } else {
+                        format!("{}={}", w.name, expr)
+                    }
+                })
+                .collect();
+            format!("{:>3}  {}  {}", i, entry.timestamp_ms, windows.join("  "))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+
+/// Whether two window lists have the same names, indexes, and layouts.
+fn same_arrangement(a: &[WindowSnapshot], b: &[WindowSnapshot]) -> bool {
+    a.len() == b.len()
+        && a
+            .iter()
+            .zip(b)
+            .all(|(x, y)| x.index == y.index && x.name == y.name && x.layout == y.layout)
+}
+
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::layout_expr::parse_layout_expr;
+
+    fn temp_store(tag: &str) -> (HistoryStore, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("muxux-history-{}-{}", tag, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        (HistoryStore::new(&dir), dir)
+    }
+
+    fn snap(expr: &str, ts: u64) -> SessionSnapshot {
+        SessionSnapshot {
+            session: "work".into(),
+            windows: vec![WindowSnapshot {
+                index: 0,
+                name: "main".into(),
+                layout: parse_layout_expr(expr).unwrap(),
+                tmux_layout: None,
+            }],
+            timestamp_ms: ts,
+        }
+    }
+
+    #[test]
+    fn parse_at_specs() {
+        assert_eq!(At::parse("-1").unwrap(), At::Index(-1));
+        assert_eq!(At::parse("3").unwrap(), At::Index(3));
+        assert_eq!(At::parse("15m").unwrap(), At::Ago(900_000));
+        assert_eq!(At::parse("2h").unwrap(), At::Ago(7_200_000));
+        assert_eq!(
+            At::parse("1700000000000").unwrap(),
+            At::Time(1_700_000_000_000)
+        );
+        assert!(At::parse("soon").is_err());
+    }
+
+    #[test]
+    fn append_skips_unchanged_layouts() {
+        let (store, dir) = temp_store("dedup");
+        assert!(store.append(&snap("ROW(a, b)", 1)).unwrap());
+        assert!(!store.append(&snap("ROW(a, b)", 2)).unwrap());
+        assert!(store.append(&snap("COL(a, b)", 3)).unwrap());
+        let entries = store.entries("work");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].timestamp_ms, 3);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn find_by_index_and_time() {
+        let (store, dir) = temp_store("find");
+        store.append(&snap("ROW(a, b)", 1_000)).unwrap();
+        store.append(&snap("COL(a, b)", 2_000)).unwrap();
+        store.append(&snap("ROW(a, b, c)", 3_000)).unwrap();
+
+        assert_eq!(store.find("work", At::Index(0), 0).unwrap().timestamp_ms, 1_000);
+        assert_eq!(store.find("work", At::Index(-1), 0).unwrap().timestamp_ms, 3_000);
+        assert_eq!(store.find("work", At::Time(2_500), 0).unwrap().timestamp_ms, 2_000);
+        assert_eq!(store.find("work", At::Ago(1_500), 4_000).unwrap().timestamp_ms, 2_000);
+        assert!(store.find("work", At::Index(3), 0).is_err());
+        assert!(store.find("work", At::Time(500), 0).is_err());
+        assert!(store.find("other", At::Index(0), 0).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn session_names_are_sanitized() {
+        let store = HistoryStore::new(Path::new("/cfg"));
+        assert_eq!(store.path("a/b c"), PathBuf::from("/cfg/history/a_b_c.jsonl"));
+    }
+
+    #[test]
+    fn format_lists_entries() {
+        let text = format_history(&[snap("ROW(a, b)", 7)]);
+        assert!(text.contains("0  7  main=ROW(a, b)"));
+    }
+}
@@ -0,0 +1,211 @@
+//! Merge planning — collapse every pane of a window into a single pane.
+//!
+//! Given the panes reported by `list-panes`, the planner picks the pane to
+//! keep (an explicit choice, else the active pane, else the first one) and
+//! emits the tmux commands that remove the others. Removed panes are either
+//! killed or broken out into background windows so their scrollback and
+//! running processes survive.
+
+use crate::infrastructure::runner::CommandRunner;
+use crate::infrastructure::tmux::{parse_list_panes, TmuxCommandBuilder};
+use crate::types::session::TmuxPane;
+
+
+/// What happens to the panes that are merged away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeMode {
+    /// Kill the other panes.
+    Kill,
+    /// Move each other pane into its own background window.
+    Break,
+}
+
+
+/// The tmux commands that carry out a merge.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergePlan {
+    /// Pane ID that remains after the merge.
+    pub keep: String,
+    /// Pane IDs removed from the window, in command order.
+    pub removed: Vec<String>,
+    /// Commands to run, in order.
+    pub commands: Vec<String>,
+}
+
+
+/// Plan a merge of `panes` into a single pane.
+///
+/// `keep` selects the surviving pane by ID (`%3`) or index (`1`). Without
+/// it the active pane survives, falling back to the first pane listed.
+pub fn plan_merge(
+    panes: &[TmuxPane],
+    keep: Option<&str>,
+    mode: MergeMode,
+) -> Result<MergePlan, String> {
+    if panes.is_empty() {
+        return Err("no panes to merge".into());
+    }
+    let kept = match keep {
+        Some(sel) => panes
+            .iter()
+            .find(|p| p.id == sel || p.index.to_string() == sel)
+            .ok_or_else(|| format!("pane '{}' not found", sel))?,
+        None => panes.iter().find(|p| p.active).unwrap_or(&panes[0]),
+    };
+
+    let builder = TmuxCommandBuilder::new();
+    let removed: Vec<String> = panes
+        .iter()
+        .filter(|p| p.id != kept.id)
+        .map(|p| p.id.clone())
+        .collect();
+    let mut commands: Vec<String> = removed
+        .iter()
+        .map(|id| match mode {
+            MergeMode::Kill => builder.kill_pane(id),
+            MergeMode::Break => builder.break_pane_to_window(id),
+        })
+        .collect();
+    if !removed.is_empty() && !kept.active {
+        commands.push(builder.select_pane(&kept.id));
+    }
+
+    Ok(MergePlan {
+        keep: kept.id.clone(),
+        removed,
+        commands,
+    })
+}
+
+
+/// Merge the current window of `session` into one pane through `runner`.
+///
+/// Reads the live panes with `list-panes`, plans the merge, and runs each
+/// command in order, stopping at the first failure.
+pub fn merge_session(
+    runner: &dyn CommandRunner,
+    session: &str,
+    keep: Option<&str>,
+    mode: MergeMode,
+) -> Result<MergePlan, String> {
+    let builder = TmuxCommandBuilder::new();
+    let output = runner
+        .run(&builder.list_panes(session))
+        .map_err(|e| format!("cannot list panes of '{}': {}", session, e.trim()))?;
+    let plan = plan_merge(&parse_list_panes(&output), keep, mode)?;
+    for command in &plan.commands {
+        runner
+            .run(command)
+            .map_err(|e| format!("{} (cmd: {})", e.trim(), command))?;
+    }
+    Ok(plan)
+}
+
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::runner::MockRunner;
+
+    fn pane(id: &str, index: u32, active: bool) -> TmuxPane {
+        TmuxPane {
+            id: id.into(),
+            index,
+            width: 40,
+            height: 40,
+            top: 0,
+            left: index * 41,
+            agent: None,
+            active,
+            title: String::new(),
+            command: String::new(),
+        }
+    }
+
+    #[test]
+    fn keeps_active_pane_by_default() {
+        let panes = vec![pane("%0", 0, false), pane("%1", 1, true), pane("%2", 2, false)];
+        let plan = plan_merge(&panes, None, MergeMode::Kill).unwrap();
+        assert_eq!(plan.keep, "%1");
+        assert_eq!(plan.removed, vec!["%0", "%2"]);
+        assert_eq!(
+            plan.commands,
+            vec!["tmux kill-pane -t %0", "tmux kill-pane -t %2"]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_first_pane() {
+        let panes = vec![pane("%4", 0, false), pane("%5", 1, false)];
+        let plan = plan_merge(&panes, None, MergeMode::Kill).unwrap();
+        assert_eq!(plan.keep, "%4");
+        assert_eq!(plan.commands.last().unwrap(), "tmux select-pane -t %4");
+    }
+
+    #[test]
+    fn explicit_keep_by_id_or_index() {
+        let panes = vec![pane("%0", 0, true), pane("%1", 1, false)];
+        let by_id = plan_merge(&panes, Some("%1"), MergeMode::Kill).unwrap();
+        assert_eq!(by_id.keep, "%1");
+        let by_index = plan_merge(&panes, Some("1"), MergeMode::Kill).unwrap();
+        assert_eq!(by_index.keep, "%1");
+        assert_eq!(
+            by_index.commands,
+            vec!["tmux kill-pane -t %0", "tmux select-pane -t %1"]
+        );
+    }
+
+    #[test]
+    fn break_mode_moves_panes_to_windows() {
+        let panes = vec![pane("%0", 0, true), pane("%1", 1, false), pane("%2", 2, false)];
+        let plan = plan_merge(&panes, None, MergeMode::Break).unwrap();
+        assert_eq!(
+            plan.commands,
+            vec!["tmux break-pane -d -s %1", "tmux break-pane -d -s %2"]
+        );
+    }
+
+    #[test]
+    fn single_pane_is_a_no_op() {
+        let panes = vec![pane("%0", 0, false)];
+        let plan = plan_merge(&panes, None, MergeMode::Kill).unwrap();
+        assert!(plan.removed.is_empty());
+        assert!(plan.commands.is_empty());
+    }
+
+    #[test]
+    fn unknown_keep_is_an_error() {
+        let panes = vec![pane("%0", 0, true)];
+        assert!(plan_merge(&panes, Some("%9"), MergeMode::Kill).is_err());
+    }
+
+    #[test]
+    fn empty_panes_is_an_error() {
+        assert!(plan_merge(&[], None, MergeMode::Kill).is_err());
+    }
+
+    #[test]
+    fn merge_session_runs_plan() {
+        let runner = MockRunner::with_responses(vec![Ok(
+            "%0\t0\t80\t24\t0\t0\t0\n%1\t1\t80\t24\t0\t81\t1\n".into(),
+        )]);
+        let plan = merge_session(&runner, "work", None, MergeMode::Break).unwrap();
+        assert_eq!(plan.keep, "%1");
+        let cmds = runner.executed_commands();
+        assert_eq!(cmds.len(), 2);
+        assert!(cmds[0].contains("list-panes -t work"));
+        assert_eq!(cmds[1], "tmux break-pane -d -s %0");
+    }
+
+    #[test]
+    fn merge_session_reports_missing_session() {
+        let runner = MockRunner::with_responses(vec![Err("can't find session: nope\n".into())]);
+        let err = merge_session(&runner, "nope", None, MergeMode::Kill).unwrap_err();
+        assert!(err.contains("can't find session"));
+        assert_eq!(runner.executed_commands().len(), 1);
+    }
+}
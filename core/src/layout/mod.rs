@@ -4,9 +4,28 @@
 //! concrete tmux pane identifiers. The `snapshot` module reconstructs a
 //! `LayoutNode` tree from raw pane geometry data. The `capture` module
 //! wires together parsing, reconstruction, and diffing into an end-to-end
-//! pipeline. The `timer` module schedules periodic captures.
+//! pipeline. The `merge` module collapses a window into a single pane, and
+//! the `reconcile` module drives a live window toward a desired layout. The
+//! `restore` module captures and restores whole multi-window sessions, and
+//! the `history` module keeps an on-disk log of past layouts. The
+//! `timer` module decides when each session is due for a capture and the
+//! `scheduler` module runs those captures from the daemon loop. The
+//! `tmux_layout` module reads and writes tmux's native `#{window_layout}`
+//! strings, the `solver` module turns cell sizes and bounds into concrete
+//! split sizes, and the `preview` module draws layouts as text. The
+//! `algebra` module transforms layout trees: normalizing, rotating,
+//! mirroring, and editing them leaf by leaf.
 
+pub mod algebra;
 pub mod capture;
+pub mod history;
+pub mod merge;
+pub mod preview;
+pub mod reconcile;
+pub mod restore;
+pub mod scheduler;
 pub mod snapshot;
+pub mod solver;
 pub mod targeting;
 pub mod timer;
+pub mod tmux_layout;
@@ -0,0 +1,311 @@
+//! Layout previews — draw a `LayoutNode` as text.
+//!
+//! `render` lays the tree out on a character grid, giving each child its
+//! share of the parent from `LayoutEntry.percent`, and draws every pane as
+//! a box labelled with its agent. Neighbouring panes share a border, drawn
+//! with Unicode box-drawing characters or plain `+-|` ASCII.
+//! `render_tree` prints the tree structure itself, one node per line.
+
+use crate::infrastructure::tmux::child_weights;
+use crate::layout::solver::{has_constraints, resolve, NOMINAL_HEIGHT, NOMINAL_WIDTH};
+use crate::types::session::{LayoutEntry, LayoutNode};
+
+
+/// Largest grid `render` draws; bigger sizes are clamped to it.
+pub const MAX_WIDTH: usize = 500;
+pub const MAX_HEIGHT: usize = 200;
+
+const UP: u8 = 1;
+const DOWN: u8 = 2;
+const LEFT: u8 = 4;
+const RIGHT: u8 = 8;
+
+
+/// Characters used for borders.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BoxStyle {
+    /// `+`, `-` and `|`, for terminals without Unicode.
+    Ascii,
+    /// Box-drawing characters (`┌─┬─┐`).
+    Unicode,
+}
+
+
+/// A character grid that records which way each border cell connects.
+struct Canvas {
+    width: usize,
+    height: usize,
+    edges: Vec<u8>,
+    labels: Vec<Option<char>>,
+}
+
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Canvas {
+        Canvas {
+            width,
+            height,
+            edges: vec![0; width * height],
+            labels: vec![None; width * height],
+        }
+    }
+
+    fn connect(&mut self, x: usize, y: usize, bits: u8) {
+        self.edges[y * self.width + x] |= bits;
+    }
+
+    fn hline(&mut self, y: usize, x0: usize, x1: usize) {
+        for x in x0..x1 {
+            self.connect(x, y, RIGHT);
+            self.connect(x + 1, y, LEFT);
+        }
+    }
+
+    fn vline(&mut self, x: usize, y0: usize, y1: usize) {
+        for y in y0..y1 {
+            self.connect(x, y, DOWN);
+            self.connect(x, y + 1, UP);
+        }
+    }
+
+    /// Draw `node` into the box whose border runs from (x0, y0) to (x1, y1).
+    fn place(&mut self, node: &LayoutNode, x0: usize, y0: usize, x1: usize, y1: usize) {
+        match node {
+            LayoutNode::Pane { agent, .. } => {
+                self.hline(y0, x0, x1);
+                self.hline(y1, x0, x1);
+                self.vline(x0, y0, y1);
+                self.vline(x1, y0, y1);
+                self.label(agent, x0, y0, x1, y1);
+            }
+            LayoutNode::Row { children } => {
+                for (child, (a, b)) in children.iter().zip(spans(children, x0, x1)) {
+                    self.place(&child.node, a, y0, b, y1);
+                }
+            }
+            LayoutNode::Col { children } => {
+                for (child, (a, b)) in children.iter().zip(spans(children, y0, y1)) {
+                    self.place(&child.node, x0, a, x1, b);
+                }
+            }
+        }
+    }
+
+    /// Centre `text` inside the box, clipped to its inner width.
+    fn label(&mut self, text: &str, x0: usize, y0: usize, x1: usize, y1: usize) {
+        if x1 < x0 + 2 || y1 < y0 + 2 {
+            return;
+        }
+        let inner = x1 - x0 - 1;
+        let chars: Vec<char> = text.chars().take(inner).collect();
+        let x = x0 + 1 + (inner - chars.len()) / 2;
+        let y = y0 + (y1 - y0) / 2;
+        for (i, c) in chars.into_iter().enumerate() {
+            self.labels[y * self.width + x + i] = Some(c);
+        }
+    }
+
+    fn to_text(&self, style: BoxStyle) -> String {
+        let mut lines = Vec::with_capacity(self.height);
+        for y in 0..self.height {
+            let line: String = (0..self.width)
+                .map(|x| {
+                    let i = y * self.width + x;
+                    match self.edges[i] {
+                        0 => self.labels[i].unwrap_or(' '),
+                        e => border(e, style),
+                    }
+                })
+                .collect();
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
+}
+
+
+/// The border character joining the directions in `edges`.
+fn border(edges: u8, style: BoxStyle) -> char {
+    let vertical = edges & (UP | DOWN) != 0;
+    let horizontal = edges & (LEFT | RIGHT) != 0;
+    match style {
+        BoxStyle::Ascii if !vertical => '-',
+        BoxStyle::Ascii if !horizontal => '|',
+        BoxStyle::Ascii => '+',
+        BoxStyle::Unicode if !vertical => '─',
+        BoxStyle::Unicode if !horizontal => '│',
+        BoxStyle::Unicode => match edges {
+            e if e == DOWN | RIGHT => '┌',
+            e if e == DOWN | LEFT => '┐',
+            e if e == UP | RIGHT => '└',
+            e if e == UP | LEFT => '┘',
+            e if e == UP | DOWN | RIGHT => '├',
+            e if e == UP | DOWN | LEFT => '┤',
+            e if e == LEFT | RIGHT | DOWN => '┬',
+            e if e == LEFT | RIGHT | UP => '┴',
+            _ => '┼',
+        },
+    }
+}
+
+
+/// Split the border range `start..=end` among `children` by weight. Each
+/// pair is a child's first and last border line; neighbours share one.
+fn spans(children: &[LayoutEntry], start: usize, end: usize) -> Vec<(usize, usize)> {
+    let weights = child_weights(children);
+    let total: f64 = weights.iter().sum();
+    let span = (end - start) as f64;
+    let mut bounds = vec![start];
+    let mut acc = 0.0;
+    for w in &weights[..weights.len().saturating_sub(1)] {
+        acc += w;
+        let at = start + (span * acc / total).round() as usize;
+        let prev = *bounds.last().unwrap_or(&start);
+        bounds.push(at.clamp(prev, end));
+    }
+    bounds.push(end);
+    bounds.windows(2).map(|w| (w[0], w[1])).collect()
+}
+
+
+/// Draw `node` as boxes filling `width` x `height` characters.
+///
+/// Returns an empty string when the grid is too small to hold a box. The
+/// grid is at most `MAX_WIDTH` x `MAX_HEIGHT`.
+pub fn render(node: &LayoutNode, width: usize, height: usize, style: BoxStyle) -> String {
+    let (width, height) = (width.min(MAX_WIDTH), height.min(MAX_HEIGHT));
+    if width < 2 || height < 2 {
+        return String::new();
+    }
+    let mut canvas = Canvas::new(width, height);
+    if has_constraints(node) {
+        // Cell sizes are relative to a window, so size for a typical one.
+        let node = resolve(node, NOMINAL_WIDTH, NOMINAL_HEIGHT);
+        canvas.place(&node, 0, 0, width - 1, height - 1);
+    } else {
+        canvas.place(node, 0, 0, width - 1, height - 1);
+    }
+    canvas.to_text(style)
+}
+
+
+/// `render` with ASCII borders.
+pub fn render_ascii(node: &LayoutNode, width: usize, height: usize) -> String {
+    render(node, width, height, BoxStyle::Ascii)
+}
+
+
+/// Print the tree, one node per line, with each child's percentage.
+///
+/// ```text
+/// COL
+/// ├─ 30% pm
+/// └─ 70% ROW
+///    ├─ worker
+///    └─ worker
+/// ```
+pub fn render_tree(node: &LayoutNode) -> String {
+    let mut lines = Vec::new();
+    tree_lines(node, "", &mut lines, String::new());
+    lines.join("\n")
+}
+
+
+fn tree_lines(node: &LayoutNode, prefix: &str, lines: &mut Vec<String>, head: String) {
+    let (name, children) = match node {
+        LayoutNode::Pane { agent, .. } => (agent.as_str(), None),
+        LayoutNode::Row { children } => ("ROW", Some(children)),
+        LayoutNode::Col { children } => ("COL", Some(children)),
+    };
+    lines.push(format!("{}{}", head, name));
+    for (i, entry) in children.into_iter().flatten().enumerate() {
+        let last = i + 1 == children.map_or(0, |c| c.len());
+        let percent = entry.percent.map(|p| format!("{}% ", p)).unwrap_or_default();
+        let branch = if last { "└─ " } else { "├─ " };
+        let deeper = format!("{}{}", prefix, if last { "   " } else { "│  " });
+        tree_lines(
+            &entry.node,
+            &deeper,
+            lines,
+            format!("{}{}{}", prefix, branch, percent),
+        );
+    }
+}
+
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::layout_expr::parse_layout_expr;
+
+    fn layout(expr: &str) -> LayoutNode {
+        parse_layout_expr(expr).unwrap()
+    }
+
+    #[test]
+    fn single_pane_box() {
+        let text = render_ascii(&layout("ROW(pm)"), 8, 3);
+        assert_eq!(text, "+------+\n|  pm  |\n+------+");
+    }
+
+    #[test]
+    fn row_split_by_percent() {
+        let text = render_ascii(&layout("ROW(a 25%, b 75%)"), 13, 3);
+        assert_eq!(text, "+--+--------+\n|a |   b    |\n+--+--------+");
+    }
+
+    #[test]
+    fn nested_split_shares_borders() {
+        let text = render_ascii(&layout("COL(top, ROW(l, r))"), 9, 5);
+        assert_eq!(
+            text,
+            ["+-------+", "|  top  |", "+---+---+", "| l | r |", "+---+---+"].join("\n")
+        );
+    }
+
+    #[test]
+    fn oversized_grids_are_clamped() {
+        let text = render_ascii(&layout("ROW(a, b)"), usize::MAX, 65_535);
+        assert_eq!(text.lines().count(), MAX_HEIGHT);
+        assert!(text.lines().all(|l| l.chars().count() == MAX_WIDTH));
+    }
+
+    #[test]
+    fn unicode_borders_join() {
+        let text = render(&layout("COL(top, ROW(l, r))"), 9, 5, BoxStyle::Unicode);
+        assert_eq!(
+            text,
+            ["┌───────┐", "│  top  │", "├───┬───┤", "│ l │ r │", "└───┴───┘"].join("\n")
+        );
+    }
+
+    #[test]
+    fn unicode_cross() {
+        let text = render(&layout("ROW(COL(a, b), COL(c, d))"), 9, 5, BoxStyle::Unicode);
+        assert_eq!(text.lines().nth(2).unwrap(), "├───┼───┤");
+    }
+
+    #[test]
+    fn labels_are_clipped() {
+        let text = render_ascii(&layout("ROW(supervisor)"), 6, 3);
+        assert_eq!(text.lines().nth(1).unwrap(), "|supe|");
+    }
+
+    #[test]
+    fn too_small_grid_is_empty() {
+        assert!(render_ascii(&layout("ROW(a)"), 1, 5).is_empty());
+    }
+
+    #[test]
+    fn tree_shows_structure_and_percent() {
+        let text = render_tree(&layout("COL(pm 30%, ROW(w1, w2) 70%)"));
+        assert_eq!(
+            text,
+            ["COL", "├─ 30% pm", "└─ 70% ROW", "   ├─ w1", "   └─ w2"].join("\n")
+        );
+    }
+}
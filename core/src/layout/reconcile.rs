@@ -0,0 +1,948 @@
+//! Layout reconciliation — drive a live tmux window toward a `LayoutNode`.
+//!
+//! The reconciler compares the live pane tree of a window with a desired
+//! layout and plans the steps that close the gap: splits for missing panes,
+//! kills for surplus ones, swaps to move tagged agents into place, and
+//! resizes to hit the requested percentages. Panes that already sit in the
+//! right place are left alone, so applying the same layout twice is a no-op.
+//!
+//! Planning is pure and happens in phases — structure first, then agent
+//! placement, then sizes. `apply_layout` runs one phase per pass, re-reading
+//! `list-panes` in between, until the plan comes back empty. A window that
+//! starts as a single pane is built out directly with `realize_layout`.
+
+use cmx_utils::response::Direction;
+
+use crate::infrastructure::runner::CommandRunner;
+use crate::infrastructure::tmux::{
+    child_weights, parse_list_panes, realize_layout, shell_escape, TmuxCommandBuilder,
+};
+use crate::layout::snapshot::pane_id_tree;
+use crate::layout::solver::{has_constraints, resolve};
+use crate::layout::tmux_layout::{emit_tmux_layout, from_layout_node};
+use crate::types::session::{LayoutEntry, LayoutNode, TmuxPane};
+
+
+/// Upper bound on plan/apply passes before `apply_layout` gives up.
+const MAX_PASSES: usize = 12;
+
+/// Size differences (in cells) at or below this are considered a match.
+const SIZE_TOLERANCE: u32 = 1;
+
+
+/// One step of a reconciliation plan.
+#[derive(Debug, Clone)]
+pub enum Step {
+    /// Split `target`, creating a new pane beside (`Horizontal`) or below
+    /// (`Vertical`) it that takes `percent` of its space.
+    Split {
+        target: String,
+        direction: Direction,
+        percent: u32,
+    },
+    /// Kill a pane that has no place in the desired layout.
+    Kill { pane: String },
+    /// Set a pane's width (`Horizontal`) or height (`Vertical`) in cells.
+    Resize {
+        pane: String,
+        direction: Direction,
+        cells: u32,
+    },
+    /// Exchange two panes so an agent lands in its desired slot.
+    Swap { source: String, target: String },
+}
+
+
+impl Step {
+    /// The tmux command that performs this step.
+    pub fn command(&self) -> String {
+        let builder = TmuxCommandBuilder::new();
+        match self {
+            Step::Split {
+                target,
+                direction,
+                percent,
+            } => builder.split_pane(target, direction, *percent),
+            Step::Kill { pane } => builder.kill_pane(pane),
+            Step::Resize {
+                pane,
+                direction,
+                cells,
+            } => builder.resize_pane_to(pane, direction, *cells),
+            Step::Swap { source, target } => builder.swap_panes(source, target),
+        }
+    }
+}
+
+
+/// Result of `apply_layout`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApplyReport {
+    /// Number of passes that ran commands.
+    pub passes: usize,
+    /// Every command run, in order.
+    pub commands: Vec<String>,
+    /// Whether the final plan was empty.
+    pub converged: bool,
+}
+
+
+/// Live window tree rebuilt from pane geometry, keeping the panes themselves.
+#[derive(Debug, Clone)]
+pub enum LiveNode {
+    Row(Vec<LiveNode>),
+    Col(Vec<LiveNode>),
+    Pane(TmuxPane),
+}
+
+
+impl LiveNode {
+    fn leaves(&self) -> Vec<&TmuxPane> {
+        match self {
+            LiveNode::Pane(p) => vec![p],
+            LiveNode::Row(children) | LiveNode::Col(children) => {
+                children.iter().flat_map(|c| c.leaves()).collect()
+            }
+        }
+    }
+
+    /// The panes of `panes` in the shape of `tree`, whose leaves are pane ids.
+    fn from_tree(tree: &LayoutNode, panes: &[TmuxPane]) -> Option<LiveNode> {
+        let group = |children: &[LayoutEntry]| -> Option<Vec<LiveNode>> {
+            children
+                .iter()
+                .map(|e| LiveNode::from_tree(&e.node, panes))
+                .collect()
+        };
+        Some(match tree {
+            LayoutNode::Row { children } => LiveNode::Row(group(children)?),
+            LayoutNode::Col { children } => LiveNode::Col(group(children)?),
+            LayoutNode::Pane { agent: id, .. } => {
+                LiveNode::Pane(panes.iter().find(|p| p.id == *id)?.clone())
+            }
+        })
+    }
+
+    fn first_leaf(&self) -> &TmuxPane {
+        match self {
+            LiveNode::Pane(p) => p,
+            LiveNode::Row(children) | LiveNode::Col(children) => children[0].first_leaf(),
+        }
+    }
+
+    /// Width and height of the area covered by this node.
+    fn extent(&self) -> (u32, u32) {
+        let leaves = self.leaves();
+        let left = leaves.iter().map(|p| p.left).min().unwrap_or(0);
+        let top = leaves.iter().map(|p| p.top).min().unwrap_or(0);
+        let right = leaves.iter().map(|p| p.left + p.width).max().unwrap_or(0);
+        let bottom = leaves.iter().map(|p| p.top + p.height).max().unwrap_or(0);
+        (right - left, bottom - top)
+    }
+}
+
+
+/// Rebuild the window's split tree from pane geometry.
+///
+/// The cuts are found by `snapshot::pane_id_tree`, the same way captures
+/// are, so a captured layout applied back has the shape it was read with.
+pub fn live_tree(panes: &[TmuxPane]) -> Result<LiveNode, String> {
+    let tree = pane_id_tree(panes)?;
+    LiveNode::from_tree(&tree, panes).ok_or_else(|| "pane ids are not unique".into())
+}
+
+
+/// Desired layout with resolved weights and same-direction nesting flattened.
+#[derive(Debug, Clone)]
+enum Want {
+    Row(Vec<(Want, f64)>),
+    Col(Vec<(Want, f64)>),
+    Pane(String),
+}
+
+
+impl Want {
+    fn from_node(node: &LayoutNode) -> Want {
+        match node {
+            LayoutNode::Pane { agent, .. } => Want::Pane(agent.clone()),
+            LayoutNode::Row { children } => Want::group(true, children),
+            LayoutNode::Col { children } => Want::group(false, children),
+        }
+    }
+
+    fn group(row: bool, children: &[LayoutEntry]) -> Want {
+        let mut flat: Vec<(Want, f64)> = Vec::new();
+        for (entry, weight) in children.iter().zip(child_weights(children)) {
+            match (Want::from_node(&entry.node), row) {
+                (Want::Row(inner), true) | (Want::Col(inner), false) => {
+                    let total: f64 = inner.iter().map(|(_, w)| w).sum();
+                    for (want, w) in inner {
+                        flat.push((want, weight * w / total));
+                    }
+                }
+                (want, _) => flat.push((want, weight)),
+            }
+        }
+        if flat.len() == 1 {
+            return flat.pop().map(|(w, _)| w).unwrap_or(Want::Pane(String::new()));
+        }
+        if row {
+            Want::Row(flat)
+        } else {
+            Want::Col(flat)
+        }
+    }
+
+    fn agents(&self) -> Vec<&str> {
+        match self {
+            Want::Pane(agent) => vec![agent.as_str()],
+            Want::Row(children) | Want::Col(children) => {
+                children.iter().flat_map(|(c, _)| c.agents()).collect()
+            }
+        }
+    }
+}
+
+
+/// Plan the next phase of steps that move `panes` toward `desired`.
+///
+/// An empty plan means the window already matches. Fails when the panes
+/// cannot be read as a tree of splits.
+pub fn plan(panes: &[TmuxPane], desired: &LayoutNode) -> Result<Vec<Step>, String> {
+    let live = live_tree(panes)?;
+    let want = Want::from_node(desired);
+
+    let mut steps = Vec::new();
+    plan_structure(&live, &want, &mut steps);
+    if !steps.is_empty() {
+        return Ok(steps);
+    }
+    plan_swaps(&live, &want, &mut steps);
+    if !steps.is_empty() {
+        return Ok(steps);
+    }
+    plan_sizes(&live, &want, &mut steps);
+    Ok(steps)
+}
+
+
+/// Kill or split panes until the live tree has the desired shape.
+fn plan_structure(live: &LiveNode, want: &Want, steps: &mut Vec<Step>) {
+    match (want, live) {
+        (Want::Pane(_), LiveNode::Pane(_)) => {}
+        (Want::Pane(_), _) => collapse(live, &want.agents(), steps),
+        (Want::Row(wanted), LiveNode::Row(have)) => {
+            plan_children(Direction::Horizontal, wanted, have, steps)
+        }
+        (Want::Col(wanted), LiveNode::Col(have)) => {
+            plan_children(Direction::Vertical, wanted, have, steps)
+        }
+        (Want::Row(wanted), LiveNode::Pane(_)) => {
+            plan_children(Direction::Horizontal, wanted, std::slice::from_ref(live), steps)
+        }
+        (Want::Col(wanted), LiveNode::Pane(_)) => {
+            plan_children(Direction::Vertical, wanted, std::slice::from_ref(live), steps)
+        }
+        // Wrong split direction: fold the region into one pane and rebuild.
+        _ => collapse(live, &want.agents(), steps),
+    }
+}
+
+
+fn plan_children(direction: Direction, wanted: &[(Want, f64)], have: &[LiveNode], steps: &mut Vec<Step>) {
+    if have.len() > wanted.len() {
+        for extra in &have[wanted.len()..] {
+            for pane in extra.leaves() {
+                steps.push(Step::Kill { pane: pane.id.clone() });
+            }
+        }
+    } else if have.len() < wanted.len() {
+        // New siblings come from splitting a leaf child; tmux inserts them
+        // into this row/column right after it.
+        let Some(k) = have.iter().rposition(|c| matches!(c, LiveNode::Pane(_))) else {
+            let last = &have[have.len() - 1];
+            collapse(last, &[], steps);
+            return;
+        };
+        let target = have[k].first_leaf().id.clone();
+        let missing = wanted.len() - have.len();
+        for n in 0..missing {
+            let pieces = (missing - n + 1) as u32;
+            steps.push(Step::Split {
+                target: target.clone(),
+                direction: direction.clone(),
+                percent: (100 / pieces).max(1),
+            });
+        }
+        for (live, (want, _)) in have[..k].iter().zip(wanted) {
+            plan_structure(live, want, steps);
+        }
+        return;
+    }
+    for (live, (want, _)) in have.iter().zip(wanted) {
+        plan_structure(live, want, steps);
+    }
+}
+
+
+/// Kill every pane in `live` but one, preferring a pane already running
+/// one of `agents`.
+fn collapse(live: &LiveNode, agents: &[&str], steps: &mut Vec<Step>) {
+    let leaves = live.leaves();
+    let keep = leaves
+        .iter()
+        .find(|p| {
+            p.agent
+                .as_deref()
+                .map(|a| !a.is_empty() && agents.contains(&a))
+                .unwrap_or(false)
+        })
+        .unwrap_or(&leaves[0]);
+    for pane in &leaves {
+        if pane.id != keep.id {
+            steps.push(Step::Kill { pane: pane.id.clone() });
+        }
+    }
+}
+
+
+/// With the shape in place, swap tagged agents into their desired slots.
+fn plan_swaps(live: &LiveNode, want: &Want, steps: &mut Vec<Step>) {
+    let mut slots: Vec<TmuxPane> = live.leaves().into_iter().cloned().collect();
+    let agents = want.agents();
+    for (i, agent) in agents.iter().enumerate() {
+        if agent.is_empty() || slots[i].agent.as_deref() == Some(*agent) {
+            continue;
+        }
+        let found = slots
+            .iter()
+            .enumerate()
+            .skip(i + 1)
+            .find(|(_, p)| p.agent.as_deref() == Some(*agent))
+            .map(|(j, _)| j);
+        if let Some(j) = found {
+            steps.push(Step::Swap {
+                source: slots[j].id.clone(),
+                target: slots[i].id.clone(),
+            });
+            slots.swap(i, j);
+        }
+    }
+}
+
+
+/// Resize children whose share of their row/column is off. Descends only
+/// into groups whose own sizes already match, since resizing a parent
+/// changes the geometry below it.
+fn plan_sizes(live: &LiveNode, want: &Want, steps: &mut Vec<Step>) {
+    let (children, wanted, direction) = match (live, want) {
+        (LiveNode::Row(c), Want::Row(w)) => (c, w, Direction::Horizontal),
+        (LiveNode::Col(c), Want::Col(w)) => (c, w, Direction::Vertical),
+        _ => return,
+    };
+    let size = |node: &LiveNode| {
+        let (w, h) = node.extent();
+        match direction {
+            Direction::Horizontal => w,
+            Direction::Vertical => h,
+        }
+    };
+
+    let total = size(live);
+    let borders = children.len().saturating_sub(1) as u32;
+    let available = f64::from(total.saturating_sub(borders));
+    let weight_sum: f64 = wanted.iter().map(|(_, w)| w).sum();
+
+    let before = steps.len();
+    for (child, (_, weight)) in children.iter().zip(wanted).take(children.len() - 1) {
+        let target = (available * weight / weight_sum).round().max(1.0) as u32;
+        if size(child).abs_diff(target) > SIZE_TOLERANCE {
+            steps.push(Step::Resize {
+                pane: child.first_leaf().id.clone(),
+                direction: direction.clone(),
+                cells: target,
+            });
+        }
+    }
+    if steps.len() == before {
+        for (child, (want, _)) in children.iter().zip(wanted) {
+            plan_sizes(child, want, steps);
+        }
+    }
+}
+
+
+/// Reconcile the window holding `target` toward `desired` through `runner`.
+///
+/// Cell sizes and bounds in `desired` are first solved for the window's
+/// size. Each pass reads `list-panes`, plans one phase, and runs its
+/// commands. Size corrections are first attempted as a single
+/// `select-layout` with an exact tmux layout string. Stops when the plan is
+/// empty, when a sizing plan repeats (tmux could not honour it exactly), or
+/// after `MAX_PASSES`.
+pub fn apply_layout(
+    runner: &dyn CommandRunner,
+    target: &str,
+    desired: &LayoutNode,
+) -> Result<ApplyReport, String> {
+    let builder = TmuxCommandBuilder::new();
+    let mut report = ApplyReport {
+        passes: 0,
+        commands: Vec::new(),
+        converged: false,
+    };
+    let mut previous: Vec<String> = Vec::new();
+    let mut tried_exact = false;
+    let mut resolved: Option<LayoutNode> = None;
+
+    while report.passes < MAX_PASSES {
+        let output = runner
+            .run(&builder.list_panes(target))
+            .map_err(|e| format!("cannot list panes of '{}': {}", target, e.trim()))?;
+        let panes = parse_list_panes(&output);
+        if panes.is_empty() {
+            return Err(format!("no panes found for '{}'", target));
+        }
+        if resolved.is_none() && has_constraints(desired) {
+            // Cell sizes and bounds only mean something for a given window.
+            let width = panes.iter().map(|p| p.left + p.width).max().unwrap_or(0);
+            let height = panes.iter().map(|p| p.top + p.height).max().unwrap_or(0);
+            resolved = Some(resolve(desired, width, height));
+        }
+        let desired = resolved.as_ref().unwrap_or(desired);
+        if panes.len() == 1 && report.passes == 0 && !matches!(desired, LayoutNode::Pane { .. }) {
+            // A lone pane can be built out exactly in one pass.
+            let realized = realize_layout(runner, &panes[0].id, desired)?;
+            report.commands.extend(realized.commands);
+            report.passes += 1;
+            continue;
+        }
+        let steps = plan(&panes, desired)
+            .map_err(|e| format!("cannot read the layout of '{}': {}", target, e))?;
+        if steps.is_empty() {
+            report.converged = true;
+            break;
+        }
+        let sizing_only = steps.iter().all(|s| matches!(s, Step::Resize { .. }));
+        if sizing_only && !tried_exact {
+            // Sizes only: one select-layout sets them exactly; per-pane
+            // resizes remain the fallback if tmux rejects it.
+            tried_exact = true;
+            if let Some(command) = exact_layout_command(target, &panes, desired) {
+                if runner.run(&command).is_ok() {
+                    report.commands.push(command);
+                    report.passes += 1;
+                    continue;
+                }
+            }
+        }
+        let commands: Vec<String> = steps.iter().map(Step::command).collect();
+        if sizing_only && commands == previous {
+            break;
+        }
+        for command in &commands {
+            runner
+                .run(command)
+                .map_err(|e| format!("{} (cmd: {})", e.trim(), command))?;
+        }
+        report.commands.extend(commands.iter().cloned());
+        report.passes += 1;
+        previous = commands;
+    }
+    Ok(report)
+}
+
+
+/// A `select-layout` command that sets every size in one go, when the live
+/// window already has the desired shape.
+fn exact_layout_command(target: &str, panes: &[TmuxPane], desired: &LayoutNode) -> Option<String> {
+    let live = live_tree(panes).ok()?;
+    let (width, height) = live.extent();
+    let ids: Vec<u32> = live
+        .leaves()
+        .iter()
+        .map(|p| p.id.trim_start_matches('%').parse().ok())
+        .collect::<Option<_>>()?;
+    let cell = from_layout_node(desired, width, height, &ids);
+    Some(TmuxCommandBuilder::new().select_layout(target, &emit_tmux_layout(&cell)))
+}
+
+
+/// What `place_agents` did to a window.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Placement {
+    /// Agents started, in layout order.
+    pub placed: Vec<String>,
+    /// Agents not started because their pane is busy with another agent,
+    /// as `agent (pane runs other)`.
+    pub conflicts: Vec<String>,
+}
+
+
+/// Start each desired agent in its pane and tag the pane with `@mux_agent`.
+///
+/// Runs after `apply_layout` has brought the window into shape. Panes are
+/// matched to layout leaves in order; a pane already tagged with its leaf's
+/// agent is left alone, so re-applying never starts an agent twice. An agent
+/// is only started in a pane sitting at a shell prompt; a pane running
+/// anything else is reported as a conflict and nothing is typed into it.
+/// `launch` maps an agent name to the command that starts it (`None` just
+/// tags the pane). A leaf's `cwd` is entered and its `cmd` run in place of
+/// `launch`; its `title` and `focus` are applied every time.
+pub fn place_agents(
+    runner: &dyn CommandRunner,
+    target: &str,
+    desired: &LayoutNode,
+    launch: &dyn Fn(&str) -> Option<String>,
+) -> Result<Placement, String> {
+    let builder = TmuxCommandBuilder::new();
+    let output = runner
+        .run(&builder.list_panes(target))
+        .map_err(|e| format!("cannot list panes of '{}': {}", target, e.trim()))?;
+    let live = live_tree(&parse_list_panes(&output))
+        .map_err(|e| format!("cannot read the panes of '{}': {}", target, e))?;
+    let leaves = live.leaves();
+    let want = Want::from_node(desired);
+    let agents = want.agents();
+    if leaves.len() != agents.len() {
+        return Err(format!(
+            "'{}' has {} panes but the layout has {} leaves",
+            target,
+            leaves.len(),
+            agents.len()
+        ));
+    }
+
+    let mut placement = Placement::default();
+    for ((pane, agent), options) in leaves.iter().zip(agents).zip(desired.pane_options()) {
+        let mut commands = Vec::new();
+        if let Some(title) = &options.title {
+            commands.push(builder.set_pane_title(&pane.id, title));
+        }
+        let fresh = !agent.is_empty() && pane.agent.as_deref() != Some(agent);
+        if fresh {
+            match pane.agent.as_deref().unwrap_or(&pane.command) {
+                other if !pane.at_shell() => {
+                    placement
+                        .conflicts
+                        .push(format!("{} ({} runs {})", agent, pane.id, other));
+                }
+                _ => {
+                    commands.push(builder.tag_pane(&pane.id, agent));
+                    if let Some(cwd) = &options.cwd {
+                        commands.push(builder.send_keys(&pane.id, &cd_command(cwd)));
+                    }
+                    if let Some(cmd) = options.cmd.clone().or_else(|| launch(agent)) {
+                        commands.push(builder.send_keys(&pane.id, &cmd));
+                    }
+                    placement.placed.push(agent.to_string());
+                }
+            }
+        }
+        if options.focus {
+            commands.push(builder.select_pane(&pane.id));
+        }
+        for command in &commands {
+            runner
+                .run(command)
+                .map_err(|e| format!("{} (cmd: {})", e.trim(), command))?;
+        }
+    }
+    Ok(placement)
+}
+
+
+/// `cd` into `dir`, leaving a leading `~/` for the shell to expand.
+fn cd_command(dir: &str) -> String {
+    match dir.strip_prefix("~/") {
+        Some(rest) => format!("cd ~/{}", shell_escape(rest)),
+        None => format!("cd {}", shell_escape(dir)),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::layout_expr::parse_layout_expr;
+    use crate::infrastructure::runner::MockRunner;
+
+    fn pane(id: &str, w: u32, h: u32, top: u32, left: u32) -> TmuxPane {
+        TmuxPane {
+            id: id.into(),
+            index: 0,
+            width: w,
+            height: h,
+            top,
+            left,
+            agent: None,
+            active: false,
+            title: String::new(),
+            command: String::new(),
+        }
+    }
+
+    fn tagged(id: &str, w: u32, h: u32, top: u32, left: u32, agent: &str) -> TmuxPane {
+        TmuxPane {
+            agent: Some(agent.into()),
+            ..pane(id, w, h, top, left)
+        }
+    }
+
+    fn layout(expr: &str) -> LayoutNode {
+        parse_layout_expr(expr).unwrap()
+    }
+
+    fn commands(panes: &[TmuxPane], expr: &str) -> Vec<String> {
+        plan(panes, &layout(expr)).unwrap().iter().map(Step::command).collect()
+    }
+
+    /// Render panes the way `list-panes` reports them.
+    fn listing(panes: &[TmuxPane]) -> String {
+        panes
+            .iter()
+            .map(|p| {
+                let agent = p.agent.as_deref().unwrap_or("");
+                let command = if p.command.is_empty() { "zsh" } else { &p.command };
+                format!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\t0\t{}\t{}\t",
+                    p.id, p.index, p.width, p.height, p.top, p.left, agent, command
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn listing_round_trips_agents_with_colons() {
+        let panes = vec![tagged("%0", 40, 24, 0, 0, "pm:lead"), pane("%1", 39, 24, 0, 41)];
+        let parsed = parse_list_panes(&listing(&panes));
+        assert_eq!(parsed[0].agent.as_deref(), Some("pm:lead"));
+        assert_eq!(parsed[0].command, "zsh");
+        assert_eq!(parsed[1].agent, None);
+        assert_eq!((parsed[1].width, parsed[1].left), (39, 41));
+    }
+
+    #[test]
+    fn live_tree_nested_row_of_columns() {
+        // Left column split top/bottom, right pane full height.
+        let panes = vec![
+            pane("%0", 40, 12, 0, 0),
+            pane("%1", 40, 11, 13, 0),
+            pane("%2", 39, 24, 0, 41),
+        ];
+        match live_tree(&panes).unwrap() {
+            LiveNode::Row(children) => {
+                assert_eq!(children.len(), 2);
+                assert!(matches!(children[0], LiveNode::Col(ref c) if c.len() == 2));
+                assert!(matches!(children[1], LiveNode::Pane(ref p) if p.id == "%2"));
+            }
+            other => panic!("expected row, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn captured_grid_applies_without_structural_steps() {
+        // A 2x2 grid can be cut either way first; capture and apply must agree.
+        let panes = vec![
+            tagged("%0", 40, 12, 0, 0, "a"),
+            tagged("%1", 39, 12, 0, 41, "b"),
+            tagged("%2", 40, 11, 13, 0, "c"),
+            tagged("%3", 39, 11, 13, 41, "d"),
+        ];
+        let captured = crate::layout::snapshot::from_panes(&panes).unwrap();
+        let steps = plan(&panes, &captured).unwrap();
+        assert!(
+            !steps
+                .iter()
+                .any(|s| matches!(s, Step::Split { .. } | Step::Kill { .. } | Step::Swap { .. })),
+            "unexpected steps: {:?}",
+            steps
+        );
+    }
+
+    #[test]
+    fn matching_layout_plans_nothing() {
+        let panes = vec![pane("%0", 40, 24, 0, 0), pane("%1", 39, 24, 0, 41)];
+        assert!(plan(&panes, &layout("ROW(a 50%, b 50%)")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn single_pane_is_split() {
+        let panes = vec![pane("%0", 80, 24, 0, 0)];
+        assert_eq!(
+            commands(&panes, "ROW(a, b, c)"),
+            vec![
+                "tmux split-window -t %0 -h -p 33",
+                "tmux split-window -t %0 -h -p 50",
+            ]
+        );
+    }
+
+    #[test]
+    fn surplus_panes_are_killed() {
+        let panes = vec![
+            pane("%0", 26, 24, 0, 0),
+            pane("%1", 26, 24, 0, 27),
+            pane("%2", 26, 24, 0, 54),
+        ];
+        assert_eq!(commands(&panes, "ROW(a, b)"), vec!["tmux kill-pane -t %2"]);
+    }
+
+    #[test]
+    fn nested_leaf_is_split_in_place() {
+        // ROW(a, b) live; want ROW(a, COL(b, c)) — only %1 is split.
+        let panes = vec![pane("%0", 40, 24, 0, 0), pane("%1", 39, 24, 0, 41)];
+        assert_eq!(
+            commands(&panes, "ROW(a 50%, COL(b, c) 50%)"),
+            vec!["tmux split-window -t %1 -v -p 50"]
+        );
+    }
+
+    #[test]
+    fn wrong_direction_collapses_region() {
+        let panes = vec![pane("%0", 80, 12, 0, 0), pane("%1", 80, 11, 13, 0)];
+        assert_eq!(commands(&panes, "ROW(a, b)"), vec!["tmux kill-pane -t %1"]);
+    }
+
+    #[test]
+    fn collapse_keeps_pane_running_wanted_agent() {
+        let panes = vec![
+            tagged("%0", 80, 12, 0, 0, "scratch"),
+            tagged("%1", 80, 11, 13, 0, "pilot"),
+        ];
+        assert_eq!(commands(&panes, "pilot"), vec!["tmux kill-pane -t %0"]);
+    }
+
+    #[test]
+    fn misplaced_agents_are_swapped() {
+        let panes = vec![
+            tagged("%0", 40, 24, 0, 0, "worker"),
+            tagged("%1", 39, 24, 0, 41, "pilot"),
+        ];
+        assert_eq!(
+            commands(&panes, "ROW(pilot 50%, worker 50%)"),
+            vec!["tmux swap-pane -d -s %1 -t %0"]
+        );
+    }
+
+    #[test]
+    fn sizes_are_corrected() {
+        let panes = vec![pane("%0", 40, 24, 0, 0), pane("%1", 39, 24, 0, 41)];
+        assert_eq!(
+            commands(&panes, "ROW(a 25%, b 75%)"),
+            vec!["tmux resize-pane -t %0 -x 20"]
+        );
+    }
+
+    #[test]
+    fn same_direction_nesting_is_flattened() {
+        let panes = vec![
+            pane("%0", 26, 24, 0, 0),
+            pane("%1", 26, 24, 0, 27),
+            pane("%2", 26, 24, 0, 54),
+        ];
+        assert!(plan(&panes, &layout("ROW(a, ROW(b, c) 67%)")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn step_commands() {
+        let split = Step::Split {
+            target: "%0".into(),
+            direction: Direction::Vertical,
+            percent: 40,
+        };
+        assert_eq!(split.command(), "tmux split-window -t %0 -v -p 40");
+        assert_eq!(Step::Kill { pane: "%3".into() }.command(), "tmux kill-pane -t %3");
+    }
+
+    #[test]
+    fn apply_converges_over_passes() {
+        // Two panes where three are wanted: split, re-read, done.
+        let runner = MockRunner::with_responses(vec![
+            Ok(listing(&[pane("%0", 40, 24, 0, 0), pane("%1", 39, 24, 0, 41)])),
+            Ok(String::new()),
+            Ok(listing(&[
+                pane("%0", 40, 24, 0, 0),
+                pane("%1", 19, 24, 0, 41),
+                pane("%2", 19, 24, 0, 61),
+            ])),
+        ]);
+        let report = apply_layout(&runner, "work", &layout("ROW(a 50%, b 25%, c 25%)")).unwrap();
+        assert!(report.converged);
+        assert_eq!(report.passes, 1);
+        assert_eq!(report.commands, vec!["tmux split-window -t %1 -h -p 50"]);
+        assert_eq!(runner.executed_commands().len(), 3);
+    }
+
+    #[test]
+    fn apply_builds_out_single_pane_directly() {
+        let runner = MockRunner::with_responses(vec![
+            Ok(listing(&[pane("%0", 80, 24, 0, 0)])),
+            Ok("%0\n".into()),
+            Ok("%1\n".into()),
+            Ok(listing(&[pane("%0", 24, 24, 0, 0), pane("%1", 55, 24, 0, 25)])),
+        ]);
+        let report = apply_layout(&runner, "work", &layout("ROW(a 30%, b 70%)")).unwrap();
+        assert!(report.converged);
+        assert_eq!(
+            report.commands.last().unwrap(),
+            "tmux split-window -t %0 -h -p 70 -P -F '#{pane_id}'"
+        );
+    }
+
+    #[test]
+    fn apply_is_idempotent() {
+        let runner = MockRunner::with_responses(vec![Ok(listing(&[
+            pane("%0", 40, 24, 0, 0),
+            pane("%1", 39, 24, 0, 41),
+        ]))]);
+        let report = apply_layout(&runner, "work", &layout("ROW(a, b)")).unwrap();
+        assert!(report.converged);
+        assert!(report.commands.is_empty());
+    }
+
+    #[test]
+    fn place_agents_tags_and_launches() {
+        let runner = MockRunner::with_responses(vec![Ok(listing(&[
+            pane("%0", 40, 24, 0, 0),
+            tagged("%1", 39, 24, 0, 41, "shell"),
+        ]))]);
+        let launch = |agent: &str| (agent == "pm").then(|| "claude".to_string());
+        let placement = place_agents(&runner, "work", &layout("ROW(pm, shell)"), &launch).unwrap();
+        assert_eq!(placement.placed, vec!["pm"]);
+        assert_eq!(
+            runner.executed_commands()[1..],
+            [
+                "tmux set-option -p -t %0 @mux_agent pm",
+                "tmux send-keys -t %0 claude Enter",
+            ]
+        );
+    }
+
+    #[test]
+    fn place_agents_applies_pane_options() {
+        let runner = MockRunner::with_responses(vec![Ok(listing(&[
+            pane("%0", 40, 24, 0, 0),
+            tagged("%1", 39, 24, 0, 41, "logs"),
+        ]))]);
+        let desired = layout(r#"ROW(build[cmd="cargo watch", cwd="~/my proj"], logs[title=tail, focus])"#);
+        let launch = |_: &str| Some("claude".to_string());
+        let placement = place_agents(&runner, "work", &desired, &launch).unwrap();
+        assert_eq!(placement.placed, vec!["build"]);
+        assert_eq!(
+            runner.executed_commands()[1..],
+            [
+                "tmux set-option -p -t %0 @mux_agent build",
+                "tmux send-keys -t %0 'cd ~/'\\''my proj'\\''' Enter",
+                "tmux send-keys -t %0 'cargo watch' Enter",
+                "tmux select-pane -t %1 -T tail",
+                "tmux select-pane -t %1",
+            ]
+        );
+    }
+
+    #[test]
+    fn place_agents_leaves_busy_panes_alone() {
+        let busy = TmuxPane {
+            command: "claude".into(),
+            ..tagged("%0", 40, 24, 0, 0, "dev")
+        };
+        let idle = TmuxPane {
+            command: "zsh".into(),
+            ..tagged("%1", 39, 24, 0, 41, "dev")
+        };
+        let runner = MockRunner::with_responses(vec![Ok(listing(&[busy, idle]))]);
+        let launch = |_: &str| Some("claude".to_string());
+        let placement = place_agents(&runner, "work", &layout("ROW(pm, qa)"), &launch).unwrap();
+        assert_eq!(placement.placed, vec!["qa"]);
+        assert_eq!(placement.conflicts, vec!["pm (%0 runs dev)"]);
+        assert_eq!(
+            runner.executed_commands()[1..],
+            [
+                "tmux set-option -p -t %1 @mux_agent qa",
+                "tmux send-keys -t %1 claude Enter",
+            ]
+        );
+    }
+
+    #[test]
+    fn place_agents_types_nothing_into_busy_panes() {
+        let tagged_busy = TmuxPane {
+            command: "claude".into(),
+            ..tagged("%0", 40, 24, 0, 0, "dev")
+        };
+        let untagged_busy = TmuxPane {
+            command: "vim".into(),
+            ..pane("%1", 39, 24, 0, 41)
+        };
+        let runner = MockRunner::with_responses(vec![Ok(listing(&[tagged_busy, untagged_busy]))]);
+        let desired = layout("ROW(pm[cwd=/srv, cmd=make, title=build], qa[cwd=~/qa, cmd=test])");
+        let placement = place_agents(&runner, "work", &desired, &|_| None).unwrap();
+        assert!(placement.placed.is_empty());
+        assert_eq!(placement.conflicts, vec!["pm (%0 runs dev)", "qa (%1 runs vim)"]);
+        assert_eq!(runner.executed_commands()[1..], ["tmux select-pane -t %0 -T build"]);
+    }
+
+    #[test]
+    fn place_agents_rejects_mismatched_shape() {
+        let runner = MockRunner::with_responses(vec![Ok(listing(&[pane("%0", 80, 24, 0, 0)]))]);
+        let err = place_agents(&runner, "work", &layout("ROW(a, b)"), &|_| None).unwrap_err();
+        assert!(err.contains("1 panes"));
+    }
+
+    #[test]
+    fn apply_reports_panes_that_are_not_a_tree_of_splits() {
+        // A pinwheel around a center pane has no straight cut.
+        let pinwheel = [
+            pane("%0", 21, 10, 0, 0),
+            pane("%1", 10, 21, 0, 22),
+            pane("%2", 21, 10, 22, 11),
+            pane("%3", 10, 21, 11, 0),
+            pane("%4", 10, 10, 11, 11),
+        ];
+        assert!(plan(&pinwheel, &layout("ROW(a, b)")).is_err());
+        let runner = MockRunner::with_responses(vec![Ok(listing(&pinwheel))]);
+        let err = apply_layout(&runner, "work", &layout("ROW(a, b)")).unwrap_err();
+        assert!(err.starts_with("cannot read the layout of 'work'"), "{}", err);
+    }
+
+    #[test]
+    fn apply_sets_sizes_with_select_layout() {
+        let runner = MockRunner::with_responses(vec![
+            Ok(listing(&[pane("%0", 40, 24, 0, 0), pane("%1", 39, 24, 0, 41)])),
+            Ok(String::new()),
+            Ok(listing(&[pane("%0", 20, 24, 0, 0), pane("%1", 59, 24, 0, 21)])),
+        ]);
+        let report = apply_layout(&runner, "work", &layout("ROW(a 25%, b 75%)")).unwrap();
+        assert!(report.converged);
+        assert_eq!(
+            report.commands,
+            vec!["tmux select-layout -t work '7825,80x24,0,0{20x24,0,0,0,59x24,21,0,1}'"]
+        );
+    }
+
+    #[test]
+    fn apply_solves_cell_sizes_for_the_window() {
+        let runner = MockRunner::with_responses(vec![
+            Ok(listing(&[pane("%0", 40, 24, 0, 0), pane("%1", 39, 24, 0, 41)])),
+            Ok(String::new()),
+            Ok(listing(&[pane("%0", 20, 24, 0, 0), pane("%1", 59, 24, 0, 21)])),
+        ]);
+        let report = apply_layout(&runner, "work", &layout("ROW(a 20c, b *)")).unwrap();
+        assert!(report.converged);
+        assert_eq!(
+            report.commands,
+            vec!["tmux select-layout -t work '7825,80x24,0,0{20x24,0,0,0,59x24,21,0,1}'"]
+        );
+    }
+
+    #[test]
+    fn apply_reports_missing_session() {
+        let runner = MockRunner::with_responses(vec![Err("can't find session: x".into())]);
+        assert!(apply_layout(&runner, "x", &layout("a")).is_err());
+    }
+}
@@ -0,0 +1,430 @@
+//! Session snapshots — capture and restore every window of a session.
+//!
+//! `capture_snapshot` walks `list-windows` and captures each window's layout
+//! tree (agents included) into a `SessionSnapshot`. `restore_session` brings
+//! a session back from one: it creates the session if needed, recreates or
+//! renames each window, restores its panes with tmux's own layout string or
+//! reconciles them with `apply_layout`, and starts its agents with
+//! `place_agents`. Windows the snapshot does not mention are
+//! left alone.
+
+use std::collections::HashSet;
+
+use crate::infrastructure::runner::CommandRunner;
+use crate::infrastructure::tmux::{parse_list_panes, parse_list_windows, TmuxCommandBuilder};
+use crate::layout::capture::{capture_session, capture_window};
+use crate::layout::reconcile::{apply_layout, place_agents};
+use crate::types::session::{SessionSnapshot, WindowSnapshot};
+
+
+/// What a restore did.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestoreReport {
+    /// Whether the tmux session had to be created.
+    pub created_session: bool,
+    /// `session:index` target of each restored window, in snapshot order.
+    pub windows: Vec<String>,
+    /// Agents started across all windows.
+    pub placed: Vec<String>,
+    /// Agents left unstarted because their pane runs another agent.
+    pub conflicts: Vec<String>,
+}
+
+
+/// Capture every window of `session` through `runner`.
+///
+/// Uses each window's `#{window_layout}` when tmux reports one and falls
+/// back to reconstructing the tree from pane geometry otherwise.
+pub fn capture_snapshot(
+    runner: &dyn CommandRunner,
+    session: &str,
+    now_ms: u64,
+) -> Result<SessionSnapshot, String> {
+    let builder = TmuxCommandBuilder::new();
+    let output = runner
+        .run(&builder.list_windows(session))
+        .map_err(|e| format!("cannot list windows of '{}': {}", session, e.trim()))?;
+    let windows = parse_list_windows(&output);
+    if windows.is_empty() {
+        return Err(format!("no windows found for '{}'", session));
+    }
+
+    let mut snapshot = SessionSnapshot {
+        session: session.to_string(),
+        windows: Vec::new(),
+        timestamp_ms: now_ms,
+    };
+    for window in windows {
+        let target = format!("{}:{}", session, window.index);
+        let panes = runner
+            .run(&builder.list_panes(&target))
+            .map_err(|e| format!("cannot list panes of '{}': {}", target, e.trim()))?;
+        let result = if window.layout.is_empty() {
+            capture_session(&target, &panes, None, 0, now_ms)?
+        } else {
+            capture_window(&target, &window.layout, &panes, None, 0, now_ms)?
+        };
+        snapshot.windows.push(WindowSnapshot {
+            index: window.index,
+            name: window.name,
+            layout: result.layout,
+            tmux_layout: result.tmux_layout,
+        });
+    }
+    Ok(snapshot)
+}
+
+
+/// Restore `snapshot` into the tmux session `session` through `runner`.
+///
+/// A missing session is created in `cwd` and its first window takes the
+/// place of the snapshot's first window. Otherwise windows are matched by
+/// index; a missing one is created at its index, or appended when that slot
+/// is already taken. Unnamed windows keep whatever name tmux gives them.
+/// `launch` maps an agent name to its start command, as for `place_agents`.
+/// The first window with a `focus` pane is selected at the end.
+pub fn restore_session(
+    runner: &dyn CommandRunner,
+    snapshot: &SessionSnapshot,
+    session: &str,
+    cwd: &str,
+    launch: &dyn Fn(&str) -> Option<String>,
+) -> Result<RestoreReport, String> {
+    if snapshot.windows.is_empty() {
+        return Err("snapshot has no windows".into());
+    }
+    let builder = TmuxCommandBuilder::new();
+    let run = |command: &str| {
+        runner
+            .run(command)
+            .map_err(|e| format!("{} (cmd: {})", e.trim(), command))
+    };
+
+    let created_session = runner.run(&builder.has_session(session)).is_err();
+    if created_session {
+        run(&builder.new_session(session, cwd))?;
+    }
+    let output = runner
+        .run(&builder.list_windows(session))
+        .map_err(|e| format!("cannot list windows of '{}': {}", session, e.trim()))?;
+    let existing = parse_list_windows(&output);
+    let mut taken: HashSet<u32> = existing.iter().map(|w| w.index).collect();
+    let mut used: HashSet<u32> = HashSet::new();
+
+    let mut report = RestoreReport {
+        created_session,
+        windows: Vec::new(),
+        placed: Vec::new(),
+        conflicts: Vec::new(),
+    };
+    for (i, window) in snapshot.windows.iter().enumerate() {
+        let reuse = if created_session && i == 0 {
+            existing.first()
+        } else if created_session {
+            None
+        } else {
+            existing.iter().find(|w| w.index == window.index)
+        };
+        let index = match reuse.filter(|w| !used.contains(&w.index)) {
+            Some(live) => {
+                if !window.name.is_empty() && live.name != window.name {
+                    run(&builder.rename_window(
+                        &format!("{}:{}", session, live.index),
+                        &window.name,
+                    ))?;
+                }
+                live.index
+            }
+            None => {
+                let slot = if taken.contains(&window.index) {
+                    format!("{}:", session)
+                } else {
+                    format!("{}:{}", session, window.index)
+                };
+                let printed = run(&builder.new_window(&slot, &window.name, cwd))?;
+                printed.trim().parse().unwrap_or(window.index)
+            }
+        };
+        taken.insert(index);
+        used.insert(index);
+
+        let target = format!("{}:{}", session, index);
+        restore_layout(runner, &target, window)?;
+        let placement = place_agents(runner, &target, &window.layout, launch)?;
+        report.placed.extend(placement.placed);
+        report.conflicts.extend(placement.conflicts);
+        report.windows.push(target);
+    }
+    let focused = snapshot
+        .windows
+        .iter()
+        .position(|w| w.layout.pane_options().iter().any(|o| o.focus));
+    if let Some(i) = focused {
+        run(&builder.select_window(&report.windows[i]))?;
+    }
+    Ok(report)
+}
+
+
+/// Bring the live window `target` into the shape of `window`.
+///
+/// A window captured from tmux carries tmux's own layout string; when the
+/// live window already has as many panes, `select-layout` with that string
+/// restores the exact geometry. Otherwise the layout tree is reconciled
+/// with `apply_layout`.
+fn restore_layout(
+    runner: &dyn CommandRunner,
+    target: &str,
+    window: &WindowSnapshot,
+) -> Result<(), String> {
+    let builder = TmuxCommandBuilder::new();
+    if let Some(tmux_layout) = &window.tmux_layout {
+        let output = runner
+            .run(&builder.list_panes(target))
+            .map_err(|e| format!("cannot list panes of '{}': {}", target, e.trim()))?;
+        if parse_list_panes(&output).len() == window.layout.pane_options().len() {
+            let command = builder.select_layout(target, tmux_layout);
+            runner
+                .run(&command)
+                .map_err(|e| format!("{} (cmd: {})", e.trim(), command))?;
+            return Ok(());
+        }
+    }
+    apply_layout(runner, target, &window.layout).map(|_| ())
+}
+
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::layout_expr::parse_layout_expr;
+    use crate::infrastructure::runner::MockRunner;
+    use crate::types::session::LayoutNode;
+
+    fn single(agent: &str) -> LayoutNode {
+        LayoutNode::pane(agent)
+    }
+
+    fn snapshot(windows: &[(u32, &str, &str)]) -> SessionSnapshot {
+        SessionSnapshot {
+            session: "work".into(),
+            windows: windows
+                .iter()
+                .map(|(index, name, agent)| WindowSnapshot {
+                    index: *index,
+                    name: name.to_string(),
+                    layout: single(agent),
+                    tmux_layout: None,
+                })
+                .collect(),
+            timestamp_ms: 0,
+        }
+    }
+
+    const ONE_PANE: &str = "%0\t0\t80\t24\t0\t0\t1\t\tzsh\t\n";
+
+    #[test]
+    fn capture_snapshot_reads_every_window() {
+        let runner = MockRunner::with_responses(vec![
+            Ok("0:editor:2:7825,80x24,0,0{20x24,0,0,0,59x24,21,0,1}\n1:logs:1:b25d,80x24,0,0,0\n".into()),
+            Ok("%0\t0\t20\t24\t0\t0\t1\tpm\tclaude\t\n%1\t1\t59\t24\t0\t21\t0\tworker\tzsh\t\n".into()),
+            Ok(ONE_PANE.into()),
+        ]);
+        let snap = capture_snapshot(&runner, "work", 42).unwrap();
+        assert_eq!(snap.session, "work");
+        assert_eq!(snap.timestamp_ms, 42);
+        assert_eq!(snap.windows.len(), 2);
+        assert_eq!(snap.windows[0].name, "editor");
+        assert_eq!(snap.windows[1].index, 1);
+        assert_eq!(snap.windows[1].name, "logs");
+        match &snap.windows[0].layout {
+            LayoutNode::Row { children } => {
+                assert_eq!(children[0].node, single("pm"));
+                assert_eq!(children[1].node, single("worker"));
+            }
+            other => panic!("expected ROW, got {:?}", other),
+        }
+        let cmds = runner.executed_commands();
+        assert!(cmds[1].contains("list-panes -t work:0"));
+        assert!(cmds[2].contains("list-panes -t work:1"));
+    }
+
+    #[test]
+    fn capture_snapshot_reports_missing_session() {
+        let runner = MockRunner::with_responses(vec![Err("can't find session: nope".into())]);
+        let err = capture_snapshot(&runner, "nope", 0).unwrap_err();
+        assert!(err.contains("can't find session"));
+    }
+
+    #[test]
+    fn restore_creates_session_and_windows() {
+        let runner = MockRunner::with_responses(vec![
+            Err("no such session".into()), // has-session
+            Ok(String::new()),             // new-session
+            Ok("0:zsh:1:\n".into()),       // list-windows
+            Ok(String::new()),             // rename-window
+            Ok(ONE_PANE.into()),           // apply: list-panes
+            Ok(ONE_PANE.into()),           // place: list-panes
+            Ok(String::new()),             // tag
+            Ok("1\n".into()),              // new-window
+            Ok(ONE_PANE.into()),
+            Ok(ONE_PANE.into()),
+            Ok(String::new()),
+        ]);
+        let snap = snapshot(&[(0, "main", "pm"), (1, "logs", "worker")]);
+        let report = restore_session(&runner, &snap, "work", "/tmp", &|_| None).unwrap();
+        assert!(report.created_session);
+        assert_eq!(report.windows, vec!["work:0", "work:1"]);
+        assert_eq!(report.placed, vec!["pm", "worker"]);
+
+        let cmds = runner.executed_commands();
+        assert_eq!(cmds[1], "tmux new-session -d -s work -c /tmp");
+        assert_eq!(cmds[3], "tmux rename-window -t work:0 main");
+        assert_eq!(
+            cmds[7],
+            "tmux new-window -d -P -F '#{window_index}' -t work:1 -n logs -c /tmp"
+        );
+    }
+
+    #[test]
+    fn restore_reuses_matching_windows() {
+        let runner = MockRunner::with_responses(vec![
+            Ok(String::new()),                   // has-session
+            Ok("0:main:1:\n1:other:1:\n".into()), // list-windows
+            Ok(ONE_PANE.into()),
+            Ok("%0\t0\t80\t24\t0\t0\t1\tpm\tclaude\t\n".into()),
+        ]);
+        let snap = snapshot(&[(0, "main", "pm")]);
+        let report = restore_session(&runner, &snap, "work", "/tmp", &|_| None).unwrap();
+        assert!(!report.created_session);
+        assert!(report.placed.is_empty());
+        let cmds = runner.executed_commands();
+        assert_eq!(cmds.len(), 4);
+        assert!(!cmds.iter().any(|c| c.contains("rename-window") || c.contains("kill")));
+    }
+
+    #[test]
+    fn restore_appends_when_slot_is_taken() {
+        let runner = MockRunner::with_responses(vec![
+            Err("no such session".into()),
+            Ok(String::new()),
+            Ok("1:zsh:1:\n".into()), // base-index 1
+            Ok(String::new()),
+            Ok(ONE_PANE.into()),
+            Ok(ONE_PANE.into()),
+            Ok(String::new()),
+            Ok("2\n".into()),
+            Ok(ONE_PANE.into()),
+            Ok(ONE_PANE.into()),
+            Ok(String::new()),
+        ]);
+        let snap = snapshot(&[(0, "main", "pm"), (1, "logs", "worker")]);
+        let report = restore_session(&runner, &snap, "work", "/tmp", &|_| None).unwrap();
+        assert_eq!(report.windows, vec!["work:1", "work:2"]);
+        assert!(runner.executed_commands()[7].contains("-t work: -n logs"));
+    }
+
+    #[test]
+    fn restore_selects_the_focused_window() {
+        let runner = MockRunner::with_responses(vec![
+            Ok(String::new()),
+            Ok("0:main:1:\n1:logs:1:\n".into()),
+            Ok(ONE_PANE.into()),
+            Ok("%0\t0\t80\t24\t0\t0\t1\tpm\tclaude\t\n".into()),
+            Ok(ONE_PANE.into()),
+            Ok("%0\t0\t80\t24\t0\t0\t1\tworker\tzsh\t\n".into()),
+        ]);
+        let mut snap = snapshot(&[(0, "main", "pm"), (1, "logs", "worker")]);
+        if let LayoutNode::Pane { options, .. } = &mut snap.windows[1].layout {
+            options.focus = true;
+        }
+        restore_session(&runner, &snap, "work", "/tmp", &|_| None).unwrap();
+        let cmds = runner.executed_commands();
+        assert_eq!(cmds[cmds.len() - 2..], ["tmux select-pane -t %0", "tmux select-window -t work:1"]);
+    }
+
+    #[test]
+    fn restore_uses_the_captured_tmux_layout() {
+        let captured = "7825,80x24,0,0{20x24,0,0,0,59x24,21,0,1}";
+        let two_panes = "%0\t0\t40\t24\t0\t0\t1\t\tzsh\t\n%1\t1\t39\t24\t0\t41\t0\t\tzsh\t\n";
+        let runner = MockRunner::with_responses(vec![
+            Ok(String::new()),         // has-session
+            Ok("0:main:2:\n".into()), // list-windows
+            Ok(two_panes.into()),      // list-panes: counts match
+            Ok(String::new()),         // select-layout
+            Ok(two_panes.into()),      // place: list-panes
+            Ok(String::new()),
+            Ok(String::new()),
+        ]);
+        let mut snap = snapshot(&[(0, "main", "pm")]);
+        snap.windows[0].layout = parse_layout_expr("ROW(pm 25%, qa 75%)").unwrap();
+        snap.windows[0].tmux_layout = Some(captured.into());
+        let report = restore_session(&runner, &snap, "work", "/tmp", &|_| None).unwrap();
+        assert_eq!(report.placed, vec!["pm", "qa"]);
+        let cmds = runner.executed_commands();
+        assert_eq!(cmds[3], format!("tmux select-layout -t work:0 '{}'", captured));
+        assert!(!cmds.iter().any(|c| c.contains("split-window")));
+    }
+
+    #[test]
+    fn restore_applies_the_tree_when_pane_counts_differ() {
+        let runner = MockRunner::with_responses(vec![
+            Ok(String::new()),
+            Ok("0:main:1:\n".into()),
+            Ok(ONE_PANE.into()), // list-panes: one pane, two leaves
+            Ok(ONE_PANE.into()), // apply: list-panes
+        ]);
+        let mut snap = snapshot(&[(0, "main", "pm")]);
+        snap.windows[0].layout = parse_layout_expr("ROW(pm, qa)").unwrap();
+        snap.windows[0].tmux_layout = Some("7825,80x24,0,0{20x24,0,0,0,59x24,21,0,1}".into());
+        let _ = restore_session(&runner, &snap, "work", "/tmp", &|_| None);
+        let cmds = runner.executed_commands();
+        assert!(!cmds.iter().any(|c| c.contains("select-layout -t work:0 '7825")));
+        // The structural path starts by listing the panes again.
+        assert!(cmds[3].contains("list-panes -t work:0"));
+    }
+
+    #[test]
+    fn captured_session_restores_with_select_layout() {
+        let editor = "7825,80x24,0,0{20x24,0,0,0,59x24,21,0,1}";
+        let logs = "b25d,80x24,0,0,0";
+        let editor_panes = "%0\t0\t20\t24\t0\t0\t1\tpm\tclaude\t\n%1\t1\t59\t24\t0\t21\t0\tqa\tclaude\t\n";
+        let logs_panes = "%2\t0\t80\t24\t0\t0\t1\ttail\tzsh\t\n";
+        let windows = format!("0:editor:2:{}\n1:logs:1:{}\n", editor, logs);
+        let capture = MockRunner::with_responses(vec![
+            Ok(windows.clone()),
+            Ok(editor_panes.into()),
+            Ok(logs_panes.into()),
+        ]);
+        let snap = capture_snapshot(&capture, "work", 0).unwrap();
+
+        let restore = MockRunner::with_responses(vec![
+            Ok(String::new()),      // has-session
+            Ok(windows),            // list-windows
+            Ok(editor_panes.into()),
+            Ok(String::new()),      // select-layout editor
+            Ok(editor_panes.into()),
+            Ok(logs_panes.into()),
+            Ok(String::new()),      // select-layout logs
+            Ok(logs_panes.into()),
+        ]);
+        let report = restore_session(&restore, &snap, "work", "/tmp", &|_| None).unwrap();
+        assert_eq!(report.windows, vec!["work:0", "work:1"]);
+        assert!(report.placed.is_empty());
+        let cmds = restore.executed_commands();
+        assert_eq!(cmds[3], format!("tmux select-layout -t work:0 '{}'", editor));
+        assert_eq!(cmds[6], format!("tmux select-layout -t work:1 '{}'", logs));
+    }
+
+    #[test]
+    fn restore_rejects_empty_snapshot() {
+        let runner = MockRunner::new();
+        let snap = snapshot(&[]);
+        assert!(restore_session(&runner, &snap, "work", "/tmp", &|_| None).is_err());
+        assert!(runner.executed_commands().is_empty());
+    }
+}
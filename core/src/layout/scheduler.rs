@@ -0,0 +1,356 @@
+//! Snapshot scheduler — drives `SnapshotTimer` from the daemon loop.
+//!
+//! Each `tick` lists the live sessions, forgets the ones that are gone,
+//! captures the ones the timer says are due, and returns what changed as
+//! `MuxEvent`s: sessions created or closed, layouts changed, the active pane
+//! moved, or a tagged agent's pane disappeared. When a session changes and
+//! a history store is configured, every window of it is captured and
+//! written to the history; a failed write comes back as `HistoryFailed`.
+//!
+//! A layout only counts as changed when `snapshot::diff` finds something
+//! beyond the tolerance. Each session is compared against its last changed
+//! capture, so a pane that creeps a percent at a time is reported once the
+//! drift adds up.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::event::MuxEvent;
+use crate::infrastructure::runner::CommandRunner;
+use crate::infrastructure::tmux::{parse_list_panes, parse_list_sessions, TmuxCommandBuilder};
+use crate::layout::capture::capture_all_sessions;
+use crate::layout::history::HistoryStore;
+use crate::layout::restore::capture_snapshot;
+use crate::layout::timer::SnapshotTimer;
+use crate::types::agent::AgentStatus;
+use crate::types::session::LayoutNode;
+
+
+/// How often the scheduler looks for new or closed sessions, at most.
+const SCAN_INTERVAL_MS: u64 = 1_000;
+
+
+/// Periodic layout capture for every tmux session.
+pub struct SnapshotScheduler {
+    timer: SnapshotTimer,
+    /// Percentage points of share change to treat as noise.
+    tolerance: u32,
+    previous: HashMap<String, LayoutNode>,
+    history: Option<HistoryStore>,
+    last_scan_ms: Option<u64>,
+    /// Sessions seen by the last scan; `None` before the first one.
+    sessions: Option<Vec<String>>,
+    /// Active pane of each session at its last capture.
+    focus: HashMap<String, String>,
+    /// Tagged agents of each session at its last capture.
+    agents: HashMap<String, BTreeSet<String>>,
+}
+
+
+impl SnapshotScheduler {
+    /// Capture each session every `interval_ms`, ignoring share changes of
+    /// up to `tolerance` percentage points and recording changes in
+    /// `history` when given.
+    pub fn new(interval_ms: u64, tolerance: u32, history: Option<HistoryStore>) -> Self {
+        SnapshotScheduler {
+            timer: SnapshotTimer::new(interval_ms),
+            tolerance,
+            previous: HashMap::new(),
+            history,
+            last_scan_ms: None,
+            sessions: None,
+            focus: HashMap::new(),
+            agents: HashMap::new(),
+        }
+    }
+
+    /// The layout `session` had at its last reported change.
+    pub fn previous(&self, session: &str) -> Option<&LayoutNode> {
+        self.previous.get(session)
+    }
+
+    /// When each tracked session was last captured.
+    pub fn last_captures(&self) -> HashMap<String, u64> {
+        self.timer
+            .tracked()
+            .into_iter()
+            .filter_map(|s| self.timer.last_capture(&s).map(|ms| (s, ms)))
+            .collect()
+    }
+
+    /// Run one round if a scan is due, returning what changed.
+    ///
+    /// The first scan only establishes a baseline of sessions, focus, and
+    /// agents; layouts are reported from the first capture on. tmux errors
+    /// (e.g. no server running) are treated as no sessions.
+    pub fn tick(&mut self, runner: &dyn CommandRunner, now_ms: u64) -> Vec<MuxEvent> {
+        if let Some(last) = self.last_scan_ms {
+            if now_ms.saturating_sub(last) < SCAN_INTERVAL_MS.min(self.timer.interval_ms()) {
+                return Vec::new();
+            }
+        }
+        self.last_scan_ms = Some(now_ms);
+
+        let builder = TmuxCommandBuilder::new();
+        let sessions = match runner.run(&builder.list_sessions()) {
+            Ok(output) => parse_list_sessions(&output),
+            Err(_) => Vec::new(),
+        };
+        let mut events = Vec::new();
+        if let Some(known) = &self.sessions {
+            for session in sessions.iter().filter(|s| !known.contains(s)) {
+                events.push(MuxEvent::SessionCreated {
+                    session: session.clone(),
+                });
+            }
+            for session in known.iter().filter(|s| !sessions.contains(s)) {
+                events.push(MuxEvent::SessionClosed {
+                    session: session.clone(),
+                });
+            }
+        }
+        for stale in self.timer.tracked() {
+            if !sessions.contains(&stale) {
+                self.timer.remove_session(&stale);
+                self.previous.remove(&stale);
+                self.focus.remove(&stale);
+                events.extend(agents_gone(self.agents.remove(&stale), &BTreeSet::new()));
+            }
+        }
+        self.sessions = Some(sessions.clone());
+
+        let due = self.timer.sessions_due(&sessions, now_ms);
+        let mut pane_outputs = HashMap::new();
+        for session in &due {
+            self.timer.record_capture(session, now_ms);
+            if let Ok(output) = runner.run(&builder.list_panes(session)) {
+                let panes = parse_list_panes(&output);
+                if let Some(active) = panes.iter().find(|p| p.active) {
+                    let moved = self
+                        .focus
+                        .insert(session.clone(), active.id.clone())
+                        .is_some_and(|before| before != active.id);
+                    if moved {
+                        events.push(MuxEvent::PaneFocused {
+                            session: session.clone(),
+                            pane: active.id.clone(),
+                        });
+                    }
+                }
+                let tagged: BTreeSet<String> =
+                    panes.iter().filter_map(|p| p.agent.clone()).collect();
+                let before = self.agents.insert(session.clone(), tagged.clone());
+                events.extend(agents_gone(before, &tagged));
+                pane_outputs.insert(session.clone(), output);
+            }
+        }
+
+        let results =
+            capture_all_sessions(&due, &pane_outputs, &self.previous, self.tolerance, now_ms);
+        for result in results {
+            if !result.changed {
+                continue;
+            }
+            self.previous
+                .insert(result.session.clone(), result.layout.clone());
+            if let Some(history) = &self.history {
+                let recorded = capture_snapshot(runner, &result.session, now_ms)
+                    .and_then(|snapshot| history.append(&snapshot));
+                if let Err(error) = recorded {
+                    events.push(MuxEvent::HistoryFailed {
+                        session: result.session.clone(),
+                        error,
+                    });
+                }
+            }
+            events.push(MuxEvent::LayoutChanged {
+                session: result.session,
+                layout: result.layout_expr,
+            });
+        }
+        events
+    }
+}
+
+
+/// `AgentStatusChanged` (dead) for each agent in `before` missing from `now`.
+fn agents_gone(before: Option<BTreeSet<String>>, now: &BTreeSet<String>) -> Vec<MuxEvent> {
+    before
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|agent| !now.contains(agent))
+        .map(|agent| MuxEvent::AgentStatusChanged {
+            agent,
+            status: AgentStatus::Dead,
+        })
+        .collect()
+}
+
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::runner::MockRunner;
+
+    const TWO_PANES: &str = "%0\t0\t40\t24\t0\t0\t1\t\tzsh\t\n%1\t1\t39\t24\t0\t41\t0\t\tzsh\t\n";
+
+    fn kinds(events: &[MuxEvent]) -> Vec<&'static str> {
+        events.iter().map(MuxEvent::kind).collect()
+    }
+
+    #[test]
+    fn first_tick_captures_every_session() {
+        let runner = MockRunner::with_responses(vec![
+            Ok("work\nplay\n".into()),
+            Ok(TWO_PANES.into()),
+            Ok(TWO_PANES.into()),
+        ]);
+        let mut scheduler = SnapshotScheduler::new(5_000, 2, None);
+        let events = scheduler.tick(&runner, 10_000);
+        assert_eq!(kinds(&events), vec!["layout_changed", "layout_changed"]);
+        match &events[0] {
+            MuxEvent::LayoutChanged { session, .. } => assert_eq!(session, "work"),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(scheduler.previous("work"), Some(LayoutNode::Row { .. })));
+        assert_eq!(scheduler.last_captures().get("play"), Some(&10_000));
+    }
+
+    #[test]
+    fn unchanged_layout_is_not_reported() {
+        let runner = MockRunner::with_responses(vec![
+            Ok("work\n".into()),
+            Ok(TWO_PANES.into()),
+            Ok("work\n".into()),
+            Ok(TWO_PANES.into()),
+        ]);
+        let mut scheduler = SnapshotScheduler::new(5_000, 2, None);
+        assert_eq!(scheduler.tick(&runner, 10_000).len(), 1);
+        assert!(scheduler.tick(&runner, 15_000).is_empty());
+        assert_eq!(runner.executed_commands().len(), 4);
+    }
+
+    #[test]
+    fn jitter_is_ignored_until_it_adds_up() {
+        let runner = MockRunner::with_responses(vec![
+            Ok("work\n".into()),
+            Ok(TWO_PANES.into()),
+            Ok("work\n".into()),
+            Ok("%0\t0\t41\t24\t0\t0\t1\t\tzsh\t\n%1\t1\t38\t24\t0\t42\t0\t\tzsh\t\n".into()),
+            Ok("work\n".into()),
+            Ok("%0\t0\t43\t24\t0\t0\t1\t\tzsh\t\n%1\t1\t36\t24\t0\t44\t0\t\tzsh\t\n".into()),
+        ]);
+        let mut scheduler = SnapshotScheduler::new(5_000, 2, None);
+        assert_eq!(scheduler.tick(&runner, 10_000).len(), 1);
+        assert!(scheduler.tick(&runner, 15_000).is_empty());
+        let events = scheduler.tick(&runner, 20_000);
+        assert_eq!(kinds(&events), vec!["layout_changed"]);
+    }
+
+    #[test]
+    fn scans_are_throttled_and_sessions_wait_for_interval() {
+        let runner = MockRunner::with_responses(vec![
+            Ok("work\n".into()),
+            Ok(TWO_PANES.into()),
+            Ok("work\n".into()),
+        ]);
+        let mut scheduler = SnapshotScheduler::new(5_000, 2, None);
+        scheduler.tick(&runner, 10_000);
+        // Too soon to scan at all.
+        assert!(scheduler.tick(&runner, 10_500).is_empty());
+        assert_eq!(runner.executed_commands().len(), 2);
+        // Scanned, but the session is not due yet.
+        assert!(scheduler.tick(&runner, 12_000).is_empty());
+        assert_eq!(runner.executed_commands().len(), 3);
+    }
+
+    #[test]
+    fn sessions_coming_and_going_are_reported() {
+        let runner = MockRunner::with_responses(vec![
+            Ok("work\n".into()),
+            Ok("%0\t0\t80\t24\t0\t0\t1\tpm\tclaude\t\n".into()),
+            Ok("play\n".into()),
+            Ok(TWO_PANES.into()),
+        ]);
+        let mut scheduler = SnapshotScheduler::new(5_000, 2, None);
+        scheduler.tick(&runner, 10_000);
+        let events = scheduler.tick(&runner, 20_000);
+        assert_eq!(
+            kinds(&events),
+            vec!["session_created", "session_closed", "agent_status_changed", "layout_changed"]
+        );
+        assert!(scheduler.previous("work").is_none());
+    }
+
+    #[test]
+    fn focus_changes_are_reported() {
+        let runner = MockRunner::with_responses(vec![
+            Ok("work\n".into()),
+            Ok(TWO_PANES.into()),
+            Ok("work\n".into()),
+            Ok("%0\t0\t40\t24\t0\t0\t0\t\tzsh\t\n%1\t1\t39\t24\t0\t41\t1\t\tzsh\t\n".into()),
+        ]);
+        let mut scheduler = SnapshotScheduler::new(5_000, 2, None);
+        scheduler.tick(&runner, 10_000);
+        let events = scheduler.tick(&runner, 15_000);
+        assert_eq!(
+            events,
+            vec![MuxEvent::PaneFocused {
+                session: "work".into(),
+                pane: "%1".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn changes_are_written_to_history() {
+        let dir = std::env::temp_dir().join(format!("muxux-scheduler-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let runner = MockRunner::with_responses(vec![
+            Ok("work\n".into()),
+            Ok(TWO_PANES.into()),
+            // history: every window, with its real index and name
+            Ok("1:editor:2:\n3:logs:1:\n".into()),
+            Ok(TWO_PANES.into()),
+            Ok("%2\t0\t80\t24\t0\t0\t1\t\tzsh\t\n".into()),
+        ]);
+        let mut scheduler = SnapshotScheduler::new(5_000, 2, Some(HistoryStore::new(&dir)));
+        scheduler.tick(&runner, 10_000);
+        let entries = HistoryStore::new(&dir).entries("work");
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(entries.len(), 1);
+        let windows: Vec<(u32, &str)> = entries[0]
+            .windows
+            .iter()
+            .map(|w| (w.index, w.name.as_str()))
+            .collect();
+        assert_eq!(windows, vec![(1, "editor"), (3, "logs")]);
+        assert_eq!(entries[0].timestamp_ms, 10_000);
+    }
+
+    #[test]
+    fn history_failures_are_returned_as_events() {
+        let dir = std::env::temp_dir().join(format!("muxux-scheduler-bad-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        // A file where the history directory should be.
+        std::fs::write(dir.join("history"), "").unwrap();
+        let runner = MockRunner::with_responses(vec![
+            Ok("work\n".into()),
+            Ok(TWO_PANES.into()),
+            Ok("0:main:2:\n".into()),
+            Ok(TWO_PANES.into()),
+        ]);
+        let mut scheduler = SnapshotScheduler::new(5_000, 2, Some(HistoryStore::new(&dir)));
+        let events = scheduler.tick(&runner, 10_000);
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(kinds(&events), vec!["history_failed", "layout_changed"]);
+        assert!(matches!(
+            &events[0],
+            MuxEvent::HistoryFailed { session, error } if session == "work" && error.contains("cannot create")
+        ));
+    }
+}
@@ -1,183 +1,496 @@
 //! Layout snapshot — reconstruct `LayoutNode` tree from tmux pane geometry.
 //!
-//! When tmux reports pane positions and sizes, this module groups them into
-//! rows (same `top`) and columns (same `left`) and builds a recursive
+//! When tmux reports pane positions and sizes, this module finds the lines
+//! that cut the window into rows and columns and builds a recursive
 //! `LayoutNode` tree. This lets CMX compare the actual layout against the
-//! desired layout and detect drift.
+//! desired layout and detect drift. `diff` says what differs between two
+//! trees — panes added, removed, or moved, splits flipped, and children
+//! resized — ignoring percentage jitter below a tolerance.
 
-use crate::types::session::{LayoutEntry, LayoutNode, TmuxPane};
+use serde::{Deserialize, Serialize};
+
+use crate::infrastructure::tmux::child_weights;
+use crate::layout::algebra::{describe, split};
+use crate::types::session::{LayoutEntry, LayoutNode, SizeSpec, TmuxPane};
 
 /// Reconstruct a `LayoutNode` tree from a flat list of pane geometries.
 ///
 /// # Algorithm
 ///
+/// tmux only ever splits a pane in two along its full width or height, so
+/// every window is a guillotine layout and can be cut apart the same way:
+///
 /// 1. If there is only one pane, return a `Pane` node.
-/// 2. Group panes by `top` coordinate. If there are multiple groups, this
-///    is a column layout (panes stacked vertically).
-/// 3. Within each row-group, if there are multiple panes, they form a row
-///    layout (panes side by side horizontally).
-/// 4. Recurse to handle nested splits.
-/// 5. Compute percentage from pixel dimensions relative to the total.
-pub fn from_panes(panes: &[TmuxPane]) -> LayoutNode {
+/// 2. Look for horizontal cut lines — rows that no pane crosses. If there
+///    are any, the bands of panes between them are the children of a `Col`.
+/// 3. Otherwise look for vertical cut lines the same way, for a `Row`.
+/// 4. Recurse into each band, which can only be cut the other way.
+/// 5. Each child's percentage is its share of the parent, not counting the
+///    one-cell borders between children, as `tmux_layout` computes it.
+///
+/// Where cut lines line up, an area can be cut both ways. Then the tree with
+/// fewer splits wins, and rows of panes on a tie, the way tmux's `tiled`
+/// layout builds a grid.
+///
+/// Panes that overlap or cannot be cut apart into full-span bands are an
+/// error. This happens when a pane is zoomed: tmux then lists it over the
+/// panes it hides.
+pub fn from_panes(panes: &[TmuxPane]) -> Result<LayoutNode, String> {
     if panes.is_empty() {
-        return LayoutNode::Pane {
-            agent: String::new(),
-        };
+        return Ok(LayoutNode::pane(""));
     }
-    if panes.len() == 1 {
-        return LayoutNode::Pane {
-            agent: panes[0].agent.clone().unwrap_or_default(),
-        };
+    let panes: Vec<&TmuxPane> = panes.iter().collect();
+    cut(&panes, &|p| p.agent.clone().unwrap_or_default())
+}
+
+/// `from_panes` with each leaf named by its pane id (`%3`) instead of its
+/// agent, for callers that need to map the tree back to the panes.
+pub fn pane_id_tree(panes: &[TmuxPane]) -> Result<LayoutNode, String> {
+    if panes.is_empty() {
+        return Err("no panes".into());
+    }
+    let panes: Vec<&TmuxPane> = panes.iter().collect();
+    cut(&panes, &|p| p.id.clone())
+}
+
+/// One difference between two layouts, found by `diff`.
+///
+/// Paths are child indices from the root, as in `layout::algebra`; `path`
+/// and `to` point into the new layout, `from` into the old one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum LayoutChange {
+    /// A pane only the new layout has.
+    PaneAdded { agent: String, path: Vec<usize> },
+    /// A pane only the old layout had.
+    PaneRemoved { agent: String, path: Vec<usize> },
+    /// An agent whose pane is somewhere else in the tree.
+    AgentMoved {
+        agent: String,
+        from: Vec<usize>,
+        to: Vec<usize>,
+    },
+    /// A split that turned from COL into ROW (`row`) or back.
+    DirectionFlipped { path: Vec<usize>, row: bool },
+    /// A child whose share of its split changed by more than the tolerance.
+    Resized {
+        path: Vec<usize>,
+        from: u32,
+        to: u32,
+    },
+}
+
+impl std::fmt::Display for LayoutChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LayoutChange::PaneAdded { agent, path } => {
+                write!(f, "{} added at {}", pane_name(agent), describe(path))
+            }
+            LayoutChange::PaneRemoved { agent, path } => {
+                write!(f, "{} removed from {}", pane_name(agent), describe(path))
+            }
+            LayoutChange::AgentMoved { agent, from, to } => {
+                let (from, to) = (describe(from), describe(to));
+                write!(f, "{} moved from {} to {}", agent, from, to)
+            }
+            LayoutChange::DirectionFlipped { path, row } => {
+                let direction = if *row { "ROW" } else { "COL" };
+                write!(f, "split at {} is now a {}", describe(path), direction)
+            }
+            LayoutChange::Resized { path, from, to } => {
+                write!(f, "{} resized from {}% to {}%", describe(path), from, to)
+            }
+        }
+    }
+}
+
+/// The differences between two layouts: splits and sizes first, then
+/// moved, removed, and added panes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LayoutDiff {
+    pub changes: Vec<LayoutChange>,
+}
+
+impl LayoutDiff {
+    /// Whether the layouts are the same, up to the tolerance.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
     }
 
-    // Check if panes can be grouped into vertical bands (same top = row members).
-    let mut top_groups = group_by_top(panes);
+    /// One line listing every change, e.g. `pm moved from 0 to 1.1; 1
+    /// resized from 30% to 45%`.
+    pub fn summary(&self) -> String {
+        let parts: Vec<String> = self.changes.iter().map(|c| c.to_string()).collect();
+        parts.join("; ")
+    }
+}
+
+/// Compare two `LayoutNode` trees structurally.
+///
+/// Children of matching splits are aligned by the agents they hold, so
+/// inserting a pane reports one addition rather than every later pane
+/// shifting. Panes whose agent disappears from one place and appears in
+/// another are reported as moved. Share changes of up to `tolerance`
+/// percentage points are ignored, as are size changes of a split whose
+/// number of children changed. Pane options are not compared.
+pub fn diff(a: &LayoutNode, b: &LayoutNode, tolerance: u32) -> LayoutDiff {
+    let mut walk = DiffWalk {
+        tolerance,
+        changes: Vec::new(),
+        removed: Vec::new(),
+        added: Vec::new(),
+    };
+    walk.compare(a, &mut Vec::new(), b, &mut Vec::new());
+    let DiffWalk {
+        mut changes,
+        removed,
+        mut added,
+        ..
+    } = walk;
 
-    if top_groups.len() > 1 {
-        // Multiple rows stacked vertically -> Col layout.
-        top_groups.sort_by_key(|g| g[0].top);
-        let total_height = compute_total_height(&top_groups);
-        let children: Vec<LayoutEntry> = top_groups
+    let mut gone = Vec::new();
+    for (agent, from) in removed {
+        let found = added
             .iter()
-            .map(|group| {
-                let node = from_panes(group);
-                let group_height = group_height(group);
-                let percent = if total_height > 0 {
-                    ((group_height as u64 * 100) / total_height as u64) as u32
-                } else {
-                    0
-                };
-                LayoutEntry {
-                    node,
-                    percent: Some(percent),
+            .position(|(name, _)| !agent.is_empty() && *name == agent);
+        match found {
+            Some(i) => {
+                let (_, to) = added.remove(i);
+                if from != to {
+                    changes.push(LayoutChange::AgentMoved { agent, from, to });
                 }
-            })
-            .collect();
-        return LayoutNode::Col { children };
+            }
+            None => gone.push(LayoutChange::PaneRemoved { agent, path: from }),
+        }
     }
+    changes.extend(gone);
+    changes.extend(
+        added
+            .into_iter()
+            .map(|(agent, path)| LayoutChange::PaneAdded { agent, path }),
+    );
+    LayoutDiff { changes }
+}
 
-    // All panes share the same top -> they are side by side horizontally.
-    let mut sorted = panes.to_vec();
-    sorted.sort_by_key(|p| p.left);
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
 
-    // Check if we can split by left coordinate into separate columns.
-    let left_groups = group_by_left(&sorted);
+/// State of one `diff`: structural changes as they are found, plus panes
+/// seen on only one side, which are paired into moves at the end.
+struct DiffWalk {
+    tolerance: u32,
+    changes: Vec<LayoutChange>,
+    removed: Vec<(String, Vec<usize>)>,
+    added: Vec<(String, Vec<usize>)>,
+}
 
-    if left_groups.len() > 1 {
-        let total_width = compute_total_width(&sorted);
-        let children: Vec<LayoutEntry> = left_groups
-            .iter()
-            .map(|group| {
-                let node = from_panes(group);
-                let group_width = group_width(group);
-                let percent = if total_width > 0 {
-                    ((group_width as u64 * 100) / total_width as u64) as u32
-                } else {
-                    0
-                };
-                LayoutEntry {
-                    node,
-                    percent: Some(percent),
+impl DiffWalk {
+    fn compare(
+        &mut self,
+        a: &LayoutNode,
+        a_path: &mut Vec<usize>,
+        b: &LayoutNode,
+        b_path: &mut Vec<usize>,
+    ) {
+        match (split(a), split(b)) {
+            (Some((a_row, a_children)), Some((b_row, b_children))) => {
+                if a_row != b_row {
+                    self.changes.push(LayoutChange::DirectionFlipped {
+                        path: b_path.clone(),
+                        row: b_row,
+                    });
                 }
-            })
-            .collect();
-        return LayoutNode::Row { children };
+                self.compare_children((a_children, false), a_path, (b_children, false), b_path);
+            }
+            // A pane that was split, or a split that shrank to one pane:
+            // the pane stands in for one of the children.
+            (None, Some((_, b_children))) => {
+                let lone = [entry(a)];
+                self.compare_children((&lone, true), a_path, (b_children, false), b_path);
+            }
+            (Some((_, a_children)), None) => {
+                let lone = [entry(b)];
+                self.compare_children((a_children, false), a_path, (&lone, true), b_path);
+            }
+            (None, None) => match (a, b) {
+                (LayoutNode::Pane { agent: x, .. }, LayoutNode::Pane { agent: y, .. })
+                    if x == y => {}
+                _ => {
+                    leaves(a, a_path, &mut self.removed);
+                    leaves(b, b_path, &mut self.added);
+                }
+            },
+        }
     }
 
-    // Fallback: treat each pane as a leaf in a row.
-    let total_width = compute_total_width(&sorted);
-    let children: Vec<LayoutEntry> = sorted
-        .iter()
-        .map(|p| {
-            let percent = if total_width > 0 {
-                ((p.width as u64 * 100) / total_width as u64) as u32
-            } else {
-                0
-            };
-            LayoutEntry {
-                node: LayoutNode::Pane {
-                    agent: p.agent.clone().unwrap_or_default(),
-                },
-                percent: Some(percent),
+    /// Compare two lists of children. A `lone` list holds a single pane
+    /// standing in for a split, so its index is not part of the path.
+    fn compare_children(
+        &mut self,
+        (a, a_lone): (&[LayoutEntry], bool),
+        a_path: &mut Vec<usize>,
+        (b, b_lone): (&[LayoutEntry], bool),
+        b_path: &mut Vec<usize>,
+    ) {
+        let a_shares = shares(a);
+        let b_shares = shares(b);
+        let sizes = !a_lone && !b_lone && a.len() == b.len();
+        for pair in align(a, b) {
+            if let Some(i) = pair.0.filter(|_| !a_lone) {
+                a_path.push(i);
             }
-        })
-        .collect();
-    LayoutNode::Row { children }
+            if let Some(j) = pair.1.filter(|_| !b_lone) {
+                b_path.push(j);
+            }
+            match pair {
+                (Some(i), Some(j)) => {
+                    let (from, to) = (a_shares[i], b_shares[j]);
+                    if sizes && (from - to).abs() > self.tolerance as f64 {
+                        self.changes.push(LayoutChange::Resized {
+                            path: b_path.clone(),
+                            from: from.round() as u32,
+                            to: to.round() as u32,
+                        });
+                    }
+                    self.compare(&a[i].node, a_path, &b[j].node, b_path);
+                }
+                (Some(i), None) => leaves(&a[i].node, a_path, &mut self.removed),
+                (None, Some(j)) => leaves(&b[j].node, b_path, &mut self.added),
+                (None, None) => {}
+            }
+            if pair.0.is_some() && !a_lone {
+                a_path.pop();
+            }
+            if pair.1.is_some() && !b_lone {
+                b_path.pop();
+            }
+        }
+    }
 }
 
-/// Compare two `LayoutNode` trees structurally. Returns `true` if they differ.
-pub fn diff(a: &LayoutNode, b: &LayoutNode) -> bool {
-    a != b
+/// `node` as a child with no size of its own.
+fn entry(node: &LayoutNode) -> LayoutEntry {
+    LayoutEntry {
+        node: node.clone(),
+        percent: None,
+        size: SizeSpec::default(),
+    }
 }
 
-// ---------------------------------------------------------------------------
-// Helpers
-// ---------------------------------------------------------------------------
+/// Every pane under `node` with its path, in reading order.
+fn leaves(node: &LayoutNode, path: &mut Vec<usize>, out: &mut Vec<(String, Vec<usize>)>) {
+    match split(node) {
+        None => {
+            if let LayoutNode::Pane { agent, .. } = node {
+                out.push((agent.clone(), path.clone()));
+            }
+        }
+        Some((_, children)) => {
+            for (i, child) in children.iter().enumerate() {
+                path.push(i);
+                leaves(&child.node, path, out);
+                path.pop();
+            }
+        }
+    }
+}
+
+/// Each child's share of its split, in percent.
+fn shares(children: &[LayoutEntry]) -> Vec<f64> {
+    let weights = child_weights(children);
+    let total: f64 = weights.iter().sum();
+    weights
+        .iter()
+        .map(|w| if total > 0.0 { w * 100.0 / total } else { 0.0 })
+        .collect()
+}
+
+/// Pair up the children of two splits.
+///
+/// Children holding the same agents in the same order are matched by a
+/// longest common subsequence. Between two matches, runs of unmatched
+/// children of equal length are paired in order, since those are most
+/// likely the same panes with something changed inside; the rest stand
+/// alone as `(Some, None)` or `(None, Some)`.
+fn align(a: &[LayoutEntry], b: &[LayoutEntry]) -> Vec<(Option<usize>, Option<usize>)> {
+    let key = |entry: &LayoutEntry| {
+        let mut out = Vec::new();
+        leaves(&entry.node, &mut Vec::new(), &mut out);
+        out.into_iter().map(|(agent, _)| agent).collect::<Vec<_>>()
+    };
+    let a_keys: Vec<_> = a.iter().map(key).collect();
+    let b_keys: Vec<_> = b.iter().map(key).collect();
 
-/// Group panes by their `top` coordinate.
-fn group_by_top(panes: &[TmuxPane]) -> Vec<Vec<TmuxPane>> {
-    let mut map: Vec<(u32, Vec<TmuxPane>)> = Vec::new();
-    for p in panes {
-        if let Some(entry) = map.iter_mut().find(|(t, _)| *t == p.top) {
-            entry.1.push(p.clone());
+    // lcs[i][j]: length of the longest common subsequence of a[i..], b[j..].
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a_keys[i] == b_keys[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut matches = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a_keys[i] == b_keys[j] {
+            matches.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            i += 1;
         } else {
-            map.push((p.top, vec![p.clone()]));
+            j += 1;
         }
     }
-    map.into_iter().map(|(_, v)| v).collect()
-}
+    matches.push((a.len(), b.len()));
 
-/// Group panes by their `left` coordinate.
-fn group_by_left(panes: &[TmuxPane]) -> Vec<Vec<TmuxPane>> {
-    let mut map: Vec<(u32, Vec<TmuxPane>)> = Vec::new();
-    for p in panes {
-        if let Some(entry) = map.iter_mut().find(|(l, _)| *l == p.left) {
-            entry.1.push(p.clone());
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    for (mi, mj) in matches {
+        if mi - i == mj - j {
+            pairs.extend((i..mi).zip(j..mj).map(|(x, y)| (Some(x), Some(y))));
         } else {
-            map.push((p.left, vec![p.clone()]));
+            pairs.extend((i..mi).map(|x| (Some(x), None)));
+            pairs.extend((j..mj).map(|y| (None, Some(y))));
         }
+        if mi < a.len() {
+            pairs.push((Some(mi), Some(mj)));
+        }
+        i = mi + 1;
+        j = mj + 1;
     }
-    map.sort_by_key(|(l, _)| *l);
-    map.into_iter().map(|(_, v)| v).collect()
+    pairs
 }
 
-/// Compute the total width from a sorted list of panes.
-fn compute_total_width(panes: &[TmuxPane]) -> u32 {
-    if panes.is_empty() {
-        return 0;
+fn pane_name(agent: &str) -> String {
+    if agent.is_empty() {
+        "unnamed pane".to_string()
+    } else {
+        format!("pane {}", agent)
     }
-    // Total = rightmost pane's (left + width) - leftmost pane's left.
-    let min_left = panes.iter().map(|p| p.left).min().unwrap_or(0);
-    let max_right = panes.iter().map(|p| p.left + p.width).max().unwrap_or(0);
-    max_right - min_left
 }
 
-/// Compute the total height from grouped rows.
-fn compute_total_height(groups: &[Vec<TmuxPane>]) -> u32 {
-    if groups.is_empty() {
-        return 0;
+/// Panes between two neighbouring cut lines, and the cells they span.
+struct Band<'a> {
+    panes: Vec<&'a TmuxPane>,
+    start: u32,
+    end: u32,
+}
+
+/// Steps 1–5 of `from_panes` for one area of the window, naming each leaf
+/// with `leaf`.
+fn cut(panes: &[&TmuxPane], leaf: &dyn Fn(&TmuxPane) -> String) -> Result<LayoutNode, String> {
+    if let [pane] = panes {
+        return Ok(LayoutNode::pane(leaf(pane)));
     }
-    let min_top = groups
-        .iter()
-        .map(|g| g.iter().map(|p| p.top).min().unwrap_or(0))
-        .min()
-        .unwrap_or(0);
-    let max_bottom = groups
+    let rows = bands(panes, &vertical);
+    let columns = bands(panes, &horizontal);
+    match (rows.len() > 1, columns.len() > 1) {
+        (true, false) => join(&rows, false, leaf),
+        (false, true) => join(&columns, true, leaf),
+        // Both ways work only when some cut lines line up across the whole
+        // area; the tree with fewer splits is the likelier one.
+        (true, true) => {
+            let by_rows = join(&rows, false, leaf)?;
+            let by_columns = join(&columns, true, leaf)?;
+            Ok(if splits(&by_columns) < splits(&by_rows) {
+                by_columns
+            } else {
+                by_rows
+            })
+        }
+        (false, false) => Err(format!(
+            "panes {} overlap or cannot be split into rows and columns",
+            ids(panes)
+        )),
+    }
+}
+
+/// A `Row` (`row`) or `Col` of `bands`, each of which must run the whole
+/// length of the split.
+fn join(bands: &[Band], row: bool, leaf: &dyn Fn(&TmuxPane) -> String) -> Result<LayoutNode, String> {
+    let cross: &dyn Fn(&TmuxPane) -> (u32, u32) = if row { &vertical } else { &horizontal };
+    let extent = |panes: &[&TmuxPane]| {
+        let start = panes.iter().map(|p| cross(p).0).min().unwrap_or(0);
+        let end = panes.iter().map(|p| cross(p).1).max().unwrap_or(0);
+        (start, end)
+    };
+    let all: Vec<&TmuxPane> = bands.iter().flat_map(|b| b.panes.iter().copied()).collect();
+    let whole = extent(&all);
+    if let Some(short) = bands.iter().find(|b| extent(&b.panes) != whole) {
+        return Err(format!(
+            "panes {} do not reach across their {}",
+            ids(&short.panes),
+            if row { "column" } else { "row" }
+        ));
+    }
+
+    let total: u32 = bands.iter().map(|b| b.end - b.start).sum();
+    let children = bands
         .iter()
-        .map(|g| g.iter().map(|p| p.top + p.height).max().unwrap_or(0))
-        .max()
-        .unwrap_or(0);
-    max_bottom - min_top
+        .map(|band| {
+            let size = u64::from(band.end - band.start);
+            Ok(LayoutEntry {
+                node: cut(&band.panes, leaf)?,
+                percent: Some(((size * 100 + u64::from(total) / 2) / u64::from(total)) as u32),
+                size: SizeSpec::default(),
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(if row {
+        LayoutNode::Row { children }
+    } else {
+        LayoutNode::Col { children }
+    })
+}
+
+fn horizontal(pane: &TmuxPane) -> (u32, u32) {
+    (pane.left, pane.left + pane.width)
 }
 
-/// Height of a group of panes (they share the same top).
-fn group_height(group: &[TmuxPane]) -> u32 {
-    group.iter().map(|p| p.height).max().unwrap_or(0)
+fn vertical(pane: &TmuxPane) -> (u32, u32) {
+    (pane.top, pane.top + pane.height)
 }
 
-/// Width of a group of panes (they share the same left).
-fn group_width(group: &[TmuxPane]) -> u32 {
-    group.iter().map(|p| p.width).max().unwrap_or(0)
+/// Number of ROW and COL nodes in `node`.
+fn splits(node: &LayoutNode) -> usize {
+    match split(node) {
+        Some((_, children)) => 1 + children.iter().map(|c| splits(&c.node)).sum::<usize>(),
+        None => 0,
+    }
+}
+
+/// Group `panes` into bands along the axis `span` measures, splitting at
+/// every line that no pane crosses. Bands come out in order.
+fn bands<'a>(panes: &[&'a TmuxPane], span: &dyn Fn(&TmuxPane) -> (u32, u32)) -> Vec<Band<'a>> {
+    let mut sorted = panes.to_vec();
+    sorted.sort_by_key(|p| span(p));
+    let mut bands: Vec<Band<'a>> = Vec::new();
+    for pane in sorted {
+        let (start, end) = span(pane);
+        match bands.last_mut() {
+            Some(band) if start < band.end => {
+                band.end = band.end.max(end);
+                band.panes.push(pane);
+            }
+            _ => bands.push(Band {
+                panes: vec![pane],
+                start,
+                end,
+            }),
+        }
+    }
+    bands
+}
+
+/// Pane ids for an error message: `%0, %1`.
+fn ids(panes: &[&TmuxPane]) -> String {
+    let ids: Vec<&str> = panes.iter().map(|p| p.id.as_str()).collect();
+    ids.join(", ")
 }
 
 // ---------------------------------------------------------------------------
@@ -187,6 +500,9 @@ fn group_width(group: &[TmuxPane]) -> u32 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::data::layout_expr::serialize_layout_expr;
+    use crate::infrastructure::tmux::parse_list_panes;
+    use crate::layout::tmux_layout::{parse_tmux_layout, to_layout_node};
 
     fn pane(id: &str, w: u32, h: u32, top: u32, left: u32, agent: Option<&str>) -> TmuxPane {
         TmuxPane {
@@ -197,18 +513,19 @@ mod tests {
             top,
             left,
             agent: agent.map(|a| a.into()),
+            active: false,
+            title: String::new(),
+            command: String::new(),
         }
     }
 
     #[test]
     fn single_pane_becomes_leaf() {
         let panes = vec![pane("%0", 120, 40, 0, 0, Some("pilot"))];
-        let layout = from_panes(&panes);
+        let layout = from_panes(&panes).unwrap();
         assert_eq!(
             layout,
-            LayoutNode::Pane {
-                agent: "pilot".into()
-            }
+            LayoutNode::pane("pilot")
         );
     }
 
@@ -218,7 +535,7 @@ mod tests {
             pane("%0", 60, 40, 0, 0, Some("left")),
             pane("%1", 60, 40, 0, 60, Some("right")),
         ];
-        let layout = from_panes(&panes);
+        let layout = from_panes(&panes).unwrap();
         match &layout {
             LayoutNode::Row { children } => {
                 assert_eq!(children.len(), 2);
@@ -233,7 +550,7 @@ mod tests {
             pane("%0", 120, 20, 0, 0, Some("top")),
             pane("%1", 120, 20, 20, 0, Some("bottom")),
         ];
-        let layout = from_panes(&panes);
+        let layout = from_panes(&panes).unwrap();
         match &layout {
             LayoutNode::Col { children } => {
                 assert_eq!(children.len(), 2);
@@ -244,22 +561,114 @@ mod tests {
 
     #[test]
     fn empty_panes_returns_empty_leaf() {
-        let layout = from_panes(&[]);
+        let layout = from_panes(&[]).unwrap();
         assert_eq!(
             layout,
-            LayoutNode::Pane {
-                agent: String::new()
+            LayoutNode::pane("")
+        );
+    }
+
+    /// Each file under `fixtures/list-panes` is real `list-panes` output
+    /// from a 160x48 window, preceded by a `# description`, the `expect:`ed
+    /// layout expression or an `error:` message, and, when it agrees with
+    /// the panes, tmux's own `layout:` string, which must give the same
+    /// tree.
+    #[test]
+    fn fixture_corpus() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/list-panes");
+        let mut count = 0;
+        for file in std::fs::read_dir(&dir).unwrap() {
+            let path = file.unwrap().path();
+            let text = std::fs::read_to_string(&path).unwrap();
+            let field = |key: &str| {
+                text.lines()
+                    .find_map(|l| l.strip_prefix(key))
+                    .map(str::trim)
+            };
+            let lines: Vec<&str> = text.lines().filter(|l| l.starts_with('%')).collect();
+            let panes = parse_list_panes(&lines.join("\n"));
+            let name = path.display();
+            count += 1;
+
+            if let Some(error) = field("error:") {
+                let message = from_panes(&panes).unwrap_err();
+                assert!(message.contains(error), "{}: {}", name, message);
+                continue;
+            }
+            let layout = from_panes(&panes).unwrap_or_else(|e| panic!("{}: {}", name, e));
+            let expr = serialize_layout_expr(&layout);
+            assert_eq!(Some(expr.as_str()), field("expect:"), "{}", name);
+            if let Some(window_layout) = field("layout:") {
+                let cell = parse_tmux_layout(window_layout).unwrap();
+                let agent = |number: u32| {
+                    let id = format!("%{}", number);
+                    let pane = panes.iter().find(|p| p.id == id);
+                    pane.and_then(|p| p.agent.clone()).unwrap_or_default()
+                };
+                assert_eq!(layout, to_layout_node(&cell, &agent), "{}", name);
             }
+        }
+        assert!(count >= 10, "only {} fixtures in {}", count, dir.display());
+    }
+
+    #[test]
+    fn l_shape_with_differing_tops() {
+        // A full-height pane beside two stacked ones: tops 0, 0, and 25.
+        let panes = vec![
+            pane("%0", 80, 48, 0, 0, Some("editor")),
+            pane("%1", 79, 24, 0, 81, Some("logs")),
+            pane("%2", 79, 23, 25, 81, Some("shell")),
+        ];
+        let layout = from_panes(&panes).unwrap();
+        assert_eq!(
+            serialize_layout_expr(&layout),
+            "ROW(editor 50%, COL(logs 51%, shell 49%) 50%)"
         );
     }
 
+    #[test]
+    fn borders_are_not_counted() {
+        // 39 + 1 border + 40 cells: the panes are 49% and 51%, not 48%.
+        let panes = vec![
+            pane("%0", 39, 24, 0, 0, Some("a")),
+            pane("%1", 40, 24, 0, 40, Some("b")),
+        ];
+        let layout = from_panes(&panes).unwrap();
+        assert_eq!(serialize_layout_expr(&layout), "ROW(a 49%, b 51%)");
+    }
+
+    #[test]
+    fn pinwheel_cannot_be_represented() {
+        // Four panes around a centre pane, no line crosses the whole window.
+        let panes = vec![
+            pane("%0", 20, 10, 0, 0, None),
+            pane("%1", 10, 20, 0, 21, None),
+            pane("%2", 20, 10, 21, 11, None),
+            pane("%3", 10, 20, 11, 0, None),
+            pane("%4", 9, 9, 11, 11, None),
+        ];
+        let err = from_panes(&panes).unwrap_err();
+        assert!(err.contains("cannot be split into rows and columns"));
+    }
+
+    #[test]
+    fn band_must_span_its_split() {
+        // Cut into two columns, but the right one stops halfway down.
+        let panes = vec![
+            pane("%0", 40, 24, 0, 0, None),
+            pane("%1", 39, 12, 0, 41, None),
+        ];
+        let err = from_panes(&panes).unwrap_err();
+        assert!(err.contains("%1 do not reach across their column"));
+    }
+
     #[test]
     fn percentages_computed() {
         let panes = vec![
             pane("%0", 30, 40, 0, 0, Some("left")),
             pane("%1", 90, 40, 0, 30, Some("right")),
         ];
-        let layout = from_panes(&panes);
+        let layout = from_panes(&panes).unwrap();
         match &layout {
             LayoutNode::Row { children } => {
                 assert_eq!(children.len(), 2);
@@ -272,56 +681,144 @@ mod tests {
 
     #[test]
     fn diff_same_layout_no_difference() {
-        let a = LayoutNode::Pane {
-            agent: "pilot".into(),
-        };
-        let b = LayoutNode::Pane {
-            agent: "pilot".into(),
-        };
-        assert!(!diff(&a, &b));
+        let a = LayoutNode::pane("pilot");
+        let b = LayoutNode::pane("pilot");
+        assert!(diff(&a, &b, 0).is_empty());
     }
 
     #[test]
     fn diff_different_layouts() {
-        let a = LayoutNode::Pane {
-            agent: "pilot".into(),
-        };
-        let b = LayoutNode::Pane {
-            agent: "worker".into(),
-        };
-        assert!(diff(&a, &b));
+        let a = LayoutNode::pane("pilot");
+        let b = LayoutNode::pane("worker");
+        assert_eq!(diff(&a, &b, 0).changes.len(), 2);
     }
 
     #[test]
     fn diff_row_vs_col() {
         let a = LayoutNode::Row {
             children: vec![LayoutEntry {
-                node: LayoutNode::Pane {
-                    agent: "x".into(),
-                },
+                node: LayoutNode::pane("x"),
                 percent: Some(100),
+                size: SizeSpec::default(),
             }],
         };
         let b = LayoutNode::Col {
             children: vec![LayoutEntry {
-                node: LayoutNode::Pane {
-                    agent: "x".into(),
-                },
+                node: LayoutNode::pane("x"),
                 percent: Some(100),
+                size: SizeSpec::default(),
             }],
         };
-        assert!(diff(&a, &b));
+        assert_eq!(
+            diff(&a, &b, 0).changes,
+            vec![LayoutChange::DirectionFlipped {
+                path: vec![],
+                row: false,
+            }]
+        );
+    }
+
+    fn layout(expr: &str) -> LayoutNode {
+        crate::data::layout_expr::parse_layout_expr(expr).unwrap()
+    }
+
+    fn changes(a: &str, b: &str, tolerance: u32) -> Vec<String> {
+        diff(&layout(a), &layout(b), tolerance)
+            .changes
+            .iter()
+            .map(|c| c.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn diff_ignores_jitter_within_tolerance() {
+        let a = "ROW(pm 33%, worker 67%)";
+        assert!(changes(a, "ROW(pm 34%, worker 66%)", 2).is_empty());
+        assert_eq!(
+            changes(a, "ROW(pm 40%, worker 60%)", 2),
+            vec!["0 resized from 33% to 40%", "1 resized from 67% to 60%"]
+        );
+        assert_eq!(changes(a, "ROW(pm 34%, worker 66%)", 0).len(), 2);
+    }
+
+    #[test]
+    fn diff_reports_one_insertion() {
+        assert_eq!(
+            changes("ROW(a, b, c)", "ROW(a, x, b, c)", 2),
+            vec!["pane x added at 1"]
+        );
+        assert_eq!(
+            changes("COL(a, ROW(b, c))", "COL(a, ROW(b))", 2),
+            vec!["pane c removed from 1.1"]
+        );
+    }
+
+    #[test]
+    fn diff_reports_moved_agents() {
+        assert_eq!(
+            changes("ROW(a, b, c)", "ROW(b, c, a)", 2),
+            vec!["a moved from 0 to 2"]
+        );
+        assert_eq!(
+            changes("ROW(a, b)", "ROW(a, COL(b, c))", 2),
+            vec!["pane c added at 1.1"]
+        );
+    }
+
+    #[test]
+    fn diff_looks_inside_changed_children() {
+        assert_eq!(
+            changes("ROW(a 30%, COL(b, c) 70%)", "ROW(a 50%, ROW(b, c) 50%)", 2),
+            vec![
+                "0 resized from 30% to 50%",
+                "1 resized from 70% to 50%",
+                "split at 1 is now a ROW",
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_never_pairs_unnamed_panes() {
+        let a = LayoutNode::pane("");
+        let b = LayoutNode::pane("");
+        assert!(diff(&a, &b, 0).is_empty());
+        let two = from_panes(&[
+            pane("%0", 60, 40, 0, 0, None),
+            pane("%1", 60, 40, 0, 60, None),
+        ])
+        .unwrap();
+        let three = from_panes(&[
+            pane("%0", 40, 40, 0, 0, None),
+            pane("%1", 40, 40, 0, 40, None),
+            pane("%2", 40, 40, 0, 80, None),
+        ])
+        .unwrap();
+        assert_eq!(diff(&two, &three, 2).summary(), "unnamed pane added at 2");
+        assert_eq!(
+            diff(&layout("ROW(a, b)"), &layout("ROW(b, c)"), 2).summary(),
+            "pane a removed from 0; pane c added at 1"
+        );
+    }
+
+    #[test]
+    fn diff_serializes_tagged_changes() {
+        let change = LayoutChange::AgentMoved {
+            agent: "pm".into(),
+            from: vec![0],
+            to: vec![1, 0],
+        };
+        let json = serde_json::to_value(&change).unwrap();
+        assert_eq!(json["change"], "agent_moved");
+        assert_eq!(json["to"], serde_json::json!([1, 0]));
     }
 
     #[test]
     fn agent_none_uses_empty_string() {
         let panes = vec![pane("%0", 120, 40, 0, 0, None)];
-        let layout = from_panes(&panes);
+        let layout = from_panes(&panes).unwrap();
         assert_eq!(
             layout,
-            LayoutNode::Pane {
-                agent: String::new()
-            }
+            LayoutNode::pane("")
         );
     }
 
@@ -333,7 +830,7 @@ mod tests {
             pane("%1", 60, 20, 0, 60, Some("tr")),
             pane("%2", 120, 20, 20, 0, Some("bottom")),
         ];
-        let layout = from_panes(&panes);
+        let layout = from_panes(&panes).unwrap();
         match &layout {
             LayoutNode::Col { children } => {
                 assert_eq!(children.len(), 2);
@@ -346,7 +843,7 @@ mod tests {
                 }
                 // Bottom should be a single pane.
                 match &children[1].node {
-                    LayoutNode::Pane { agent } => {
+                    LayoutNode::Pane { agent, .. } => {
                         assert_eq!(agent, "bottom");
                     }
                     other => panic!("expected Pane for bottom, got {:?}", other),
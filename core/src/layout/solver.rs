@@ -0,0 +1,269 @@
+//! Size solver — turn percentages, cell sizes, and bounds into split sizes.
+//!
+//! A layout's children may mix shares of the parent (`30%`), exact sizes in
+//! cells (`40c`), and bounds (`>=10r`, `<=80c`). For a concrete window the
+//! solver gives exact sizes first, then percentages of the space, and lets
+//! children without a size share the rest. Whatever is left over or missing
+//! is absorbed by those flexible children — or by the percentage children
+//! when there are none — and any child pushed past a bound is pinned to it
+//! while the others absorb the difference. Contradictory constraints are
+//! relaxed proportionally, so the sizes always fill the space exactly.
+
+use crate::infrastructure::tmux::child_weights;
+use crate::types::session::{LayoutEntry, LayoutNode, SizeSpec};
+
+
+/// Window size assumed when a layout is sized without a live window, such
+/// as for previews.
+pub const NOMINAL_WIDTH: u32 = 200;
+pub const NOMINAL_HEIGHT: u32 = 50;
+
+
+/// Whether any entry of `node` has a cell size or a bound.
+pub fn has_constraints(node: &LayoutNode) -> bool {
+    match node {
+        LayoutNode::Pane { .. } => false,
+        LayoutNode::Row { children } | LayoutNode::Col { children } => children
+            .iter()
+            .any(|e| !e.size.is_empty() || has_constraints(&e.node)),
+    }
+}
+
+
+/// Divide `available` cells (borders excluded) among `children`.
+///
+/// Every child gets at least one cell when there is room for it, and the
+/// sizes always add up to `available`.
+pub fn solve(children: &[LayoutEntry], available: u32) -> Vec<u32> {
+    if children.is_empty() {
+        return Vec::new();
+    }
+    if children.iter().all(|e| e.size.is_empty()) {
+        return split_by_weight(&child_weights(children), available);
+    }
+
+    let space = f64::from(available);
+    let mut sizes: Vec<f64> = children
+        .iter()
+        .map(|e| match (e.size.cells, e.percent) {
+            (Some(cells), _) => f64::from(cells),
+            (None, Some(p)) => space * f64::from(p) / 100.0,
+            (None, None) => 0.0,
+        })
+        .collect();
+    let flexible: Vec<usize> = (0..children.len())
+        .filter(|&i| children[i].size.cells.is_none() && children[i].percent.is_none())
+        .collect();
+    let percent: Vec<usize> = (0..children.len())
+        .filter(|&i| children[i].size.cells.is_none() && children[i].percent.is_some())
+        .collect();
+    let elastic = if !flexible.is_empty() { flexible } else { percent };
+
+    let mut pinned = vec![false; children.len()];
+    for _ in 0..=children.len() {
+        let mut free: Vec<usize> = elastic.iter().copied().filter(|&i| !pinned[i]).collect();
+        if free.is_empty() {
+            free = (0..children.len()).filter(|&i| !pinned[i]).collect();
+        }
+        if free.is_empty() {
+            break;
+        }
+        let gap = space - sizes.iter().sum::<f64>();
+        let free_total: f64 = free.iter().map(|&i| sizes[i]).sum();
+        for &i in &free {
+            sizes[i] += if free_total > 0.0 {
+                gap * sizes[i] / free_total
+            } else {
+                gap / free.len() as f64
+            };
+        }
+
+        let mut clamped = false;
+        for (i, entry) in children.iter().enumerate() {
+            let (low, high) = bounds(&entry.size);
+            if pinned[i] {
+                continue;
+            }
+            if sizes[i] < low - 1e-9 || sizes[i] > high + 1e-9 {
+                sizes[i] = sizes[i].clamp(low, high);
+                pinned[i] = true;
+                clamped = true;
+            }
+        }
+        if !clamped {
+            break;
+        }
+    }
+
+    let total: f64 = sizes.iter().sum();
+    if total > 0.0 && (total - space).abs() > 0.5 {
+        for size in &mut sizes {
+            *size *= space / total;
+        }
+    }
+    round_to(&sizes, available)
+}
+
+
+/// Replace every entry's sizes with exact cell counts for a `width` x
+/// `height` window, so the result can be built and reconciled as is.
+pub fn resolve(node: &LayoutNode, width: u32, height: u32) -> LayoutNode {
+    let (children, horizontal) = match node {
+        LayoutNode::Pane { .. } => return node.clone(),
+        LayoutNode::Row { children } => (children, true),
+        LayoutNode::Col { children } => (children, false),
+    };
+    let span = if horizontal { width } else { height };
+    let available = span.saturating_sub(children.len().saturating_sub(1) as u32);
+    let entries = children
+        .iter()
+        .zip(solve(children, available))
+        .map(|(entry, cells)| LayoutEntry {
+            node: if horizontal {
+                resolve(&entry.node, cells, height)
+            } else {
+                resolve(&entry.node, width, cells)
+            },
+            percent: None,
+            size: SizeSpec {
+                cells: Some(cells),
+                ..SizeSpec::default()
+            },
+        })
+        .collect();
+    if horizontal {
+        LayoutNode::Row { children: entries }
+    } else {
+        LayoutNode::Col { children: entries }
+    }
+}
+
+
+/// A child's lower and upper bound in cells; never below one cell.
+fn bounds(size: &SizeSpec) -> (f64, f64) {
+    let low = f64::from(size.min.unwrap_or(1).max(1));
+    let high = size.max.map_or(f64::INFINITY, |m| f64::from(m).max(low));
+    (low, high)
+}
+
+
+/// Proportional split, rounding as it goes; the last child absorbs the
+/// rounding and each child still to come keeps at least one cell.
+fn split_by_weight(weights: &[f64], available: u32) -> Vec<u32> {
+    let total: f64 = weights.iter().sum();
+    let mut sizes = Vec::new();
+    let mut used = 0;
+    for (i, weight) in weights.iter().enumerate() {
+        let remaining_children = (weights.len() - i - 1) as u32;
+        let size = if remaining_children == 0 {
+            available.saturating_sub(used)
+        } else {
+            let ideal = (f64::from(available) * weight / total).round() as u32;
+            ideal.clamp(1, available.saturating_sub(used + remaining_children).max(1))
+        };
+        sizes.push(size);
+        used += size;
+    }
+    sizes
+}
+
+
+/// Round `sizes` to whole cells adding up to `total`, handing spare cells
+/// to the largest fractions first.
+pub(crate) fn round_to(sizes: &[f64], total: u32) -> Vec<u32> {
+    let mut out: Vec<u32> = sizes.iter().map(|s| s.max(1.0).floor() as u32).collect();
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by(|&a, &b| {
+        let frac = |i: usize| sizes[i] - sizes[i].floor();
+        frac(b).total_cmp(&frac(a))
+    });
+    let mut used: u32 = out.iter().sum();
+    for &i in order.iter().cycle() {
+        if used >= total {
+            break;
+        }
+        out[i] += 1;
+        used += 1;
+    }
+    while used > total {
+        let Some(largest) = (0..out.len()).filter(|&i| out[i] > 1).max_by_key(|&i| out[i]) else {
+            break;
+        };
+        out[largest] -= 1;
+        used -= 1;
+    }
+    out
+}
+
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::layout_expr::parse_layout_expr;
+
+    fn sizes(expr: &str, available: u32) -> Vec<u32> {
+        match parse_layout_expr(expr).unwrap() {
+            LayoutNode::Row { children } | LayoutNode::Col { children } => {
+                solve(&children, available)
+            }
+            LayoutNode::Pane { .. } => panic!("not a split"),
+        }
+    }
+
+    #[test]
+    fn percentages_alone_split_proportionally() {
+        assert_eq!(sizes("ROW(a 25%, b 75%)", 100), vec![25, 75]);
+        assert_eq!(sizes("ROW(a, b, c)", 99), vec![33, 33, 33]);
+    }
+
+    #[test]
+    fn exact_cells_keep_their_size() {
+        assert_eq!(sizes("ROW(sidebar 40c, main)", 199), vec![40, 159]);
+        assert_eq!(sizes("ROW(sidebar 40c, main)", 99), vec![40, 59]);
+    }
+
+    #[test]
+    fn flexible_children_share_the_rest() {
+        assert_eq!(sizes("ROW(a 20c, b *, c 50%, d)", 200), vec![20, 40, 100, 40]);
+    }
+
+    #[test]
+    fn percentages_absorb_when_nothing_is_flexible() {
+        assert_eq!(sizes("ROW(a 40c, b 50%, c 50%)", 200), vec![40, 80, 80]);
+    }
+
+    #[test]
+    fn minimum_is_honoured() {
+        assert_eq!(sizes("COL(main, logs 10% >=10r)", 49), vec![39, 10]);
+        assert_eq!(sizes("COL(main, logs 10% >=10r)", 199), vec![179, 20]);
+    }
+
+    #[test]
+    fn maximum_is_honoured() {
+        assert_eq!(sizes("ROW(chat <=80c, main 40c)", 300), vec![80, 220]);
+    }
+
+    #[test]
+    fn overfull_cells_are_scaled_down() {
+        let split = sizes("ROW(a 80c, b 80c)", 100);
+        assert_eq!(split.iter().sum::<u32>(), 100);
+        assert_eq!(split, vec![50, 50]);
+    }
+
+    #[test]
+    fn resolve_gives_every_entry_cells() {
+        let node = parse_layout_expr("ROW(sidebar 40c, COL(main, logs 10% >=10r))").unwrap();
+        assert!(has_constraints(&node));
+        let resolved = resolve(&node, 200, 50);
+        assert!(!has_constraints(&parse_layout_expr("ROW(a 30%, b)").unwrap()));
+        let LayoutNode::Row { children } = &resolved else { panic!() };
+        assert_eq!(children[0].size.cells, Some(40));
+        assert_eq!(children[1].size.cells, Some(159));
+        let LayoutNode::Col { children: inner } = &children[1].node else { panic!() };
+        assert_eq!((inner[0].size.cells, inner[1].size.cells), (Some(39), Some(10)));
+    }
+}
@@ -48,6 +48,16 @@ impl SnapshotTimer {
         self.last_capture.remove(session);
     }
 
+    /// When `session` was last captured, if ever.
+    pub fn last_capture(&self, session: &str) -> Option<u64> {
+        self.last_capture.get(session).copied()
+    }
+
+    /// Sessions that have been captured at least once.
+    pub fn tracked(&self) -> Vec<String> {
+        self.last_capture.keys().cloned().collect()
+    }
+
     /// Return the configured interval.
     pub fn interval_ms(&self) -> u64 {
         self.interval_ms
@@ -128,6 +138,15 @@ mod tests {
         assert_eq!(due, vec!["s1"]);
     }
 
+    #[test]
+    fn tracked_lists_captured_sessions() {
+        let mut timer = SnapshotTimer::new(5000);
+        timer.record_capture("s1", 1);
+        timer.record_capture("s2", 2);
+        timer.remove_session("s1");
+        assert_eq!(timer.tracked(), vec!["s2"]);
+    }
+
     #[test]
     fn interval_accessor() {
         let timer = SnapshotTimer::new(3000);
@@ -0,0 +1,377 @@
+//! Native tmux layout strings — parse, emit, and convert `#{window_layout}`.
+//!
+//! tmux describes a window as `csum,WxH,X,Y<body>` where the body is either
+//! `,<pane>` for a single pane, `{...}` for side-by-side cells, or `[...]`
+//! for stacked cells, and `csum` is a 16-bit rotating checksum of the rest.
+//! Unlike the geometry heuristics in `snapshot`, these strings are exact:
+//! a captured string fed back to `select-layout` restores the window as it
+//! was, down to the cell.
+
+use crate::layout::solver::solve;
+use crate::types::session::{LayoutEntry, LayoutNode, SizeSpec};
+
+
+/// One cell of a tmux layout: its geometry and what it contains.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutCell {
+    pub width: u32,
+    pub height: u32,
+    pub x: u32,
+    pub y: u32,
+    pub content: CellContent,
+}
+
+
+/// What a layout cell holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CellContent {
+    /// A pane, by its number (the `N` of `%N`).
+    Pane(u32),
+    /// Side-by-side children (`{...}`).
+    Row(Vec<LayoutCell>),
+    /// Stacked children (`[...]`).
+    Col(Vec<LayoutCell>),
+}
+
+
+impl LayoutCell {
+    /// Pane numbers in layout order.
+    pub fn panes(&self) -> Vec<u32> {
+        match &self.content {
+            CellContent::Pane(id) => vec![*id],
+            CellContent::Row(children) | CellContent::Col(children) => {
+                children.iter().flat_map(|c| c.panes()).collect()
+            }
+        }
+    }
+}
+
+
+/// tmux's layout checksum (`layout_checksum` in layout-custom.c).
+pub fn checksum(body: &str) -> u16 {
+    let mut csum: u16 = 0;
+    for byte in body.bytes() {
+        csum = (csum >> 1) | ((csum & 1) << 15);
+        csum = csum.wrapping_add(u16::from(byte));
+    }
+    csum
+}
+
+
+/// Parse a `#{window_layout}` string, verifying its checksum.
+pub fn parse_tmux_layout(input: &str) -> Result<LayoutCell, String> {
+    let input = input.trim();
+    let (csum, body) = input
+        .split_once(',')
+        .ok_or_else(|| format!("missing checksum in layout: {}", input))?;
+    let expected = u16::from_str_radix(csum, 16)
+        .map_err(|_| format!("invalid checksum '{}'", csum))?;
+    let actual = checksum(body);
+    if expected != actual {
+        return Err(format!(
+            "layout checksum mismatch: expected {:04x}, computed {:04x}",
+            expected, actual
+        ));
+    }
+    let mut parser = Parser { bytes: body.as_bytes(), pos: 0 };
+    let cell = parser.cell()?;
+    if parser.pos != body.len() {
+        return Err(format!("trailing characters at {} in layout: {}", parser.pos, body));
+    }
+    Ok(cell)
+}
+
+
+/// Emit a layout string with its checksum, as `select-layout` accepts it.
+pub fn emit_tmux_layout(cell: &LayoutCell) -> String {
+    let mut body = String::new();
+    emit_cell(cell, &mut body);
+    format!("{:04x},{}", checksum(&body), body)
+}
+
+
+fn emit_cell(cell: &LayoutCell, out: &mut String) {
+    out.push_str(&format!("{}x{},{},{}", cell.width, cell.height, cell.x, cell.y));
+    let (children, open, close) = match &cell.content {
+        CellContent::Pane(id) => {
+            out.push_str(&format!(",{}", id));
+            return;
+        }
+        CellContent::Row(children) => (children, '{', '}'),
+        CellContent::Col(children) => (children, '[', ']'),
+    };
+    out.push(open);
+    for (i, child) in children.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        emit_cell(child, out);
+    }
+    out.push(close);
+}
+
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+
+impl Parser<'_> {
+    fn cell(&mut self) -> Result<LayoutCell, String> {
+        let width = self.number()?;
+        self.expect(b'x')?;
+        let height = self.number()?;
+        self.expect(b',')?;
+        let x = self.number()?;
+        self.expect(b',')?;
+        let y = self.number()?;
+        let content = match self.peek() {
+            Some(b',') => {
+                self.pos += 1;
+                CellContent::Pane(self.number()?)
+            }
+            Some(b'{') => CellContent::Row(self.children(b'}')?),
+            Some(b'[') => CellContent::Col(self.children(b']')?),
+            _ => return Err(format!("expected ',', '{{' or '[' at {}", self.pos)),
+        };
+        Ok(LayoutCell {
+            width,
+            height,
+            x,
+            y,
+            content,
+        })
+    }
+
+    fn children(&mut self, close: u8) -> Result<Vec<LayoutCell>, String> {
+        self.pos += 1; // opening bracket
+        let mut children = vec![self.cell()?];
+        loop {
+            match self.peek() {
+                Some(b',') => {
+                    self.pos += 1;
+                    children.push(self.cell()?);
+                }
+                Some(c) if c == close => {
+                    self.pos += 1;
+                    return Ok(children);
+                }
+                _ => return Err(format!("expected ',' or '{}' at {}", close as char, self.pos)),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<u32, String> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(b'0'..=b'9')) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos])
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| format!("expected number at {}", start))
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!("expected '{}' at {}", byte as char, self.pos))
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+}
+
+
+/// Convert a layout cell into a `LayoutNode`, naming each leaf with `agent`
+/// (given the pane number). Percentages are each child's share of its
+/// parent, excluding borders.
+pub fn to_layout_node(cell: &LayoutCell, agent: &dyn Fn(u32) -> String) -> LayoutNode {
+    let (children, horizontal) = match &cell.content {
+        CellContent::Pane(id) => return LayoutNode::pane(agent(*id)),
+        CellContent::Row(children) => (children, true),
+        CellContent::Col(children) => (children, false),
+    };
+    let size = |c: &LayoutCell| if horizontal { c.width } else { c.height };
+    let total: u32 = children.iter().map(size).sum();
+    let entries = children
+        .iter()
+        .map(|child| LayoutEntry {
+            node: to_layout_node(child, agent),
+            percent: Some(if total > 0 {
+                ((u64::from(size(child)) * 100 + u64::from(total) / 2) / u64::from(total)) as u32
+            } else {
+                0
+            }),
+            size: SizeSpec::default(),
+        })
+        .collect();
+    if horizontal {
+        LayoutNode::Row { children: entries }
+    } else {
+        LayoutNode::Col { children: entries }
+    }
+}
+
+
+/// Lay `node` out in a `width` x `height` window.
+///
+/// Children split their parent's space (less one border cell between each)
+/// as `layout::solver` sizes them: by percentage, with unset percentages
+/// sharing what is left, or by cell sizes and bounds. Leaves take pane
+/// numbers from `panes` in order, or their position when `panes` runs out —
+/// `select-layout` assigns panes by position anyway.
+pub fn from_layout_node(node: &LayoutNode, width: u32, height: u32, panes: &[u32]) -> LayoutCell {
+    let mut next = 0;
+    place(node, width, height, 0, 0, panes, &mut next)
+}
+
+
+fn place(
+    node: &LayoutNode,
+    width: u32,
+    height: u32,
+    x: u32,
+    y: u32,
+    panes: &[u32],
+    next: &mut usize,
+) -> LayoutCell {
+    let (children, horizontal) = match node {
+        LayoutNode::Pane { .. } => {
+            let id = panes.get(*next).copied().unwrap_or(*next as u32);
+            *next += 1;
+            return LayoutCell {
+                width,
+                height,
+                x,
+                y,
+                content: CellContent::Pane(id),
+            };
+        }
+        LayoutNode::Row { children } => (children, true),
+        LayoutNode::Col { children } => (children, false),
+    };
+    if children.len() == 1 {
+        return place(&children[0].node, width, height, x, y, panes, next);
+    }
+
+    let span = if horizontal { width } else { height };
+    let available = span.saturating_sub(children.len() as u32 - 1);
+    let sizes = solve(children, available);
+    let mut offset = 0;
+    let mut cells = Vec::new();
+    for (entry, size) in children.iter().zip(sizes) {
+        let cell = if horizontal {
+            place(&entry.node, size, height, x + offset, y, panes, next)
+        } else {
+            place(&entry.node, width, size, x, y + offset, panes, next)
+        };
+        cells.push(cell);
+        offset += size + 1;
+    }
+    LayoutCell {
+        width,
+        height,
+        x,
+        y,
+        content: if horizontal {
+            CellContent::Row(cells)
+        } else {
+            CellContent::Col(cells)
+        },
+    }
+}
+
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::layout_expr::{parse_layout_expr, serialize_layout_expr};
+
+    const TWO_COLUMNS: &str = "200x50,0,0{100x50,0,0,1,99x50,101,0,2}";
+
+    fn with_checksum(body: &str) -> String {
+        format!("{:04x},{}", checksum(body), body)
+    }
+
+    #[test]
+    fn checksum_matches_tmux() {
+        // Captured from tmux 3.4: a fresh 80x24 window.
+        assert_eq!(checksum("80x24,0,0,0"), 0xb25d);
+    }
+
+    #[test]
+    fn parse_single_pane() {
+        let cell = parse_tmux_layout(&with_checksum("80x24,0,0,3")).unwrap();
+        assert_eq!(cell.content, CellContent::Pane(3));
+        assert_eq!((cell.width, cell.height), (80, 24));
+    }
+
+    #[test]
+    fn parse_row() {
+        let cell = parse_tmux_layout(&with_checksum(TWO_COLUMNS)).unwrap();
+        match &cell.content {
+            CellContent::Row(children) => {
+                assert_eq!(children.len(), 2);
+                assert_eq!(children[1].x, 101);
+                assert_eq!(children[1].content, CellContent::Pane(2));
+            }
+            other => panic!("expected row, got {:?}", other),
+        }
+        assert_eq!(cell.panes(), vec![1, 2]);
+    }
+
+    #[test]
+    fn parse_nested() {
+        let body = "160x40,0,0{80x40,0,0,0,79x40,81,0[79x20,81,0,1,79x19,81,21,2]}";
+        let cell = parse_tmux_layout(&with_checksum(body)).unwrap();
+        assert_eq!(cell.panes(), vec![0, 1, 2]);
+        assert_eq!(emit_tmux_layout(&cell), with_checksum(body));
+    }
+
+    #[test]
+    fn bad_checksum_is_rejected() {
+        let err = parse_tmux_layout(&format!("0000,{}", TWO_COLUMNS)).unwrap_err();
+        assert!(err.contains("checksum mismatch"));
+    }
+
+    #[test]
+    fn malformed_layout_is_rejected() {
+        assert!(parse_tmux_layout("nocomma").is_err());
+        assert!(parse_tmux_layout(&with_checksum("80x24,0,0{80x24,0,0,1")).is_err());
+        assert!(parse_tmux_layout(&with_checksum("80x24,0,0,1junk")).is_err());
+    }
+
+    #[test]
+    fn to_layout_node_uses_agents_and_shares() {
+        let cell = parse_tmux_layout(&with_checksum(TWO_COLUMNS)).unwrap();
+        let node = to_layout_node(&cell, &|id| format!("agent{}", id));
+        assert_eq!(serialize_layout_expr(&node), "ROW(agent1 50%, agent2 50%)");
+    }
+
+    #[test]
+    fn from_layout_node_fills_the_window() {
+        let node = parse_layout_expr("ROW(a 30%, COL(b, c) 70%)").unwrap();
+        let cell = from_layout_node(&node, 101, 40, &[4, 5, 6]);
+        let expected = "101x40,0,0{30x40,0,0,4,70x40,31,0[70x20,31,0,5,70x19,31,21,6]}";
+        assert_eq!(emit_tmux_layout(&cell), with_checksum(expected));
+    }
+
+    #[test]
+    fn layout_round_trips_through_node() {
+        let body = "200x50,0,0[200x25,0,0,0,200x24,0,26{100x24,0,26,1,99x24,101,26,2}]";
+        let cell = parse_tmux_layout(&with_checksum(body)).unwrap();
+        let node = to_layout_node(&cell, &|_| String::new());
+        let rebuilt = from_layout_node(&node, 200, 50, &cell.panes());
+        assert_eq!(rebuilt, cell);
+    }
+}
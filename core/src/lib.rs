@@ -1,8 +1,12 @@
 pub mod sys;
 pub mod command;
+pub mod event;
 pub mod service;
 pub mod help;
+pub mod status;
 pub mod types;
+pub mod view;
+pub mod job;
 pub mod infrastructure;
 pub mod layout;
 pub mod data;
@@ -1,32 +1,71 @@
 //! MuxUX service — Unix socket listener wrapping cmx-utils service.
+//!
+//! `run_daemon` is the long-lived service loop behind `mux daemon run`: it
+//! owns one `Sys` and one set of `Watchers`, accepts connections on `mux.sock`
+//! until a `DaemonStop` arrives or SIGTERM/SIGINT is received, and executes
+//! the actions each command queues. Between connections it drives the
+//! `SnapshotScheduler`. Every executed command and every change the scheduler
+//! sees becomes a `MuxEvent` in the daemon's `EventLog`, which is what
+//! watchers receive. A watch poll that sees no event before its timeout is
+//! answered with an empty batch (`[]`).
 
-use std::os::unix::net::UnixStream;
-use std::path::Path;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
+use cmx_utils::response::Response;
 use cmx_utils::watch::WatchRegistry;
 
-use crate::command::Command;
+use crate::command::{Command, Request};
+use crate::event::{now_ms, records_json, EventLog, MuxEvent};
+use crate::infrastructure::executing::{failures, ExecutingTmuxBackend};
+use crate::infrastructure::runner::CommandRunner;
+use crate::layout::history::HistoryStore;
+use crate::layout::scheduler::SnapshotScheduler;
 use crate::sys::Sys;
 
 
-/// Handle a single connection: read command, dispatch through Sys.
-pub fn handle_connection(
+/// How long the accept loop sleeps when no connection is pending.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Set by the SIGTERM/SIGINT handler; checked by the accept loop.
+static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+
+/// Handle a single connection: read command, dispatch through Sys, execute
+/// the queued actions, then reply. A request that names its project root
+/// runs against that project instead of the daemon's own.
+///
+/// A `Watch` whose `since` sequence number is behind the log is answered at
+/// once with the missed events; otherwise it waits for the next event.
+pub fn handle_connection<R: CommandRunner>(
     mut stream: UnixStream,
     sys: &mut Sys,
-    registry: &mut WatchRegistry,
+    watchers: &mut Watchers,
+    backend: &mut ExecutingTmuxBackend<R>,
+    events: &mut EventLog,
 ) -> Result<bool, String> {
     let raw = cmx_utils::service::read_frame(&mut stream)?;
 
-    let cmd: Command = serde_json::from_slice(&raw)
+    let Request { command: cmd, project_root } = serde_json::from_slice(&raw)
         .map_err(|e| format!("Failed to parse command JSON: {}", e))?;
 
     match cmd {
         Command::Watch { since, timeout } => {
-            let since_ms = since.and_then(|s| s.parse::<u64>().ok());
+            let since_seq = since.and_then(|s| s.parse::<u64>().ok());
+            let missed = since_seq.map(|seq| events.since(seq)).unwrap_or_default();
+            if !missed.is_empty() {
+                let response = Response::Ok {
+                    output: records_json(&missed),
+                };
+                cmx_utils::service::write_response(&mut stream, &response)?;
+                return Ok(false);
+            }
             let timeout_ms = timeout
                 .and_then(|t| t.parse::<u64>().ok())
                 .unwrap_or(30_000);
-            registry.register(stream, since_ms, timeout_ms);
+            watchers.register(stream, now_ms(), timeout_ms);
             Ok(false)
         }
         Command::DaemonStop => {
@@ -35,20 +74,17 @@ pub fn handle_connection(
             Ok(true) // signal shutdown
         }
         _ => {
-            let summary = format!("{:?}", cmd);
-            let summary = if summary.len() > 200 {
-                format!("{}...", &summary[..200])
-            } else {
-                summary
-            };
-            let response = sys.execute(cmd);
+            let command = serde_json::to_value(&cmd).unwrap_or_default();
+            let own_root = sys.settings().project_root.clone();
+            if let Some(root) = project_root {
+                sys.set_project_root(root);
+            }
+            let response = execute_command(sys, backend, cmd);
+            sys.set_project_root(own_root);
             cmx_utils::service::write_response(&mut stream, &response)?;
 
-            let now_ms = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_millis() as u64;
-            registry.notify_all(&summary, now_ms);
+            let ok = matches!(response, Response::Ok { .. });
+            publish(events, watchers, MuxEvent::CommandExecuted { command, ok });
 
             Ok(false)
         }
@@ -56,15 +92,310 @@ pub fn handle_connection(
 }
 
 
+/// Record `event` in the log and send it to every waiting watcher.
+pub fn publish(events: &mut EventLog, watchers: &mut Watchers, event: MuxEvent) {
+    let now = now_ms();
+    let record = events.push(event, now);
+    watchers.notify_all(&records_json(&[record]), now);
+}
+
+
+/// Watch polls waiting for the next event.
+///
+/// `WatchRegistry` delivers events; this also remembers when each poll
+/// times out, so the daemon can answer it with an empty batch instead of
+/// leaving the client to hit its socket timeout.
+#[derive(Default)]
+pub struct Watchers {
+    registry: WatchRegistry,
+    /// A second handle on each waiting stream, with its deadline in Unix ms.
+    deadlines: Vec<(UnixStream, u64)>,
+}
+
+
+impl Watchers {
+    pub fn new() -> Watchers {
+        Watchers::default()
+    }
+
+    /// Hold `stream` open until the next event or `timeout_ms` from `now_ms`.
+    pub fn register(&mut self, stream: UnixStream, now_ms: u64, timeout_ms: u64) {
+        if let Ok(answer) = stream.try_clone() {
+            self.deadlines.push((answer, now_ms.saturating_add(timeout_ms)));
+        }
+        self.registry.register(stream, Some(now_ms), timeout_ms);
+    }
+
+    /// Send `msg` to every waiting poll; each poll gets one answer.
+    pub fn notify_all(&mut self, msg: &str, now_ms: u64) {
+        self.registry.notify_all(msg, now_ms);
+        self.deadlines.clear();
+    }
+
+    /// Answer every poll whose deadline has passed with `[]` and drop it.
+    pub fn expire(&mut self, now_ms: u64) {
+        let (due, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut self.deadlines)
+            .into_iter()
+            .partition(|(_, deadline)| *deadline <= now_ms);
+        for (mut stream, _) in due {
+            let empty = Response::Ok {
+                output: records_json(&[]),
+            };
+            let _ = cmx_utils::service::write_response(&mut stream, &empty);
+        }
+        self.deadlines = waiting;
+        self.registry.expire(now_ms);
+    }
+
+    /// Number of polls still waiting.
+    pub fn len(&self) -> usize {
+        self.deadlines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deadlines.is_empty()
+    }
+}
+
+
+/// Execute a command through `sys` and run the job and actions it queues
+/// on `backend`.
+///
+/// Any tmux command that fails turns a successful response into an error
+/// listing the failures, so callers never report a layout change that did
+/// not happen.
+pub fn execute_command<R: CommandRunner>(
+    sys: &mut Sys,
+    backend: &mut ExecutingTmuxBackend<R>,
+    cmd: Command,
+) -> Response {
+    let mut response = sys.execute(cmd);
+    if let Some(job) = sys.take_job() {
+        response = job.run(backend.runner());
+    }
+    let outcomes = backend.run_actions(&sys.drain_actions());
+    let failed = failures(&outcomes);
+    if failed.is_empty() {
+        return response;
+    }
+    let message = match response {
+        Response::Ok { output } => format!("{}; tmux failed: {}", output, failed.join("; ")),
+        Response::Error { message } => message,
+    };
+    Response::Error { message }
+}
+
+
 /// Start the MuxUX service socket.
 pub fn start(config_dir: &Path) -> Result<cmx_utils::service::ServiceSocket, String> {
     cmx_utils::service::ServiceSocket::start(config_dir, "mux")
 }
 
 
+/// Path of the daemon socket inside `config_dir`.
+pub fn socket_path(config_dir: &Path) -> PathBuf {
+    config_dir.join("mux.sock")
+}
+
+
+/// Path of the daemon pidfile inside `config_dir`.
+pub fn pidfile_path(config_dir: &Path) -> PathBuf {
+    config_dir.join("mux.pid")
+}
+
+
+/// The pid recorded in `mux.pid`, if that process is still alive.
+///
+/// A daemon killed without cleaning up leaves its pidfile behind, so the
+/// file alone does not mean one is running.
+pub fn live_daemon_pid(config_dir: &Path) -> Option<u32> {
+    let pid = std::fs::read_to_string(pidfile_path(config_dir))
+        .ok()?
+        .trim()
+        .parse::<u32>()
+        .ok()?;
+    process_alive(pid).then_some(pid)
+}
+
+
+/// Whether a process with `pid` exists, via `kill(pid, 0)`.
+fn process_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    if pid <= 0 {
+        return false;
+    }
+    // SAFETY: signal 0 sends nothing; it only checks that the pid exists.
+    let found = unsafe { libc::kill(pid, 0) } == 0;
+    // EPERM means the process exists but belongs to someone else.
+    found || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+
+/// Run the daemon in the foreground until `DaemonStop` or SIGTERM/SIGINT.
+///
+/// Writes `mux.pid`, binds `mux.sock`, and removes both on the way out.
+/// Refuses to start when another daemon already answers on the socket.
+pub fn run_daemon<R: CommandRunner>(
+    config_dir: &Path,
+    sys: &mut Sys,
+    runner: R,
+) -> Result<(), String> {
+    install_signal_handlers();
+    serve_until_stopped(config_dir, sys, runner)
+}
+
+
+/// `run_daemon` without the process-wide signal handlers, so tests can run
+/// a daemon without changing how the test binary handles signals.
+fn serve_until_stopped<R: CommandRunner>(
+    config_dir: &Path,
+    sys: &mut Sys,
+    runner: R,
+) -> Result<(), String> {
+    std::fs::create_dir_all(config_dir)
+        .map_err(|e| format!("cannot create {}: {}", config_dir.display(), e))?;
+
+    // The pidfile goes first, so anyone who sees the socket also sees it.
+    refuse_live_daemon(&socket_path(config_dir))?;
+    let pidfile = pidfile_path(config_dir);
+    std::fs::write(&pidfile, format!("{}\n", std::process::id()))
+        .map_err(|e| format!("cannot write {}: {}", pidfile.display(), e))?;
+    let listener = match bind_socket(config_dir) {
+        Ok(listener) => listener,
+        Err(e) => {
+            let _ = std::fs::remove_file(&pidfile);
+            return Err(e);
+        }
+    };
+
+    sys.set_daemon_started(now_ms());
+    eprintln!("[mux-daemon] listening on {}", socket_path(config_dir).display());
+
+    let mut backend = ExecutingTmuxBackend::new(runner);
+    let result = serve(&listener, sys, &mut backend);
+
+    let _ = std::fs::remove_file(socket_path(config_dir));
+    let _ = std::fs::remove_file(&pidfile);
+    eprintln!("[mux-daemon] stopped");
+    result
+}
+
+
+/// Accept connections until a handler signals shutdown or a signal arrives.
+fn serve<R: CommandRunner>(
+    listener: &UnixListener,
+    sys: &mut Sys,
+    backend: &mut ExecutingTmuxBackend<R>,
+) -> Result<(), String> {
+    let mut watchers = Watchers::new();
+    let mut events = EventLog::new();
+    let mut scheduler = snapshot_scheduler(sys);
+    while !SHUTDOWN_REQUESTED.load(Ordering::SeqCst) {
+        watchers.expire(now_ms());
+        if let Some(scheduler) = scheduler.as_mut() {
+            for event in scheduler.tick(backend.runner(), now_ms()) {
+                if let MuxEvent::HistoryFailed { session, error } = &event {
+                    eprintln!("[mux-daemon] history of '{}': {}", session, error);
+                }
+                publish(&mut events, &mut watchers, event);
+            }
+            sys.set_last_captures(scheduler.last_captures());
+        }
+        match listener.accept() {
+            Ok((stream, _)) => {
+                // Accepted sockets inherit O_NONBLOCK on some platforms.
+                let _ = stream.set_nonblocking(false);
+                match handle_connection(stream, sys, &mut watchers, backend, &mut events) {
+                    Ok(true) => break,
+                    Ok(false) => {}
+                    Err(e) => eprintln!("[mux-daemon] connection error: {}", e),
+                }
+            }
+            Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                std::thread::sleep(POLL_INTERVAL);
+            }
+            Err(ref e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(format!("accept failed: {}", e)),
+        }
+    }
+    Ok(())
+}
+
+
+/// The background snapshot scheduler, unless `snapshot_interval_ms` is 0.
+fn snapshot_scheduler(sys: &Sys) -> Option<SnapshotScheduler> {
+    let settings = sys.settings();
+    if settings.snapshot_interval_ms == 0 {
+        return None;
+    }
+    let history = if settings.config_dir.is_empty() {
+        None
+    } else {
+        Some(HistoryStore::new(Path::new(&settings.config_dir)))
+    };
+    Some(SnapshotScheduler::new(
+        settings.snapshot_interval_ms,
+        settings.layout_tolerance_percent,
+        history,
+    ))
+}
+
+
+/// Fail if a daemon answers on the socket at `path`.
+fn refuse_live_daemon(path: &Path) -> Result<(), String> {
+    if path.exists() && UnixStream::connect(path).is_ok() {
+        return Err(format!("a daemon is already running on {}", path.display()));
+    }
+    Ok(())
+}
+
+
+/// Bind `mux.sock`, clearing a stale socket left behind by a dead daemon.
+fn bind_socket(config_dir: &Path) -> Result<UnixListener, String> {
+    let path = socket_path(config_dir);
+    if path.exists() {
+        refuse_live_daemon(&path)?;
+        std::fs::remove_file(&path)
+            .map_err(|e| format!("cannot remove stale socket {}: {}", path.display(), e))?;
+    }
+    let listener = UnixListener::bind(&path)
+        .map_err(|e| format!("cannot bind {}: {}", path.display(), e))?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| format!("cannot configure socket: {}", e))?;
+    Ok(listener)
+}
+
+
+extern "C" fn handle_shutdown_signal(_signal: libc::c_int) {
+    SHUTDOWN_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+
+fn install_signal_handlers() {
+    SHUTDOWN_REQUESTED.store(false, Ordering::SeqCst);
+    let handler = handle_shutdown_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    // SAFETY: the handler only stores to an atomic, which is async-signal-safe.
+    unsafe {
+        libc::signal(libc::SIGTERM, handler);
+        libc::signal(libc::SIGINT, handler);
+    }
+}
+
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::infrastructure::runner::MockRunner;
+
+    fn temp_dir(tag: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("muxux-{}-{}", tag, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
 
     #[test]
     fn start_returns_socket_name() {
@@ -76,4 +407,229 @@ mod tests {
         sock.shutdown();
         let _ = std::fs::remove_dir_all(&dir);
     }
+
+    #[test]
+    fn execute_command_runs_queued_actions() {
+        let mut sys = Sys::new("/tmp".into());
+        let mut backend = ExecutingTmuxBackend::new(MockRunner::new());
+        let resp = execute_command(
+            &mut sys,
+            &mut backend,
+            Command::LayoutRow {
+                session: "work".into(),
+                percent: Some("40".into()),
+            },
+        );
+        assert!(matches!(resp, Response::Ok { .. }));
+        let cmds = backend.runner().executed_commands();
+        assert_eq!(cmds.len(), 1);
+        assert!(cmds[0].contains("split-window -t work -h -p 40"));
+    }
+
+    #[test]
+    fn execute_command_surfaces_tmux_failure() {
+        let mut sys = Sys::new("/tmp".into());
+        let runner = MockRunner::with_responses(vec![Err("can't find pane: nope".into())]);
+        let mut backend = ExecutingTmuxBackend::new(runner);
+        let resp = execute_command(
+            &mut sys,
+            &mut backend,
+            Command::LayoutColumn {
+                session: "nope".into(),
+                percent: None,
+            },
+        );
+        match resp {
+            Response::Error { message } => assert!(message.contains("can't find pane")),
+            other => panic!("expected error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn bind_socket_replaces_stale_file() {
+        let dir = temp_dir("stale");
+        std::fs::write(socket_path(&dir), b"").unwrap();
+        let listener = bind_socket(&dir);
+        assert!(listener.is_ok());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn bind_socket_refuses_live_daemon() {
+        let dir = temp_dir("live");
+        let _first = bind_socket(&dir).unwrap();
+        let second = bind_socket(&dir);
+        assert!(second.unwrap_err().contains("already running"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn failed_start_leaves_no_files() {
+        let dir = temp_dir("failed-start");
+        let _live = bind_socket(&dir).unwrap();
+        std::fs::write(pidfile_path(&dir), "1\n").unwrap();
+        let mut sys = Sys::new("/tmp".into());
+        let result = serve_until_stopped(&dir, &mut sys, MockRunner::new());
+        assert!(result.unwrap_err().contains("already running"));
+        // The live daemon's pidfile is left alone.
+        assert_eq!(std::fs::read_to_string(pidfile_path(&dir)).unwrap(), "1\n");
+
+        // A pidfile that cannot be written stops the start before binding.
+        let blocked = temp_dir("blocked-pid");
+        std::fs::create_dir_all(pidfile_path(&blocked)).unwrap();
+        let result = serve_until_stopped(&blocked, &mut sys, MockRunner::new());
+        assert!(result.unwrap_err().contains("cannot write"));
+        assert!(!socket_path(&blocked).exists());
+        let _ = std::fs::remove_dir_all(&dir);
+        let _ = std::fs::remove_dir_all(&blocked);
+    }
+
+    #[test]
+    fn daemon_serves_until_stop() {
+        let dir = temp_dir("daemon");
+        let server = start_daemon(&dir);
+        let sock = socket_path(&dir);
+        assert!(pidfile_path(&dir).exists());
+
+        let send = |cmd: &Command| cmx_utils::client::send_and_receive(&sock, cmd, 5_000).unwrap();
+
+        let help = send(&Command::Help { topic: None });
+        assert!(matches!(help, Response::Ok { .. }));
+        let replay = send(&Command::Watch {
+            since: Some("0".into()),
+            timeout: None,
+        });
+        match replay {
+            Response::Ok { output } => {
+                let events: serde_json::Value = serde_json::from_str(&output).unwrap();
+                assert_eq!(events[0]["seq"], 1);
+                assert_eq!(events[0]["type"], "command_executed");
+                assert_eq!(events[0]["command"]["command"], "help");
+            }
+            other => panic!("expected replay, got {:?}", other),
+        }
+        let stop = send(&Command::DaemonStop);
+        assert!(matches!(stop, Response::Ok { .. }));
+
+        server.join().unwrap().unwrap();
+        assert!(!sock.exists());
+        assert!(!pidfile_path(&dir).exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn quiet_watch_is_answered_with_an_empty_batch() {
+        let dir = temp_dir("quiet");
+        let server = start_daemon(&dir);
+        let sock = socket_path(&dir);
+        let started = std::time::Instant::now();
+        let quiet = cmx_utils::client::send_and_receive(
+            &sock,
+            &Command::Watch {
+                since: None,
+                timeout: Some("200".into()),
+            },
+            5_000,
+        );
+        assert_eq!(quiet, Ok(Response::Ok { output: "[]".into() }));
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        cmx_utils::client::send_and_receive(&sock, &Command::DaemonStop, 5_000).unwrap();
+        server.join().unwrap().unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn requests_use_the_clients_project_catalog() {
+        let dir = temp_dir("project");
+        std::fs::create_dir_all(dir.join(".mux")).unwrap();
+        std::fs::write(dir.join(".mux/parts.md"), "## team-lead\nrole: lead\n").unwrap();
+        let server = start_daemon(&dir);
+        let sock = socket_path(&dir);
+        let request = Request {
+            command: Command::PartsList,
+            project_root: Some(dir.to_string_lossy().into()),
+        };
+        let with_root = cmx_utils::client::send_and_receive(&sock, &request, 5_000);
+        let without = cmx_utils::client::send_and_receive(&sock, &Command::PartsList, 5_000);
+        cmx_utils::client::send_and_receive(&sock, &Command::DaemonStop, 5_000).unwrap();
+        server.join().unwrap().unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let output = |r: Result<Response, String>| match r {
+            Ok(Response::Ok { output }) => output,
+            other => panic!("Unexpected response: {:?}", other),
+        };
+        assert!(output(with_root).contains("team-lead"));
+        assert!(!output(without).contains("team-lead"));
+    }
+
+    #[test]
+    fn watcher_is_dropped_after_its_ttl() {
+        let (server, mut client) = UnixStream::pair().unwrap();
+        let mut watchers = Watchers::new();
+        watchers.register(server, 1_000, 500);
+        watchers.expire(1_499);
+        assert_eq!(watchers.len(), 1);
+        watchers.expire(1_500);
+        assert!(watchers.is_empty());
+        let raw = cmx_utils::service::read_frame(&mut client).unwrap();
+        let answer: Response = serde_json::from_slice(&raw).unwrap();
+        assert_eq!(answer, Response::Ok { output: "[]".into() });
+    }
+
+    #[test]
+    fn watch_stream_resumes_after_an_expired_poll() {
+        let dir = temp_dir("resume");
+        let server = start_daemon(&dir);
+        let sock = socket_path(&dir);
+        let send = |cmd: &Command| cmx_utils::client::send_and_receive(&sock, cmd, 5_000).unwrap();
+        let watch = |since: Option<&str>| Command::Watch {
+            since: since.map(|s| s.to_string()),
+            timeout: Some("100".into()),
+        };
+        assert_eq!(send(&watch(None)), Response::Ok { output: "[]".into() });
+        send(&Command::Help { topic: None });
+        match send(&watch(Some("0"))) {
+            Response::Ok { output } => {
+                let events: serde_json::Value = serde_json::from_str(&output).unwrap();
+                assert_eq!(events[0]["seq"], 1);
+            }
+            other => panic!("expected replay, got {:?}", other),
+        }
+        send(&Command::DaemonStop);
+        server.join().unwrap().unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn live_daemon_pid_ignores_stale_pidfiles() {
+        let dir = temp_dir("pid");
+        assert_eq!(live_daemon_pid(&dir), None);
+        std::fs::write(pidfile_path(&dir), format!("{}\n", std::process::id())).unwrap();
+        assert_eq!(live_daemon_pid(&dir), Some(std::process::id()));
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let dead = child.id();
+        child.wait().unwrap();
+        std::fs::write(pidfile_path(&dir), format!("{}\n", dead)).unwrap();
+        assert_eq!(live_daemon_pid(&dir), None);
+        std::fs::write(pidfile_path(&dir), "0\n").unwrap();
+        assert_eq!(live_daemon_pid(&dir), None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// Serve `dir` on a background thread and wait for its socket.
+    fn start_daemon(dir: &Path) -> std::thread::JoinHandle<Result<(), String>> {
+        let server_dir = dir.to_path_buf();
+        let server = std::thread::spawn(move || {
+            let mut sys = Sys::new("/tmp".into());
+            serve_until_stopped(&server_dir, &mut sys, MockRunner::new())
+        });
+        let sock = socket_path(dir);
+        let mut tries = 0;
+        while !sock.exists() && tries < 100 {
+            std::thread::sleep(Duration::from_millis(20));
+            tries += 1;
+        }
+        server
+    }
 }
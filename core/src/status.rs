@@ -0,0 +1,327 @@
+//! `mux status` — a snapshot of the daemon, tmux, hook, and parts catalog.
+//!
+//! `StatusReport` is the schema behind `mux status --json`. Its shape is
+//! stable: fields are only ever added, and `schema_version` is bumped if one
+//! has to change meaning.
+//!
+//! ```json
+//! {
+//!   "schema_version": 1,
+//!   "daemon":  { "running": true, "pid": 4242, "uptime_ms": 93000 },
+//!   "config_dir": "/home/me/.config/muxux",
+//!   "tmux":    { "server_running": true, "sessions": 2, "windows": 3, "panes": 7 },
+//!   "sessions": [
+//!     { "name": "work", "windows": 2, "panes": 5, "last_capture_ms": 1700000000000 }
+//!   ],
+//!   "hook":    { "installed": true },
+//!   "catalog": { "path": "/home/me/.config/skd/skd-library/parts.md",
+//!                "agents": 4, "compositions": 2, "sessions": 2 }
+//! }
+//! ```
+//!
+//! `pid`, `uptime_ms`, `last_capture_ms`, and `path` are `null` when unknown.
+
+use serde::{Deserialize, Serialize};
+
+use crate::data::parts::PartRegistry;
+use crate::infrastructure::runner::CommandRunner;
+use crate::infrastructure::tmux::TmuxCommandBuilder;
+use crate::types::tiles::TileKind;
+
+
+/// Version of the `StatusReport` JSON schema.
+pub const SCHEMA_VERSION: u32 = 1;
+
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StatusReport {
+    pub schema_version: u32,
+    pub daemon: DaemonStatus,
+    pub config_dir: String,
+    pub tmux: TmuxStatus,
+    pub sessions: Vec<SessionStatus>,
+    pub hook: HookStatus,
+    pub catalog: CatalogStatus,
+}
+
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DaemonStatus {
+    pub running: bool,
+    pub pid: Option<u32>,
+    /// Only known when the report comes from the daemon itself.
+    pub uptime_ms: Option<u64>,
+}
+
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TmuxStatus {
+    pub server_running: bool,
+    pub sessions: usize,
+    pub windows: usize,
+    pub panes: usize,
+}
+
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionStatus {
+    pub name: String,
+    pub windows: usize,
+    pub panes: usize,
+    /// Unix ms of the last layout capture, from the daemon or the history.
+    pub last_capture_ms: Option<u64>,
+}
+
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HookStatus {
+    /// Whether the right-click `mux studio` binding is present.
+    pub installed: bool,
+}
+
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CatalogStatus {
+    pub path: Option<String>,
+    pub agents: usize,
+    pub compositions: usize,
+    pub sessions: usize,
+}
+
+
+/// Count sessions, windows, and panes from `list-panes -a` output, keeping
+/// tmux's session order.
+pub fn parse_pane_census(output: &str) -> Vec<SessionStatus> {
+    let mut sessions: Vec<(SessionStatus, Vec<String>)> = Vec::new();
+    for line in output.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        // Session names may contain ':', so split from the right.
+        let mut fields = line.rsplitn(3, ':');
+        let (Some(_pane), Some(window), Some(name)) = (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        let index = match sessions.iter().position(|(s, _)| s.name == name) {
+            Some(i) => i,
+            None => {
+                sessions.push((
+                    SessionStatus {
+                        name: name.to_string(),
+                        windows: 0,
+                        panes: 0,
+                        last_capture_ms: None,
+                    },
+                    Vec::new(),
+                ));
+                sessions.len() - 1
+            }
+        };
+        let (status, windows) = &mut sessions[index];
+        status.panes += 1;
+        if !windows.iter().any(|w| w == window) {
+            windows.push(window.to_string());
+            status.windows += 1;
+        }
+    }
+    sessions.into_iter().map(|(s, _)| s).collect()
+}
+
+
+/// Query tmux through `runner` and assemble a report.
+///
+/// `last_capture` gives each session's last capture time, if known.
+pub fn collect(
+    runner: &dyn CommandRunner,
+    daemon: DaemonStatus,
+    config_dir: &str,
+    catalog: &PartRegistry,
+    catalog_path: Option<String>,
+    last_capture: &dyn Fn(&str) -> Option<u64>,
+) -> StatusReport {
+    let builder = TmuxCommandBuilder::new();
+    let census = runner.run(&builder.list_all_panes());
+    let server_running = census.is_ok();
+    let mut sessions = census.map(|out| parse_pane_census(&out)).unwrap_or_default();
+    for session in &mut sessions {
+        session.last_capture_ms = last_capture(&session.name);
+    }
+    let hook = runner
+        .run(&builder.list_mouse_hook())
+        .map(|out| out.contains("studio"))
+        .unwrap_or(false);
+
+    StatusReport {
+        schema_version: SCHEMA_VERSION,
+        daemon,
+        config_dir: config_dir.to_string(),
+        tmux: TmuxStatus {
+            server_running,
+            sessions: sessions.len(),
+            windows: sessions.iter().map(|s| s.windows).sum(),
+            panes: sessions.iter().map(|s| s.panes).sum(),
+        },
+        sessions,
+        hook: HookStatus { installed: hook },
+        catalog: CatalogStatus {
+            path: catalog_path,
+            agents: catalog.by_kind(TileKind::Agent).len(),
+            compositions: catalog.by_kind(TileKind::Composition).len(),
+            sessions: catalog.by_kind(TileKind::Session).len(),
+        },
+    }
+}
+
+
+/// Human-readable rendering of a report, relative to `now_ms`.
+pub fn format_text(report: &StatusReport, now_ms: u64) -> String {
+    let daemon = match (&report.daemon.pid, report.daemon.running) {
+        (_, false) => "not running".to_string(),
+        (Some(pid), true) => match report.daemon.uptime_ms {
+            Some(up) => format!("running (pid {}, up {})", pid, duration(up)),
+            None => format!("running (pid {})", pid),
+        },
+        (None, true) => "running".to_string(),
+    };
+    let tmux = if report.tmux.server_running {
+        format!(
+            "{} sessions, {} windows, {} panes",
+            report.tmux.sessions, report.tmux.windows, report.tmux.panes
+        )
+    } else {
+        "server not running".to_string()
+    };
+    let config_dir = if report.config_dir.is_empty() {
+        "(none)"
+    } else {
+        report.config_dir.as_str()
+    };
+    let mut lines = vec![
+        "MuxUX status".to_string(),
+        format!("  daemon:   {}", daemon),
+        format!("  config:   {}", config_dir),
+        format!("  tmux:     {}", tmux),
+        format!(
+            "  hook:     {}",
+            if report.hook.installed { "installed" } else { "not installed" }
+        ),
+        format!(
+            "  catalog:  {} agents, {} compositions, {} sessions{}",
+            report.catalog.agents,
+            report.catalog.compositions,
+            report.catalog.sessions,
+            report
+                .catalog
+                .path
+                .as_ref()
+                .map(|p| format!(" ({})", p))
+                .unwrap_or_default()
+        ),
+    ];
+    for session in &report.sessions {
+        let captured = match session.last_capture_ms {
+            Some(ms) => format!("captured {} ago", duration(now_ms.saturating_sub(ms))),
+            None => "never captured".to_string(),
+        };
+        lines.push(format!(
+            "  {:<16} {} windows, {} panes, {}",
+            session.name, session.windows, session.panes, captured
+        ));
+    }
+    lines.join("\n")
+}
+
+
+/// Compact duration: `45s`, `3m 12s`, `2h 5m`, `3d 4h`.
+fn duration(ms: u64) -> String {
+    let s = ms / 1000;
+    match s {
+        0..=59 => format!("{}s", s),
+        60..=3_599 => format!("{}m {}s", s / 60, s % 60),
+        3_600..=86_399 => format!("{}h {}m", s / 3_600, s / 60 % 60),
+        _ => format!("{}d {}h", s / 86_400, s / 3_600 % 24),
+    }
+}
+
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::runner::MockRunner;
+
+    const CENSUS: &str = "work:0:%0\nwork:0:%1\nwork:1:%2\nmy:app:0:%3\n";
+
+    fn daemon() -> DaemonStatus {
+        DaemonStatus {
+            running: true,
+            pid: Some(42),
+            uptime_ms: Some(125_000),
+        }
+    }
+
+    #[test]
+    fn census_counts_windows_and_panes() {
+        let sessions = parse_pane_census(CENSUS);
+        assert_eq!(sessions.len(), 2);
+        assert_eq!((sessions[0].windows, sessions[0].panes), (2, 3));
+        assert_eq!(sessions[1].name, "my:app");
+        assert_eq!((sessions[1].windows, sessions[1].panes), (1, 1));
+    }
+
+    #[test]
+    fn collect_builds_report() {
+        let runner = MockRunner::with_responses(vec![
+            Ok(CENSUS.into()),
+            Ok("bind-key -T root MouseDown3Pane run-shell \"mux studio\"\n".into()),
+        ]);
+        let catalog = PartRegistry::from_markdown("## pm\nrole: pm\n");
+        let last = |s: &str| if s == "work" { Some(1_000) } else { None };
+        let report = collect(&runner, daemon(), "/cfg", &catalog, None, &last);
+        assert!(report.tmux.server_running);
+        assert_eq!(
+            (report.tmux.sessions, report.tmux.windows, report.tmux.panes),
+            (2, 3, 4)
+        );
+        assert_eq!(report.sessions[0].last_capture_ms, Some(1_000));
+        assert!(report.hook.installed);
+        assert_eq!(report.catalog.agents, 1);
+    }
+
+    #[test]
+    fn collect_without_tmux_server() {
+        let runner = MockRunner::with_responses(vec![
+            Err("no server running".into()),
+            Err("no server running".into()),
+        ]);
+        let report = collect(&runner, daemon(), "", &PartRegistry::default(), None, &|_| None);
+        assert!(!report.tmux.server_running);
+        assert!(report.sessions.is_empty());
+        assert!(!report.hook.installed);
+    }
+
+    #[test]
+    fn json_schema_is_stable() {
+        let runner = MockRunner::with_responses(vec![Ok("work:0:%0\n".into())]);
+        let report = collect(&runner, daemon(), "/cfg", &PartRegistry::default(), None, &|_| None);
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["schema_version"], 1);
+        assert_eq!(json["daemon"]["uptime_ms"], 125_000);
+        assert_eq!(json["tmux"]["panes"], 1);
+        assert!(json["sessions"][0]["last_capture_ms"].is_null());
+        assert!(json["catalog"]["path"].is_null());
+        assert_eq!(json["hook"]["installed"], false);
+    }
+
+    #[test]
+    fn text_format() {
+        let runner = MockRunner::with_responses(vec![Ok("work:0:%0\n".into())]);
+        let last = |_: &str| Some(10_000);
+        let report = collect(&runner, daemon(), "/cfg", &PartRegistry::default(), None, &last);
+        let text = format_text(&report, 70_000);
+        assert!(text.contains("daemon:   running (pid 42, up 2m 5s)"));
+        assert!(text.contains("tmux:     1 sessions, 1 windows, 1 panes"));
+        assert!(text.contains("captured 1m 0s ago"));
+    }
+}
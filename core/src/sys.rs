@@ -1,6 +1,14 @@
+use std::collections::HashMap;
+
 use crate::command::Command;
+use crate::event::now_ms;
 use crate::infrastructure::tmux::{TmuxCommandBuilder, parse_list_sessions};
+use crate::job::Job;
+use crate::layout::merge::MergeMode;
+use crate::layout::history::{format_history, At, HistoryStore};
+use crate::status::DaemonStatus;
 use crate::types::config::MuxSettings;
+use crate::types::session::SessionSnapshot;
 use cmx_utils::response::{Action, Direction, Response};
 
 
@@ -9,22 +17,63 @@ pub struct Sys {
     project_root: String,
     actions: Vec<Action>,
     settings: MuxSettings,
+    /// Work queued during the last execute() call that must read tmux
+    /// while it runs (e.g. merge, which lists panes before killing them).
+    job: Option<Job>,
+    /// When this Sys started serving as the daemon, in Unix ms.
+    daemon_started_ms: Option<u64>,
+    /// Last layout capture per session, as reported by the daemon scheduler.
+    last_captures: HashMap<String, u64>,
 }
 
 
 impl Sys {
     pub fn new(project_root: String) -> Sys {
-        let settings = MuxSettings {
-            project_root: project_root.clone(),
+        Sys::with_settings(MuxSettings {
+            project_root,
             ..MuxSettings::default()
-        };
+        })
+    }
+
+    /// A Sys configured by `settings`, e.g. from `MuxSettings::load`.
+    pub fn with_settings(settings: MuxSettings) -> Sys {
         Sys {
-            project_root,
+            project_root: settings.project_root.clone(),
             actions: Vec::new(),
             settings,
+            job: None,
+            daemon_started_ms: None,
+            last_captures: HashMap::new(),
         }
     }
 
+    /// Keep daemon state such as layout history under `config_dir`.
+    pub fn set_config_dir(&mut self, config_dir: String) {
+        self.settings.config_dir = config_dir;
+    }
+
+    /// Run later commands for the project at `project_root`: its catalog
+    /// layer and its directory as the default cwd.
+    pub fn set_project_root(&mut self, project_root: String) {
+        self.settings.project_root = project_root.clone();
+        self.project_root = project_root;
+    }
+
+    /// The parts catalog, with the system, user, and project layers merged.
+    pub fn catalog(&self) -> crate::data::parts::PartRegistry {
+        crate::data::parts::PartRegistry::from_settings(&self.settings)
+    }
+
+    /// Mark this Sys as the daemon's, started at `now_ms`.
+    pub fn set_daemon_started(&mut self, now_ms: u64) {
+        self.daemon_started_ms = Some(now_ms);
+    }
+
+    /// Replace the per-session last capture times shown by status.
+    pub fn set_last_captures(&mut self, captures: HashMap<String, u64>) {
+        self.last_captures = captures;
+    }
+
     /// Return a reference to the current settings.
     pub fn settings(&self) -> &MuxSettings {
         &self.settings
@@ -33,20 +82,28 @@ impl Sys {
     /// The single dispatch method.
     pub fn execute(&mut self, cmd: Command) -> Response {
         self.actions.clear();
+        self.job = None;
         match cmd {
             Command::Status { format } => self.cmd_status(format),
             Command::SessionList => self.cmd_session_list(),
-            Command::View { name } => self.cmd_view(name),
+            Command::View { name, diagram } => self.cmd_view(name, diagram),
             Command::LayoutRow { session, percent } => self.cmd_layout_row(session, percent),
             Command::LayoutColumn { session, percent } => self.cmd_layout_column(session, percent),
-            Command::LayoutMerge { session } => self.cmd_layout_merge(session),
+            Command::LayoutMerge { session, keep, break_out } => {
+                self.cmd_layout_merge(session, keep, break_out)
+            }
+            Command::LayoutApply { session, layout } => self.cmd_layout_apply(session, layout),
             Command::LayoutPlace { pane, agent } => self.cmd_layout_place(pane, agent),
             Command::LayoutCapture { session } => self.cmd_layout_capture(session),
+            Command::LayoutRestore { session, part, file, at } => {
+                self.cmd_layout_restore(session, part, file, at)
+            }
+            Command::LayoutHistory { session } => self.cmd_layout_history(session),
             Command::LayoutSession { name, cwd } => self.cmd_layout_session(name, cwd),
             Command::ClientNext => self.cmd_client_next(),
             Command::ClientPrev => self.cmd_client_prev(),
             Command::Watch { .. } => Response::Error {
-                message: "Watch commands are handled at the service layer".into(),
+                message: "Watch needs a running daemon (start one with 'mux daemon run')".into(),
             },
             Command::DaemonRun => Response::Error {
                 message: "DaemonRun must be handled by the binary".into(),
@@ -59,6 +116,14 @@ impl Sys {
             Command::SetupHook => self.cmd_setup_hook(),
             Command::RemoveHook => self.cmd_remove_hook(),
             Command::PartsList => self.cmd_parts_list(),
+            Command::PartsShow { name, width, height, ascii } => {
+                self.cmd_parts_show(name, width, height, ascii)
+            }
+            Command::PartsCheck { file } => self.cmd_parts_check(file),
+            Command::PartsAdd { name, body, file } => self.cmd_parts_add(name, body, file),
+            Command::PartsUpdate { name, body, file } => self.cmd_parts_update(name, body, file),
+            Command::PartsRemove { name, file } => self.cmd_parts_remove(name, file),
+            Command::PartsRename { name, to, file } => self.cmd_parts_rename(name, to, file),
         }
     }
 
@@ -72,14 +137,47 @@ impl Sys {
         std::mem::take(&mut self.actions)
     }
 
+    /// Take the job queued by the last execute() call, if any. Its response
+    /// replaces the placeholder execute() returned.
+    pub fn take_job(&mut self) -> Option<Job> {
+        self.job.take()
+    }
+
     // -----------------------------------------------------------------------
     // Status / View
     // -----------------------------------------------------------------------
 
-    fn cmd_status(&self, _format: Option<String>) -> Response {
-        Response::Ok {
-            output: "MuxUX status: running".into(),
-        }
+    fn cmd_status(&mut self, format: Option<String>) -> Response {
+        let config_dir = &self.settings.config_dir;
+        let pidfile = if config_dir.is_empty() {
+            None
+        } else {
+            crate::service::live_daemon_pid(std::path::Path::new(config_dir))
+        };
+        let now = now_ms();
+        let daemon = match self.daemon_started_ms {
+            Some(started) => DaemonStatus {
+                running: true,
+                pid: Some(std::process::id()),
+                uptime_ms: Some(now.saturating_sub(started)),
+            },
+            None => DaemonStatus {
+                running: pidfile.is_some(),
+                pid: pidfile,
+                uptime_ms: None,
+            },
+        };
+        let catalog_path = crate::data::parts::user_catalog_path(&self.settings);
+        self.queue(Job::Status {
+            daemon,
+            config_dir: config_dir.clone(),
+            catalog: self.catalog(),
+            catalog_path: catalog_path.map(|p| p.display().to_string()),
+            last_captures: self.last_captures.clone(),
+            history: self.history(),
+            json: format.as_deref() == Some("json"),
+            now_ms: now,
+        })
     }
 
     fn cmd_session_list(&self) -> Response {
@@ -107,9 +205,19 @@ impl Sys {
         }
     }
 
-    fn cmd_view(&self, name: String) -> Response {
+    fn cmd_view(&mut self, name: String, diagram: bool) -> Response {
+        self.queue(Job::View {
+            catalog: self.catalog(),
+            name,
+            diagram,
+        })
+    }
+
+    /// Queue `job` for the backend; the real response comes from running it.
+    fn queue(&mut self, job: Job) -> Response {
+        self.job = Some(job);
         Response::Ok {
-            output: format!("MuxUX view: {}", name),
+            output: "Queued for tmux".into(),
         }
     }
 
@@ -145,30 +253,137 @@ impl Sys {
         }
     }
 
-    fn cmd_layout_merge(&self, _session: String) -> Response {
-        Response::Ok {
-            output: "Merge queued".into(),
-        }
+    fn cmd_layout_merge(&mut self, session: String, keep: Option<String>, break_out: bool) -> Response {
+        let mode = if break_out { MergeMode::Break } else { MergeMode::Kill };
+        self.queue(Job::Merge { session, keep, mode })
     }
 
-    fn cmd_layout_place(&mut self, pane_id: String, agent: String) -> Response {
-        self.actions.push(Action::PlaceAgent {
-            pane_id,
-            agent: agent.clone(),
-        });
-        Response::Ok {
-            output: format!("Agent '{}' placed in pane", agent),
+    /// Reconcile the session's current window toward a part or layout
+    /// expression, keeping panes that already match.
+    fn cmd_layout_apply(&mut self, session: String, layout: String) -> Response {
+        let catalog = self.catalog();
+        if let Some(tile) = catalog.get(&layout).filter(|t| t.windows.len() > 1) {
+            return Response::Error {
+                message: format!(
+                    "part '{}' has {} windows and apply lays out one; use 'mux layout restore <session> --part {}'",
+                    layout,
+                    tile.windows.len(),
+                    layout
+                ),
+            };
         }
+        let desired = match catalog.expand(&layout) {
+            Some(node) => node,
+            None => match crate::data::layout_expr::parse_layout_expr(&layout) {
+                Ok(node) => node,
+                Err(e) => {
+                    return Response::Error {
+                        message: format!("'{}' is neither a part nor a layout:\n{}", layout, e),
+                    }
+                }
+            },
+        };
+        self.queue(Job::Apply {
+            session,
+            layout,
+            desired,
+            catalog,
+        })
+    }
+
+    /// Tag a pane with an agent and start it; the job refuses a pane busy
+    /// with another agent or program.
+    fn cmd_layout_place(&mut self, pane: String, agent: String) -> Response {
+        let launch = self.catalog().launch_command(&agent);
+        self.queue(Job::Place { pane, agent, launch })
     }
 
     fn cmd_layout_capture(&mut self, session: String) -> Response {
-        let builder = TmuxCommandBuilder::new();
-        let list_cmd = builder.list_panes(&session);
+        let history = self.history();
+        self.queue(Job::Capture { session, history })
+    }
+
+    fn cmd_layout_history(&self, session: String) -> Response {
+        let Some(history) = self.history() else {
+            return Response::Error {
+                message: "Layout history needs a config dir".into(),
+            };
+        };
+        let entries = history.entries(&session);
+        if entries.is_empty() {
+            return Response::Ok {
+                output: format!("No layout history for '{}'", session),
+            };
+        }
         Response::Ok {
-            output: format!("Capture queued: {}", list_cmd),
+            output: format_history(&entries),
         }
     }
 
+    /// The layout history store, when a config dir is set.
+    fn history(&self) -> Option<HistoryStore> {
+        if self.settings.config_dir.is_empty() {
+            return None;
+        }
+        Some(HistoryStore::new(std::path::Path::new(&self.settings.config_dir)))
+    }
+
+    fn cmd_layout_restore(
+        &mut self,
+        session: String,
+        part: Option<String>,
+        file: Option<String>,
+        at: Option<String>,
+    ) -> Response {
+        let registry = self.catalog();
+        let snapshot: SessionSnapshot = match (part, file, at) {
+            (None, None, Some(spec)) => {
+                let found = match self.history() {
+                    Some(history) => At::parse(&spec).and_then(|at| {
+                        history.find(&session, at, now_ms())
+                    }),
+                    None => Err("Layout history needs a config dir".into()),
+                };
+                match found {
+                    Ok(snapshot) => snapshot,
+                    Err(message) => return Response::Error { message },
+                }
+            }
+            (Some(name), None, None) => match registry.session_snapshot(&name) {
+                Some(snapshot) => snapshot,
+                None => {
+                    return Response::Error {
+                        message: format!("Unknown part: '{}'", name),
+                    }
+                }
+            },
+            (None, Some(path), None) => {
+                let loaded = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("cannot read {}: {}", path, e))
+                    .and_then(|text| {
+                        serde_json::from_str(&text)
+                            .map_err(|e| format!("invalid snapshot {}: {}", path, e))
+                    });
+                match loaded {
+                    Ok(snapshot) => snapshot,
+                    Err(message) => return Response::Error { message },
+                }
+            }
+            _ => {
+                return Response::Error {
+                    message: "Restore needs exactly one of --part, --file or --at".into(),
+                }
+            }
+        };
+        let cwd = self.project_root.clone();
+        self.queue(Job::Restore {
+            session,
+            snapshot,
+            cwd,
+            catalog: registry,
+        })
+    }
+
     fn cmd_layout_session(&mut self, name: String, cwd: Option<String>) -> Response {
         let cwd = cwd.unwrap_or_else(|| self.project_root.clone());
         self.actions.push(Action::CreateSession {
@@ -225,12 +440,125 @@ impl Sys {
     // -----------------------------------------------------------------------
 
     fn cmd_parts_list(&self) -> Response {
-        let registry = crate::data::parts::PartRegistry::from_default_path();
+        let registry = self.catalog();
         Response::Ok {
             output: registry.to_json(),
         }
     }
 
+    fn cmd_parts_show(
+        &self,
+        name: String,
+        width: Option<u16>,
+        height: Option<u16>,
+        ascii: bool,
+    ) -> Response {
+        if width == Some(0) || height == Some(0) {
+            return Response::Error {
+                message: "Diagram width and height must be at least 1".into(),
+            };
+        }
+        let size = |value: Option<u16>, default: usize| value.map_or(default, usize::from);
+        let style = if ascii {
+            crate::layout::preview::BoxStyle::Ascii
+        } else {
+            crate::layout::preview::BoxStyle::Unicode
+        };
+        let registry = self.catalog();
+        match crate::view::show_part(
+            &registry,
+            &name,
+            size(width, crate::view::DIAGRAM_WIDTH),
+            size(height, crate::view::DIAGRAM_HEIGHT),
+            style,
+        ) {
+            Ok(output) => Response::Ok { output },
+            Err(message) => Response::Error { message },
+        }
+    }
+
+    /// Validate every layout in each catalog layer that exists (or in
+    /// `file`). Any error makes the response an error; warnings alone do
+    /// not.
+    fn cmd_parts_check(&self, file: Option<String>) -> Response {
+        let paths: Vec<std::path::PathBuf> = match file {
+            Some(file) => vec![file.into()],
+            None => crate::data::parts::catalog_layers(&self.settings)
+                .into_iter()
+                .filter(|p| p.exists())
+                .collect(),
+        };
+        if paths.is_empty() {
+            return Response::Error {
+                message: "No parts catalog found".into(),
+            };
+        }
+        let catalog = self.catalog();
+        let mut lines = Vec::new();
+        let mut failed = false;
+        for path in &paths {
+            let content = match std::fs::read_to_string(path) {
+                Ok(content) => content,
+                Err(e) => {
+                    return Response::Error {
+                        message: format!("Cannot read {}: {}", path.display(), e),
+                    }
+                }
+            };
+            let issues = crate::data::parts::check_markdown(&content, &catalog);
+            let label = path.display().to_string();
+            let errors = issues
+                .iter()
+                .filter(|i| i.diagnostic.severity == crate::data::layout_expr::Severity::Error)
+                .count();
+            failed |= errors > 0;
+            lines.extend(issues.iter().map(|i| i.render(&label)));
+            lines.push(format!(
+                "{}: {} errors, {} warnings",
+                label,
+                errors,
+                issues.len() - errors
+            ));
+        }
+        let output = lines.join("\n");
+        if failed {
+            Response::Error { message: output }
+        } else {
+            Response::Ok { output }
+        }
+    }
+
+    fn cmd_parts_add(&self, name: String, body: String, file: Option<String>) -> Response {
+        edit_catalog(&self.settings, file, &name, |registry, path| {
+            let warnings = registry.add(&name, &body)?;
+            Ok(edit_report(format!("Added part '{}' to {}", name, path), &body, &warnings))
+        })
+    }
+
+    fn cmd_parts_update(&self, name: String, body: String, file: Option<String>) -> Response {
+        edit_catalog(&self.settings, file, &name, |registry, path| {
+            let warnings = registry.update(&name, &body)?;
+            Ok(edit_report(format!("Updated part '{}' in {}", name, path), &body, &warnings))
+        })
+    }
+
+    fn cmd_parts_remove(&self, name: String, file: Option<String>) -> Response {
+        edit_catalog(&self.settings, file, &name, |registry, path| {
+            registry.remove(&name)?;
+            Ok(format!("Removed part '{}' from {}", name, path))
+        })
+    }
+
+    fn cmd_parts_rename(&self, name: String, to: String, file: Option<String>) -> Response {
+        edit_catalog(&self.settings, file, &to, |registry, path| {
+            let updated = registry.rename(&name, &to)?;
+            Ok(format!(
+                "Renamed part '{}' to '{}' in {} ({} layouts updated)",
+                name, to, path, updated
+            ))
+        })
+    }
+
     // -----------------------------------------------------------------------
     // Help
     // -----------------------------------------------------------------------
@@ -243,9 +571,88 @@ impl Sys {
 }
 
 
+/// `file`, or the user's parts catalog.
+fn catalog_path(
+    settings: &MuxSettings,
+    file: Option<String>,
+) -> Result<std::path::PathBuf, String> {
+    match file {
+        Some(file) => Ok(file.into()),
+        None => crate::data::parts::user_catalog_path(settings)
+            .ok_or_else(|| "No parts catalog path (HOME is not set)".into()),
+    }
+}
+
+
+/// Load the catalog at `file`, apply `edit`, and save it back. A catalog
+/// that does not exist yet starts out empty; nothing is written if `edit`
+/// fails. The output ends with where part `name` now comes from when that
+/// is not the edited file.
+fn edit_catalog(
+    settings: &MuxSettings,
+    file: Option<String>,
+    name: &str,
+    edit: impl FnOnce(&mut crate::data::parts::PartRegistry, &str) -> Result<String, String>,
+) -> Response {
+    let result = catalog_path(settings, file).and_then(|path| {
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(format!("Cannot read {}: {}", path.display(), e)),
+        };
+        let mut registry = crate::data::parts::PartRegistry::from_markdown(&content);
+        let output = edit(&mut registry, &path.display().to_string())?;
+        registry.save(&path)?;
+        Ok(match precedence_note(settings, name, &path) {
+            Some(note) => format!("{}\n{}", output, note),
+            None => output,
+        })
+    });
+    match result {
+        Ok(output) => Response::Ok { output },
+        Err(message) => Response::Error { message },
+    }
+}
+
+
+/// Which catalog layer part `name` comes from, when it is not `edited`. A
+/// later layer that still defines it shadows the edit, which is a warning.
+fn precedence_note(settings: &MuxSettings, name: &str, edited: &std::path::Path) -> Option<String> {
+    let layers = crate::data::parts::catalog_layers(settings);
+    let catalog = crate::data::parts::PartRegistry::from_layers(&layers);
+    let winner = catalog.origin(name).filter(|w| *w != edited)?;
+    let rank = |path: &std::path::Path| layers.iter().position(|l| l == path);
+    Some(match (rank(edited), rank(winner)) {
+        (Some(e), Some(w)) if w > e => format!(
+            "warning: {} also defines '{}' and takes precedence, so this edit has no effect",
+            winner.display(),
+            name
+        ),
+        _ => format!("'{}' is now taken from {}", name, winner.display()),
+    })
+}
+
+
+/// `summary`, followed by any warnings about the layout in `body`.
+fn edit_report(
+    summary: String,
+    body: &str,
+    warnings: &[crate::data::layout_expr::Diagnostic],
+) -> String {
+    let source = body.trim();
+    std::iter::once(summary)
+        .chain(warnings.iter().map(|w| w.render(source)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::infrastructure::executing::ExecutingTmuxBackend;
+    use crate::infrastructure::runner::MockRunner;
+    use crate::service::execute_command;
 
     #[test]
     fn session_list_returns_json_array() {
@@ -288,6 +695,325 @@ mod tests {
         assert_eq!(sys.pending_actions().len(), 1);
     }
 
+    #[test]
+    fn layout_merge_kills_other_panes() {
+        let runner = MockRunner::with_responses(vec![Ok(
+            "%0\t0\t40\t24\t0\t0\t1\n%1\t1\t39\t24\t0\t41\t0\n".into(),
+        )]);
+        let mut sys = Sys::new("/tmp".into());
+        let mut backend = ExecutingTmuxBackend::new(runner);
+        let resp = execute_command(&mut sys, &mut backend, Command::LayoutMerge {
+            session: "work".into(),
+            keep: None,
+            break_out: false,
+        });
+        match resp {
+            Response::Ok { output } => {
+                assert!(output.contains("into pane %0"));
+                assert!(output.contains("killed %1"));
+            }
+            Response::Error { message } => panic!("Unexpected error: {}", message),
+        }
+        assert!(sys.pending_actions().is_empty());
+    }
+
+    #[test]
+    fn layout_merge_queues_a_job() {
+        let mut sys = Sys::new("/tmp".into());
+        sys.execute(Command::LayoutMerge {
+            session: "work".into(),
+            keep: Some("%1".into()),
+            break_out: true,
+        });
+        match sys.take_job() {
+            Some(Job::Merge { session, keep, mode }) => {
+                assert_eq!(session, "work");
+                assert_eq!(keep.as_deref(), Some("%1"));
+                assert_eq!(mode, MergeMode::Break);
+            }
+            other => panic!("expected a merge job, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn layout_merge_unknown_keep_is_error() {
+        let runner = MockRunner::with_responses(vec![Ok("%0\t0\t80\t24\t0\t0\t1\n".into())]);
+        let mut sys = Sys::new("/tmp".into());
+        let mut backend = ExecutingTmuxBackend::new(runner);
+        let resp = execute_command(&mut sys, &mut backend, Command::LayoutMerge {
+            session: "work".into(),
+            keep: Some("%7".into()),
+            break_out: true,
+        });
+        assert!(matches!(resp, Response::Error { .. }));
+    }
+
+    #[test]
+    fn layout_apply_leaves_matching_window_alone() {
+        let listing = "%0\t0\t40\t24\t0\t0\t1\ta\tzsh\t\n%1\t1\t39\t24\t0\t41\t0\tb\tzsh\t\n";
+        let runner = MockRunner::with_responses(vec![Ok(listing.into()), Ok(listing.into())]);
+        let mut sys = Sys::new("/tmp".into());
+        let mut backend = ExecutingTmuxBackend::new(runner);
+        let resp = execute_command(&mut sys, &mut backend, Command::LayoutApply {
+            session: "work".into(),
+            layout: "ROW(a, b)".into(),
+        });
+        match resp {
+            Response::Ok { output } => assert!(output.contains("already matches")),
+            Response::Error { message } => panic!("Unexpected error: {}", message),
+        }
+    }
+
+    #[test]
+    fn layout_apply_refuses_multi_window_parts() {
+        let dir = std::env::temp_dir().join(format!("muxux-sys-win-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join(".mux")).unwrap();
+        std::fs::write(dir.join(".mux/parts.md"), "## studio\nWIN(code: ROW(a, b), ops: c)\n").unwrap();
+        let mut sys = Sys::new(dir.to_string_lossy().into());
+        let resp = sys.execute(Command::LayoutApply {
+            session: "work".into(),
+            layout: "studio".into(),
+        });
+        let _ = std::fs::remove_dir_all(&dir);
+        match resp {
+            Response::Error { message } => assert!(message.contains("has 2 windows")),
+            Response::Ok { output } => panic!("Unexpected success: {}", output),
+        }
+    }
+
+    #[test]
+    fn layout_place_tags_pane() {
+        let runner = MockRunner::with_responses(vec![Ok("%3\t0\t80\t24\t0\t0\t1\t\tzsh\t\n".into())]);
+        let mut sys = Sys::new("/tmp".into());
+        let mut backend = ExecutingTmuxBackend::new(runner);
+        let resp = execute_command(&mut sys, &mut backend, Command::LayoutPlace {
+            pane: "%3".into(),
+            agent: "nobody-by-this-name".into(),
+        });
+        assert!(matches!(resp, Response::Ok { .. }));
+        assert!(sys.pending_actions().is_empty());
+        assert_eq!(
+            backend.runner().executed_commands()[1..],
+            ["tmux set-option -p -t %3 @mux_agent nobody-by-this-name"]
+        );
+    }
+
+    #[test]
+    fn layout_apply_rejects_bad_expression() {
+        let mut sys = Sys::new("/tmp".into());
+        let mut backend = ExecutingTmuxBackend::new(MockRunner::new());
+        let resp = execute_command(&mut sys, &mut backend, Command::LayoutApply {
+            session: "work".into(),
+            layout: "ROW(a".into(),
+        });
+        assert!(matches!(resp, Response::Error { .. }));
+    }
+
+    #[test]
+    fn layout_capture_returns_snapshot_json() {
+        let runner = MockRunner::with_responses(vec![
+            Ok("0:main:1:b25d,80x24,0,0,0\n".into()),
+            Ok("%0\t0\t80\t24\t0\t0\t1\tpm\tclaude\t\n".into()),
+        ]);
+        let mut sys = Sys::new("/tmp".into());
+        let mut backend = ExecutingTmuxBackend::new(runner);
+        let resp = execute_command(&mut sys, &mut backend, Command::LayoutCapture {
+            session: "work".into(),
+        });
+        let output = match resp {
+            Response::Ok { output } => output,
+            other => panic!("expected ok, got {:?}", other),
+        };
+        let snapshot: SessionSnapshot = serde_json::from_str(&output).unwrap();
+        assert_eq!(snapshot.windows.len(), 1);
+        assert_eq!(snapshot.windows[0].name, "main");
+    }
+
+    #[test]
+    fn layout_restore_from_file() {
+        let path = std::env::temp_dir().join(format!("muxux-restore-{}.json", std::process::id()));
+        let snapshot = SessionSnapshot {
+            session: "work".into(),
+            windows: vec![crate::types::session::WindowSnapshot {
+                index: 0,
+                name: "main".into(),
+                layout: crate::types::session::LayoutNode::pane("pm"),
+                tmux_layout: None,
+            }],
+            timestamp_ms: 0,
+        };
+        std::fs::write(&path, serde_json::to_string(&snapshot).unwrap()).unwrap();
+        let runner = MockRunner::with_responses(vec![
+            Ok(String::new()),
+            Ok("0:main:1:\n".into()),
+            Ok("%0\t0\t80\t24\t0\t0\t1\t\tzsh\t\n".into()),
+            Ok("%0\t0\t80\t24\t0\t0\t1\t\tzsh\t\n".into()),
+        ]);
+        let mut sys = Sys::new("/tmp".into());
+        let mut backend = ExecutingTmuxBackend::new(runner);
+        let resp = execute_command(&mut sys, &mut backend, Command::LayoutRestore {
+            session: "work".into(),
+            part: None,
+            file: Some(path.to_string_lossy().into()),
+            at: None,
+        });
+        let _ = std::fs::remove_file(&path);
+        match resp {
+            Response::Ok { output } => {
+                assert!(output.contains("Restored 'work' with 1 windows"));
+                assert!(output.contains("placed pm"));
+            }
+            other => panic!("expected ok, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn layout_capture_feeds_history() {
+        let dir = std::env::temp_dir().join(format!("muxux-sys-history-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let runner = MockRunner::with_responses(vec![
+            Ok("0:main:1:b25d,80x24,0,0,0\n".into()),
+            Ok("%0\t0\t80\t24\t0\t0\t1\tpm\tclaude\t\n".into()),
+            // restore --at -1 into the existing session
+            Ok(String::new()),
+            Ok("0:main:1:\n".into()),
+            Ok("%0\t0\t80\t24\t0\t0\t1\tpm\tclaude\t\n".into()),
+            Ok(String::new()), // select-layout
+            Ok("%0\t0\t80\t24\t0\t0\t1\tpm\tclaude\t\n".into()),
+        ]);
+        let mut sys = Sys::new("/tmp".into());
+        let mut backend = ExecutingTmuxBackend::new(runner);
+        sys.set_config_dir(dir.to_string_lossy().into());
+        execute_command(&mut sys, &mut backend, Command::LayoutCapture {
+            session: "work".into(),
+        });
+        match execute_command(&mut sys, &mut backend, Command::LayoutHistory {
+            session: "work".into(),
+        }) {
+            Response::Ok { output } => assert!(output.contains("main=pm")),
+            other => panic!("expected ok, got {:?}", other),
+        }
+        let resp = execute_command(&mut sys, &mut backend, Command::LayoutRestore {
+            session: "work".into(),
+            part: None,
+            file: None,
+            at: Some("-1".into()),
+        });
+        let _ = std::fs::remove_dir_all(&dir);
+        match resp {
+            Response::Ok { output } => assert!(output.contains("Restored 'work' with 1 windows")),
+            other => panic!("expected ok, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn restore_at_puts_each_window_back_from_its_own_layout() {
+        let dir = std::env::temp_dir().join(format!("muxux-sys-windows-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let editor = "7825,80x24,0,0{20x24,0,0,0,59x24,21,0,1}";
+        let logs = "b25d,80x24,0,0,0";
+        let windows = format!("0:editor:2:{}\n1:logs:1:{}\n", editor, logs);
+        let editor_panes = "%0\t0\t20\t24\t0\t0\t1\tpm\tclaude\t\n%1\t1\t59\t24\t0\t21\t0\tqa\tclaude\t\n";
+        let logs_panes = "%2\t0\t80\t24\t0\t0\t1\ttail\tzsh\t\n";
+        // The scheduler records the session while its second window is active.
+        let runner = MockRunner::with_responses(vec![
+            Ok("work\n".into()),
+            Ok(logs_panes.into()),
+            Ok(windows.clone()),
+            Ok(editor_panes.into()),
+            Ok(logs_panes.into()),
+        ]);
+        let history = HistoryStore::new(&dir);
+        crate::layout::scheduler::SnapshotScheduler::new(5_000, 2, Some(history))
+            .tick(&runner, 10_000);
+
+        let runner = MockRunner::with_responses(vec![
+            Ok(String::new()),
+            Ok(windows),
+            Ok(editor_panes.into()),
+            Ok(String::new()),
+            Ok(editor_panes.into()),
+            Ok(logs_panes.into()),
+            Ok(String::new()),
+            Ok(logs_panes.into()),
+        ]);
+        let mut sys = Sys::new("/tmp".into());
+        sys.set_config_dir(dir.to_string_lossy().into());
+        let mut backend = ExecutingTmuxBackend::new(runner);
+        let resp = execute_command(&mut sys, &mut backend, Command::LayoutRestore {
+            session: "work".into(),
+            part: None,
+            file: None,
+            at: Some("-1".into()),
+        });
+        let _ = std::fs::remove_dir_all(&dir);
+        match resp {
+            Response::Ok { output } => assert!(output.contains("with 2 windows")),
+            other => panic!("expected ok, got {:?}", other),
+        }
+        let cmds = backend.runner().executed_commands();
+        assert_eq!(cmds[3], format!("tmux select-layout -t work:0 '{}'", editor));
+        assert_eq!(cmds[6], format!("tmux select-layout -t work:1 '{}'", logs));
+    }
+
+    #[test]
+    fn layout_history_needs_config_dir() {
+        let mut sys = Sys::new("/tmp".into());
+        let mut backend = ExecutingTmuxBackend::new(MockRunner::new());
+        let resp = execute_command(&mut sys, &mut backend, Command::LayoutHistory {
+            session: "work".into(),
+        });
+        assert!(matches!(resp, Response::Error { .. }));
+    }
+
+    #[test]
+    fn layout_restore_needs_one_source() {
+        let mut sys = Sys::new("/tmp".into());
+        let mut backend = ExecutingTmuxBackend::new(MockRunner::new());
+        let resp = execute_command(&mut sys, &mut backend, Command::LayoutRestore {
+            session: "work".into(),
+            part: None,
+            file: None,
+            at: None,
+        });
+        assert!(matches!(resp, Response::Error { .. }));
+    }
+
+    #[test]
+    fn status_json_reports_daemon_and_captures() {
+        let runner = MockRunner::with_responses(vec![Ok("work:0:%0\nwork:0:%1\n".into())]);
+        let mut sys = Sys::new("/tmp".into());
+        let mut backend = ExecutingTmuxBackend::new(runner);
+        sys.set_daemon_started(0);
+        sys.set_last_captures(HashMap::from([("work".to_string(), 5)]));
+        let output = match execute_command(&mut sys, &mut backend, Command::Status {
+            format: Some("json".into()),
+        }) {
+            Response::Ok { output } => output,
+            other => panic!("expected ok, got {:?}", other),
+        };
+        let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(json["schema_version"], 1);
+        assert_eq!(json["daemon"]["running"], true);
+        assert_eq!(json["daemon"]["pid"], std::process::id());
+        assert_eq!(json["sessions"][0]["panes"], 2);
+        assert_eq!(json["sessions"][0]["last_capture_ms"], 5);
+    }
+
+    #[test]
+    fn status_text_without_daemon() {
+        let runner = MockRunner::with_responses(vec![Err("no server running".into())]);
+        let mut sys = Sys::new("/tmp".into());
+        let mut backend = ExecutingTmuxBackend::new(runner);
+        match execute_command(&mut sys, &mut backend, Command::Status { format: None }) {
+            Response::Ok { output } => {
+                assert!(output.contains("daemon:   not running"));
+                assert!(output.contains("tmux:     server not running"));
+            }
+            other => panic!("expected ok, got {:?}", other),
+        }
+    }
+
     #[test]
     fn client_next_ok() {
         let mut sys = Sys::new("/tmp".into());
@@ -347,4 +1073,100 @@ mod tests {
             Response::Error { message } => panic!("Unexpected error: {}", message),
         }
     }
+
+    #[test]
+    fn parts_show_rejects_zero_sizes() {
+        let mut sys = Sys::new("/tmp".into());
+        let resp = sys.execute(Command::PartsShow {
+            name: "pair".into(),
+            width: Some(0),
+            height: None,
+            ascii: true,
+        });
+        assert!(matches!(resp, Response::Error { .. }));
+    }
+
+    #[test]
+    fn parts_edits_write_the_catalog() {
+        let dir = std::env::temp_dir().join(format!("muxux-sys-parts-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("parts.md");
+        let file = Some(path.to_string_lossy().to_string());
+        let mut sys = Sys::new("/tmp".into());
+        let mut run = |cmd| match sys.execute(cmd) {
+            Response::Ok { output } => Ok(output),
+            Response::Error { message } => Err(message),
+        };
+        let added = run(Command::PartsAdd {
+            name: "pm".into(),
+            body: "role: pm".into(),
+            file: file.clone(),
+        });
+        assert!(added.unwrap().starts_with("Added part 'pm' to "));
+        let added = run(Command::PartsAdd {
+            name: "pair".into(),
+            body: "ROW(pm, helper)".into(),
+            file: file.clone(),
+        });
+        assert!(added.unwrap().contains("'helper' is not a part in the catalog"));
+        let removed = run(Command::PartsRemove {
+            name: "pm".into(),
+            file: file.clone(),
+        });
+        assert_eq!(removed.unwrap_err(), "part 'pm' is used by 'pair'");
+        let renamed = run(Command::PartsRename {
+            name: "pm".into(),
+            to: "lead".into(),
+            file: file.clone(),
+        });
+        assert!(renamed.unwrap().ends_with("(1 layouts updated)"));
+        let saved = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(saved, "## lead\nrole: pm\n\n## pair\nROW(lead, helper)\n");
+    }
+
+    #[test]
+    fn parts_edits_report_the_winning_layer() {
+        let dir = std::env::temp_dir().join(format!("muxux-sys-layers-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join(".mux")).unwrap();
+        let system = dir.join("system.md");
+        let user = dir.join("user.md");
+        let project = dir.join(".mux/parts.md");
+        std::fs::write(&system, "## qa\nrole: qa\n").unwrap();
+        std::fs::write(&user, "## pm\nrole: pm\n\n## pair\nROW(pm, qa)\n").unwrap();
+        std::fs::write(&project, "## pm\nrole: lead\n\n## bad\nROW(pm\n").unwrap();
+        let mut sys = Sys::with_settings(MuxSettings {
+            project_root: dir.to_string_lossy().into(),
+            parts_path: user.to_string_lossy().into(),
+            system_parts_path: system.to_string_lossy().into(),
+            ..MuxSettings::default()
+        });
+        let mut run = |cmd| match sys.execute(cmd) {
+            Response::Ok { output } => Ok(output),
+            Response::Error { message } => Err(message),
+        };
+        let updated = run(Command::PartsUpdate {
+            name: "pm".into(),
+            body: "role: boss".into(),
+            file: None,
+        });
+        let checked = run(Command::PartsCheck { file: None });
+        let removed = run(Command::PartsRemove {
+            name: "pm".into(),
+            file: Some(project.to_string_lossy().into()),
+        });
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let shadowed = format!(
+            "warning: {} also defines 'pm' and takes precedence, so this edit has no effect",
+            project.display()
+        );
+        assert!(updated.unwrap().ends_with(&shadowed));
+        let checked = checked.unwrap_err();
+        assert!(checked.contains(&format!("{}: 0 errors, 0 warnings", system.display())));
+        assert!(checked.contains(&format!("{}: 0 errors, 0 warnings", user.display())));
+        assert!(checked.contains(&format!("{}: 1 errors, 0 warnings", project.display())));
+        assert!(removed.unwrap().ends_with(&format!("'pm' is now taken from {}", user.display())));
+    }
 }
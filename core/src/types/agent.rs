@@ -8,6 +8,38 @@ pub enum AgentType {
     Ssh,
 }
 
+impl AgentType {
+    /// Parse a parts-file `type:` value (`claude`, `console`, `ssh`).
+    pub fn parse(s: &str) -> Option<AgentType> {
+        match s.trim().to_lowercase().as_str() {
+            "claude" => Some(AgentType::Claude),
+            "console" | "shell" => Some(AgentType::Console),
+            "ssh" => Some(AgentType::Ssh),
+            _ => None,
+        }
+    }
+
+    /// The command typed into a fresh pane to start this kind of agent.
+    ///
+    /// Console agents run in the pane's own shell, so there is nothing to
+    /// start. Ssh agents need a host (`user@box` or `user@box:port`).
+    pub fn launch_command(&self, host: Option<&str>) -> Option<String> {
+        match self {
+            AgentType::Claude => Some("claude".into()),
+            AgentType::Console => None,
+            AgentType::Ssh => {
+                let host = host?;
+                match host.rsplit_once(':') {
+                    Some((name, port)) if port.parse::<u16>().is_ok() => {
+                        Some(format!("ssh -p {} {}", port, name))
+                    }
+                    _ => Some(format!("ssh {}", host)),
+                }
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum AgentStatus {
@@ -67,6 +99,29 @@ mod tests {
         assert_eq!(back.health, HealthState::Healthy);
     }
 
+    #[test]
+    fn agent_type_parse() {
+        assert_eq!(AgentType::parse("Claude"), Some(AgentType::Claude));
+        assert_eq!(AgentType::parse("shell"), Some(AgentType::Console));
+        assert_eq!(AgentType::parse("ssh"), Some(AgentType::Ssh));
+        assert_eq!(AgentType::parse("robot"), None);
+    }
+
+    #[test]
+    fn launch_commands() {
+        assert_eq!(AgentType::Claude.launch_command(None).as_deref(), Some("claude"));
+        assert_eq!(AgentType::Console.launch_command(None), None);
+        assert_eq!(AgentType::Ssh.launch_command(None), None);
+        assert_eq!(
+            AgentType::Ssh.launch_command(Some("me@gpu1")).as_deref(),
+            Some("ssh me@gpu1")
+        );
+        assert_eq!(
+            AgentType::Ssh.launch_command(Some("me@gpu1:2222")).as_deref(),
+            Some("ssh -p 2222 me@gpu1")
+        );
+    }
+
     #[test]
     fn health_state_serde() {
         let json = serde_json::to_string(&HealthState::Degraded).unwrap();
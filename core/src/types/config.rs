@@ -1,7 +1,13 @@
+use std::path::Path;
+
 use serde::{Deserialize, Serialize};
 
+/// Settings file read from the config directory by `MuxSettings::load`.
+pub const SETTINGS_FILE: &str = "settings.json";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MuxSettings {
+    #[serde(default)]
     pub project_root: String,
     /// Maximum width (in px) for overlay zone containers. Default: 160.
     #[serde(default = "default_zone_max_width")]
@@ -9,6 +15,27 @@ pub struct MuxSettings {
     /// Maximum rows in the Spotlight-style search dropdown. Default: 10.
     #[serde(default = "default_search_max_rows")]
     pub search_max_rows: u32,
+    /// Directory for daemon state such as layout history. Empty disables
+    /// history.
+    #[serde(default)]
+    pub config_dir: String,
+    /// How often the daemon captures each session's layout, in ms. 0
+    /// disables background snapshots. Default: 5000.
+    #[serde(default = "default_snapshot_interval_ms")]
+    pub snapshot_interval_ms: u64,
+    /// Share changes of up to this many percentage points are rounding
+    /// noise, not layout changes, for watch events and history. Default: 2.
+    #[serde(default = "default_layout_tolerance_percent")]
+    pub layout_tolerance_percent: u32,
+    /// The user's parts catalog. Empty falls back to `$MUX_PARTS_PATH`,
+    /// then `~/.config/skd/skd-library/parts.md`.
+    #[serde(default)]
+    pub parts_path: String,
+    /// A parts catalog shared by every user, layered under the user's.
+    /// Empty falls back to `$MUX_SYSTEM_PARTS_PATH`, then
+    /// `/etc/muxux/parts.md`.
+    #[serde(default)]
+    pub system_parts_path: String,
 }
 
 fn default_zone_max_width() -> u32 {
@@ -19,12 +46,62 @@ fn default_search_max_rows() -> u32 {
     10
 }
 
+fn default_snapshot_interval_ms() -> u64 {
+    5_000
+}
+
+fn default_layout_tolerance_percent() -> u32 {
+    2
+}
+
 impl Default for MuxSettings {
     fn default() -> Self {
         MuxSettings {
             project_root: String::new(),
             zone_max_width: default_zone_max_width(),
             search_max_rows: default_search_max_rows(),
+            config_dir: String::new(),
+            snapshot_interval_ms: default_snapshot_interval_ms(),
+            layout_tolerance_percent: default_layout_tolerance_percent(),
+            parts_path: String::new(),
+            system_parts_path: String::new(),
         }
     }
 }
+
+impl MuxSettings {
+    /// The settings in `config_dir/settings.json`, with defaults for any
+    /// key it leaves out. A missing file gives the defaults.
+    pub fn load(config_dir: &Path) -> Result<MuxSettings, String> {
+        let path = config_dir.join(SETTINGS_FILE);
+        match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| format!("Invalid settings in {}: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(MuxSettings::default()),
+            Err(e) => Err(format!("Cannot read {}: {}", path.display(), e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_fills_in_defaults() {
+        let dir = std::env::temp_dir().join(format!("muxux-settings-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let missing = MuxSettings::load(&dir).unwrap();
+        std::fs::write(dir.join(SETTINGS_FILE), r#"{"parts_path": "/srv/parts.md"}"#).unwrap();
+        let loaded = MuxSettings::load(&dir);
+        std::fs::write(dir.join(SETTINGS_FILE), "{").unwrap();
+        let broken = MuxSettings::load(&dir);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(missing.snapshot_interval_ms, 5_000);
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.parts_path, "/srv/parts.md");
+        assert_eq!(loaded.search_max_rows, 10);
+        assert!(broken.unwrap_err().starts_with("Invalid settings in "));
+    }
+}
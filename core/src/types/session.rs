@@ -11,6 +11,9 @@ pub struct TmuxWindow {
     pub index: u32,
     pub name: String,
     pub panes: Vec<TmuxPane>,
+    /// tmux's own `#{window_layout}` string, checksum included.
+    #[serde(default)]
+    pub layout: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,6 +25,45 @@ pub struct TmuxPane {
     pub top: u32,
     pub left: u32,
     pub agent: Option<String>,
+    /// Whether this is the active pane of its window.
+    #[serde(default)]
+    pub active: bool,
+    /// Pane title as set by the program running in it.
+    #[serde(default)]
+    pub title: String,
+    /// Name of the pane's foreground command (e.g. `zsh`, `claude`).
+    #[serde(default)]
+    pub command: String,
+}
+
+/// Foreground commands that mean a pane is sitting at a prompt.
+pub const SHELLS: &[&str] = &["bash", "zsh", "sh", "fish", "dash", "ksh", "tcsh"];
+
+impl TmuxPane {
+    /// Whether the pane's foreground command is a shell.
+    pub fn at_shell(&self) -> bool {
+        SHELLS.contains(&self.command.as_str())
+    }
+}
+
+/// A saved session: every window's name, index, and layout (agents included).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionSnapshot {
+    pub session: String,
+    pub windows: Vec<WindowSnapshot>,
+    #[serde(default)]
+    pub timestamp_ms: u64,
+}
+
+/// One window of a `SessionSnapshot`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WindowSnapshot {
+    pub index: u32,
+    pub name: String,
+    pub layout: LayoutNode,
+    /// Exact tmux layout string when captured from a live window.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tmux_layout: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -29,13 +71,95 @@ pub struct TmuxPane {
 pub enum LayoutNode {
     Row { children: Vec<LayoutEntry> },
     Col { children: Vec<LayoutEntry> },
-    Pane { agent: String },
+    Pane {
+        agent: String,
+        /// How the pane is started and shown (`worker[cmd="make", focus]`).
+        #[serde(default, skip_serializing_if = "PaneOptions::is_empty")]
+        options: PaneOptions,
+    },
+}
+
+impl LayoutNode {
+    /// A leaf for `agent` with no pane options.
+    pub fn pane(agent: impl Into<String>) -> LayoutNode {
+        LayoutNode::Pane {
+            agent: agent.into(),
+            options: PaneOptions::default(),
+        }
+    }
+
+    /// Every leaf's options, left to right and top to bottom.
+    pub fn pane_options(&self) -> Vec<&PaneOptions> {
+        match self {
+            LayoutNode::Pane { options, .. } => vec![options],
+            LayoutNode::Row { children } | LayoutNode::Col { children } => {
+                children.iter().flat_map(|e| e.node.pane_options()).collect()
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct LayoutEntry {
     pub node: LayoutNode,
     pub percent: Option<u32>,
+    /// Absolute size and min/max constraints, on top of `percent`.
+    #[serde(default, skip_serializing_if = "SizeSpec::is_unset")]
+    pub size: SizeSpec,
+}
+
+/// Per-pane settings from a layout expression's `[...]` attributes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PaneOptions {
+    /// Command typed into the pane instead of the agent's own launch command.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cmd: Option<String>,
+    /// Directory the pane changes to before running anything.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Whether this pane is the one selected once the layout is in place.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub focus: bool,
+}
+
+impl PaneOptions {
+    /// No attributes at all.
+    pub fn is_empty(&self) -> bool {
+        self.cmd.is_none() && self.cwd.is_none() && self.title.is_none() && !self.focus
+    }
+}
+
+/// Sizes in cells along the parent's split direction: columns inside a
+/// ROW, rows inside a COL. `layout::solver` turns them into split sizes
+/// for a concrete window.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct SizeSpec {
+    /// Exact size (`sidebar 40c`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cells: Option<u32>,
+    /// Lower bound (`logs >=10r`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<u32>,
+    /// Upper bound (`chat <=80c`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<u32>,
+    /// Written as `*` (`editor *`): takes whatever is left, like no size.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub fill: bool,
+}
+
+impl SizeSpec {
+    /// No absolute size and no constraints.
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_none() && self.min.is_none() && self.max.is_none()
+    }
+
+    /// Nothing was written at all, not even `*`.
+    pub fn is_unset(&self) -> bool {
+        self.is_empty() && !self.fill
+    }
 }
 
 #[cfg(test)]
@@ -57,7 +181,11 @@ mod tests {
                     top: 0,
                     left: 0,
                     agent: Some("worker-1".into()),
+                    active: true,
+                    title: "worker-1".into(),
+                    command: "claude".into(),
                 }],
+                layout: "b25d,80x24,0,0,0".into(),
             }],
         };
         let json = serde_json::to_string(&session).unwrap();
@@ -71,18 +199,37 @@ mod tests {
         let layout = LayoutNode::Row {
             children: vec![
                 LayoutEntry {
-                    node: LayoutNode::Pane { agent: "pilot".into() },
+                    node: LayoutNode::pane("pilot"),
                     percent: Some(30),
+                    size: SizeSpec::default(),
                 },
                 LayoutEntry {
-                    node: LayoutNode::Pane { agent: "worker-1".into() },
+                    node: LayoutNode::pane("worker-1"),
                     percent: Some(70),
+                    size: SizeSpec::default(),
                 },
             ],
         };
         let json = serde_json::to_string(&layout).unwrap();
         assert!(json.contains("\"type\":\"row\""));
+        assert!(!json.contains("size"));
         let back: LayoutNode = serde_json::from_str(&json).unwrap();
         assert_eq!(back, layout);
     }
+
+    #[test]
+    fn size_spec_round_trip() {
+        let entry = LayoutEntry {
+            node: LayoutNode::pane("logs"),
+            percent: None,
+            size: SizeSpec {
+                min: Some(10),
+                ..SizeSpec::default()
+            },
+        };
+        let json = serde_json::to_string(&entry).unwrap();
+        assert!(json.contains("\"size\":{\"min\":10}"));
+        let back: LayoutEntry = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, entry);
+    }
 }
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
-use super::session::LayoutNode;
+use super::agent::AgentType;
+use super::session::{LayoutNode, WindowSnapshot};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
@@ -16,12 +17,28 @@ pub struct Tile {
     pub kind: TileKind,
     pub role: Option<String>,
     pub layout: Option<LayoutNode>,
+    /// How an agent part is started; `None` leaves the pane's shell as is.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_type: Option<AgentType>,
+    /// Remote host for ssh agents (`user@box` or `user@box:port`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// Windows of a `WIN(...)` session part; `layout` is the first one's.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub windows: Vec<WindowSnapshot>,
+}
+
+impl Tile {
+    /// The command that starts this part's agent in a fresh pane, if any.
+    pub fn launch_command(&self) -> Option<String> {
+        self.agent_type.as_ref()?.launch_command(self.host.as_deref())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::session::LayoutEntry;
+    use crate::types::session::{LayoutEntry, SizeSpec};
 
     #[test]
     fn tile_agent_round_trip() {
@@ -30,6 +47,9 @@ mod tests {
             kind: TileKind::Agent,
             role: Some("pilot".into()),
             layout: None,
+            agent_type: Some(AgentType::Claude),
+            host: None,
+            windows: Vec::new(),
         };
         let json = serde_json::to_string(&tile).unwrap();
         let back: Tile = serde_json::from_str(&json).unwrap();
@@ -45,26 +65,33 @@ mod tests {
             layout: Some(LayoutNode::Row {
                 children: vec![
                     LayoutEntry {
-                        node: LayoutNode::Pane { agent: "pilot".into() },
+                        node: LayoutNode::pane("pilot"),
                         percent: Some(30),
+                        size: SizeSpec::default(),
                     },
                     LayoutEntry {
                         node: LayoutNode::Col {
                             children: vec![
                                 LayoutEntry {
-                                    node: LayoutNode::Pane { agent: "worker-1".into() },
+                                    node: LayoutNode::pane("worker-1"),
                                     percent: Some(50),
+                                    size: SizeSpec::default(),
                                 },
                                 LayoutEntry {
-                                    node: LayoutNode::Pane { agent: "worker-2".into() },
+                                    node: LayoutNode::pane("worker-2"),
                                     percent: Some(50),
+                                    size: SizeSpec::default(),
                                 },
                             ],
                         },
                         percent: Some(70),
+                        size: SizeSpec::default(),
                     },
                 ],
             }),
+            agent_type: None,
+            host: None,
+            windows: Vec::new(),
         };
         let json = serde_json::to_string(&tile).unwrap();
         assert!(json.contains("\"kind\":\"composition\""));
@@ -72,6 +99,14 @@ mod tests {
         assert_eq!(back, tile);
     }
 
+    #[test]
+    fn tile_without_agent_type_deserializes() {
+        let json = r#"{"name":"pm","kind":"agent","role":"pm","layout":null}"#;
+        let tile: Tile = serde_json::from_str(json).unwrap();
+        assert_eq!(tile.agent_type, None);
+        assert_eq!(tile.launch_command(), None);
+    }
+
     #[test]
     fn tile_kind_serde() {
         let json = serde_json::to_string(&TileKind::Session).unwrap();
@@ -0,0 +1,317 @@
+//! `mux view <name>` — look a name up as a session, an agent, or a part.
+//!
+//! Live tmux sessions win, then agents (a tagged pane or an agent part),
+//! then compositions and session parts. Sessions print each window's layout
+//! expression and panes, agents print where they run, and parts print their
+//! expanded tree. With a diagram requested, layouts are also drawn as boxes.
+
+use crate::data::layout_expr::{serialize_layout_expr, serialize_windows_expr};
+use crate::data::parts::PartRegistry;
+use crate::infrastructure::runner::CommandRunner;
+use crate::infrastructure::tmux::{
+    parse_list_panes, parse_list_sessions, parse_list_windows, TmuxCommandBuilder,
+};
+use crate::layout::capture::{capture_session, capture_window};
+use crate::layout::preview::{render, render_tree, BoxStyle};
+use crate::types::session::{LayoutNode, SHELLS};
+use crate::types::tiles::TileKind;
+
+
+/// Size of the box diagram, in characters.
+pub const DIAGRAM_WIDTH: usize = 48;
+pub const DIAGRAM_HEIGHT: usize = 12;
+
+
+/// A pane carrying a `@mux_agent` tag, from `list_agent_panes`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgentPane {
+    pub pane_id: String,
+    pub agent: String,
+    pub command: String,
+    pub window: u32,
+    pub session: String,
+}
+
+
+/// Parse `list_agent_panes` output, keeping only tagged panes.
+pub fn parse_agent_panes(output: &str) -> Vec<AgentPane> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.trim_end_matches('\r').splitn(5, '\t').collect();
+            if fields.len() < 5 || fields[1].is_empty() {
+                return None;
+            }
+            Some(AgentPane {
+                pane_id: fields[0].to_string(),
+                agent: fields[1].to_string(),
+                command: fields[2].to_string(),
+                window: fields[3].parse().unwrap_or(0),
+                session: fields[4].to_string(),
+            })
+        })
+        .collect()
+}
+
+
+/// Describe `name` through `runner` and `registry`.
+pub fn view(
+    runner: &dyn CommandRunner,
+    registry: &PartRegistry,
+    name: &str,
+    diagram: bool,
+) -> Result<String, String> {
+    let builder = TmuxCommandBuilder::new();
+    let sessions = runner
+        .run(&builder.list_sessions())
+        .map(|out| parse_list_sessions(&out))
+        .unwrap_or_default();
+    if sessions.iter().any(|s| s == name) {
+        return view_session(runner, name, diagram);
+    }
+
+    let agent_panes: Vec<AgentPane> = runner
+        .run(&builder.list_agent_panes())
+        .map(|out| parse_agent_panes(&out))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|p| p.agent == name)
+        .collect();
+    let part = registry.get(name);
+    if !agent_panes.is_empty() || part.is_some_and(|t| t.kind == TileKind::Agent) {
+        return Ok(view_agent(registry, name, &agent_panes));
+    }
+
+    if let Some(tile) = part {
+        let windows = registry
+            .expand_windows(name)
+            .ok_or_else(|| format!("part '{}' has no layout", name))?;
+        let kind = match tile.kind {
+            TileKind::Session => "session",
+            _ => "composition",
+        };
+        let mut out = vec![format!("Part '{}' ({})", name, kind)];
+        for window in &windows {
+            if !window.name.is_empty() {
+                out.push(format!("window {} '{}':", window.index, window.name));
+            }
+            out.push(render_tree(&window.layout));
+            if diagram {
+                out.push(render(&window.layout, DIAGRAM_WIDTH, DIAGRAM_HEIGHT, BoxStyle::Unicode));
+            }
+        }
+        return Ok(out.join("\n"));
+    }
+
+    Err(format!("'{}' is not a tmux session, agent, or part", name))
+}
+
+
+/// `mux parts show` — a part's layout expression and its box diagram.
+pub fn show_part(
+    registry: &PartRegistry,
+    name: &str,
+    width: usize,
+    height: usize,
+    style: BoxStyle,
+) -> Result<String, String> {
+    let tile = registry
+        .get(name)
+        .ok_or_else(|| format!("Part '{}' not found in catalog", name))?;
+    let windows = registry
+        .expand_windows(name)
+        .ok_or_else(|| format!("part '{}' has no layout", name))?;
+    let kind = match tile.kind {
+        TileKind::Agent => "agent",
+        TileKind::Composition => "composition",
+        TileKind::Session => "session",
+    };
+    let mut out = vec![
+        format!("Part '{}' ({})", name, kind),
+        serialize_windows_expr(&windows),
+    ];
+    for window in &windows {
+        if !window.name.is_empty() {
+            out.push(format!("window {} '{}':", window.index, window.name));
+        }
+        out.push(render(&window.layout, width, height, style));
+    }
+    Ok(out.join("\n"))
+}
+
+
+fn view_session(
+    runner: &dyn CommandRunner,
+    session: &str,
+    diagram: bool,
+) -> Result<String, String> {
+    let builder = TmuxCommandBuilder::new();
+    let windows = runner
+        .run(&builder.list_windows(session))
+        .map(|out| parse_list_windows(&out))
+        .map_err(|e| format!("cannot list windows of '{}': {}", session, e.trim()))?;
+    let mut out = vec![format!("Session '{}' ({} windows)", session, windows.len())];
+    for window in windows {
+        let target = format!("{}:{}", session, window.index);
+        let pane_output = runner
+            .run(&builder.list_panes(&target))
+            .map_err(|e| format!("cannot list panes of '{}': {}", target, e.trim()))?;
+        let capture = if window.layout.is_empty() {
+            capture_session(&target, &pane_output, None, 0, 0)
+        } else {
+            capture_window(&target, &window.layout, &pane_output, None, 0, 0)
+        };
+        let layout: Option<LayoutNode> = capture.ok().map(|c| c.layout);
+        out.push(format!(
+            "window {} '{}': {}",
+            window.index,
+            window.name,
+            layout.as_ref().map(serialize_layout_expr).unwrap_or_else(|| "?".into())
+        ));
+        for pane in parse_list_panes(&pane_output) {
+            out.push(format!(
+                "  {:<5} {:>3}x{:<3} {:<12} {}{}",
+                pane.id,
+                pane.width,
+                pane.height,
+                pane.agent.as_deref().unwrap_or("-"),
+                pane.command,
+                if pane.active { "  (active)" } else { "" }
+            ));
+        }
+        if let (true, Some(layout)) = (diagram, layout) {
+            out.push(render(&layout, DIAGRAM_WIDTH, DIAGRAM_HEIGHT, BoxStyle::Unicode));
+        }
+    }
+    Ok(out.join("\n"))
+}
+
+
+fn view_agent(registry: &PartRegistry, name: &str, panes: &[AgentPane]) -> String {
+    let kind = registry
+        .get(name)
+        .and_then(|t| t.agent_type.as_ref())
+        .and_then(|t| serde_json::to_value(t).ok())
+        .and_then(|v| v.as_str().map(|s| s.to_string()));
+    let mut out = vec![match kind {
+        Some(kind) => format!("Agent '{}' ({})", name, kind),
+        None => format!("Agent '{}'", name),
+    }];
+    if panes.is_empty() {
+        out.push("  not placed in any pane".into());
+    }
+    for pane in panes {
+        let status = if SHELLS.contains(&pane.command.as_str()) {
+            "idle"
+        } else {
+            "running"
+        };
+        out.push(format!(
+            "  pane {} in {}:{} — {} ({})",
+            pane.pane_id, pane.session, pane.window, status, pane.command
+        ));
+    }
+    out.join("\n")
+}
+
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::runner::MockRunner;
+
+    const PARTS: &str = "## pm\nrole: pm\ntype: claude\n\n## pair\nROW(pm 40%, pm 60%)\n";
+
+    #[test]
+    fn parse_agent_panes_keeps_tagged() {
+        let panes = parse_agent_panes("%0\tpm:lead\tclaude\t1\tmy:work\n%1\t\tzsh\t0\twork\n");
+        assert_eq!(
+            panes,
+            vec![AgentPane {
+                pane_id: "%0".into(),
+                agent: "pm:lead".into(),
+                command: "claude".into(),
+                window: 1,
+                session: "my:work".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn session_shows_windows_and_panes() {
+        let runner = MockRunner::with_responses(vec![
+            Ok("work\n".into()),
+            Ok("0:main:2:7825,80x24,0,0{20x24,0,0,0,59x24,21,0,1}\n".into()),
+            Ok("%0\t0\t20\t24\t0\t0\t1\tpm\tclaude\t\n%1\t1\t59\t24\t0\t21\t0\t\tzsh\t\n".into()),
+        ]);
+        let text = view(&runner, &PartRegistry::default(), "work", false).unwrap();
+        assert!(text.starts_with("Session 'work' (1 windows)"));
+        assert!(text.contains("window 0 'main': ROW("));
+        assert!(text.contains("pm           claude  (active)"));
+    }
+
+    #[test]
+    fn agent_shows_pane_and_status() {
+        let runner = MockRunner::with_responses(vec![
+            Ok("work\n".into()),
+            Ok("%3\tpm\tclaude\t0\twork\n%4\tpm\tzsh\t1\twork\n".into()),
+        ]);
+        let registry = PartRegistry::from_markdown(PARTS);
+        let text = view(&runner, &registry, "pm", false).unwrap();
+        assert!(text.starts_with("Agent 'pm' (claude)"));
+        assert!(text.contains("pane %3 in work:0 — running (claude)"));
+        assert!(text.contains("pane %4 in work:1 — idle (zsh)"));
+    }
+
+    #[test]
+    fn unplaced_agent_part() {
+        let runner = MockRunner::new();
+        let registry = PartRegistry::from_markdown(PARTS);
+        let text = view(&runner, &registry, "pm", false).unwrap();
+        assert!(text.contains("not placed in any pane"));
+    }
+
+    #[test]
+    fn part_shows_tree_and_diagram() {
+        let runner = MockRunner::new();
+        let registry = PartRegistry::from_markdown(PARTS);
+        let text = view(&runner, &registry, "pair", true).unwrap();
+        assert!(text.starts_with("Part 'pair' (composition)\nROW\n├─ 40% pm\n└─ 60% pm"));
+        assert!(text
+            .lines()
+            .any(|l| l.starts_with('┌') && l.chars().count() == DIAGRAM_WIDTH));
+    }
+
+    #[test]
+    fn show_part_draws_expression_and_boxes() {
+        let registry = PartRegistry::from_markdown(PARTS);
+        let text = show_part(&registry, "pair", 11, 3, BoxStyle::Ascii).unwrap();
+        assert_eq!(
+            text,
+            "Part 'pair' (composition)\nROW(pm 40%, pm 60%)\n+---+-----+\n|pm | pm  |\n+---+-----+"
+        );
+        assert!(show_part(&registry, "nope", 11, 3, BoxStyle::Ascii).is_err());
+    }
+
+    #[test]
+    fn win_parts_show_every_window() {
+        let parts = format!("{}\n## studio\nWIN(code: pair, ops: pm)\n", PARTS);
+        let registry = PartRegistry::from_markdown(&parts);
+        let text = view(&MockRunner::new(), &registry, "studio", false).unwrap();
+        assert!(text.contains("window 0 'code':\nROW\n"));
+        assert!(text.ends_with("window 1 'ops':\npm"));
+        let text = show_part(&registry, "studio", 11, 3, BoxStyle::Ascii).unwrap();
+        assert!(text.contains("WIN(code: ROW(pm 40%, pm 60%), ops: pm)"));
+        assert!(text.ends_with("window 1 'ops':\n+---------+\n|   pm    |\n+---------+"));
+    }
+
+    #[test]
+    fn unknown_name_is_an_error() {
+        let runner = MockRunner::new();
+        assert!(view(&runner, &PartRegistry::default(), "nope", false).is_err());
+    }
+}
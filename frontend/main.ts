@@ -448,8 +448,8 @@ async function fetchSessionNames(): Promise<string[]> {
 // ---------------------------------------------------------------------------
 interface PartsCatalog {
   agents: { name: string; role?: string }[];
-  compositions: { name: string }[];
-  sessions: { name: string }[];
+  compositions: { name: string; preview?: string }[];
+  sessions: { name: string; preview?: string }[];
 }
 
 async function fetchPartsCatalog(): Promise<PartsCatalog | null> {
@@ -478,7 +478,7 @@ function populateCatalogZone(catalog: PartsCatalog): void {
     const subPanel = menuItem.querySelector<HTMLDivElement>(".sub-panel");
     if (!subPanel) return;
 
-    let items: { name: string }[] = [];
+    let items: { name: string; preview?: string }[] = [];
     if (label.startsWith("Agents")) {
       items = catalog.agents;
     } else if (label.startsWith("Compositions")) {
@@ -507,6 +507,13 @@ function populateCatalogZone(catalog: PartsCatalog): void {
       childEl.textContent = part.name;
       childEl.dataset.action = "parts.place";
       childEl.dataset.param = part.name;
+      // Box diagram of the part's layout, shown while hovering
+      if (part.preview) {
+        const preview = document.createElement("pre");
+        preview.className = "part-preview";
+        preview.textContent = part.preview;
+        childEl.appendChild(preview);
+      }
       childEl.addEventListener("click", (e: MouseEvent) => {
         e.stopPropagation();
         handleItemClick(childEl);
@@ -540,11 +547,11 @@ function collectSearchableItems(): SearchableItem[] {
       });
     });
 
-    // Also collect sub-items
+    // Also collect sub-items (first text node only, skipping any preview)
     const subItems = zone.querySelectorAll<HTMLDivElement>(".sub-item");
     subItems.forEach((el) => {
       items.push({
-        text: el.textContent?.trim() || "",
+        text: el.childNodes[0]?.textContent?.trim() || "",
         action: el.dataset.action || "",
         param: el.dataset.param,
         category,
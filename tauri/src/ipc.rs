@@ -128,10 +128,12 @@ pub fn mux_layout_merge(
     state: State<'_, AppState>,
     overlay: State<'_, crate::OverlayState>,
     session: String,
+    keep: Option<String>,
+    break_out: Option<bool>,
 ) -> IpcResponse {
     let target = resolve_target(&session, &overlay);
     eprintln!("[muxux-ipc] mux_layout_merge: target={}", target);
-    let resp = to_ipc(state.layout_merge(target));
+    let resp = to_ipc(state.layout_merge(target, keep, break_out.unwrap_or(false)));
     state.run_pending_actions();
     resp
 }
@@ -152,6 +154,17 @@ pub fn mux_layout_capture(state: State<'_, AppState>, session: String) -> IpcRes
     to_ipc(state.layout_capture(session))
 }
 
+#[tauri::command]
+pub fn mux_layout_restore(
+    state: State<'_, AppState>,
+    session: String,
+    part: Option<String>,
+    file: Option<String>,
+    at: Option<String>,
+) -> IpcResponse {
+    to_ipc(state.layout_restore(session, part, file, at))
+}
+
 #[tauri::command]
 pub fn mux_layout_session(
     state: State<'_, AppState>,
@@ -305,6 +318,16 @@ pub fn mux_parts_list(state: State<'_, AppState>) -> IpcResponse {
     to_ipc(state.parts_list())
 }
 
+#[tauri::command]
+pub fn mux_parts_show(
+    state: State<'_, AppState>,
+    part: String,
+    width: Option<u16>,
+    height: Option<u16>,
+) -> IpcResponse {
+    to_ipc(state.parts_show(&part, width, height))
+}
+
 #[tauri::command]
 pub fn mux_parts_place(
     state: State<'_, AppState>,
@@ -316,6 +339,26 @@ pub fn mux_parts_place(
     to_ipc(state.parts_place(&pane, &part))
 }
 
+#[tauri::command]
+pub fn mux_parts_add(state: State<'_, AppState>, part: String, body: String) -> IpcResponse {
+    to_ipc(state.parts_add(&part, &body))
+}
+
+#[tauri::command]
+pub fn mux_parts_update(state: State<'_, AppState>, part: String, body: String) -> IpcResponse {
+    to_ipc(state.parts_update(&part, &body))
+}
+
+#[tauri::command]
+pub fn mux_parts_rm(state: State<'_, AppState>, part: String) -> IpcResponse {
+    to_ipc(state.parts_remove(&part))
+}
+
+#[tauri::command]
+pub fn mux_parts_mv(state: State<'_, AppState>, part: String, to: String) -> IpcResponse {
+    to_ipc(state.parts_rename(&part, &to))
+}
+
 // ---------------------------------------------------------------------------
 // Overlay commands
 // ---------------------------------------------------------------------------
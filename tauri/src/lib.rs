@@ -23,37 +23,19 @@ pub mod ipc;
 pub const OVERLAY_SIZE: i32 = 700;
 
 use muxux_core::command::Command;
+use muxux_core::event::now_ms;
 use muxux_core::sys::Sys;
-use muxux_core::infrastructure::tmux::{TmuxBackend, TmuxCommandBuilder, realize_layout};
+use muxux_core::infrastructure::executing::run_actions;
+use muxux_core::job::Job;
+use muxux_core::infrastructure::tmux::TmuxCommandBuilder;
 use muxux_core::infrastructure::runner::{ShellRunner, CommandRunner};
-use muxux_core::infrastructure::SessionBackend;
-use muxux_core::types::session::{LayoutNode, LayoutEntry};
+use muxux_core::types::config::MuxSettings;
+use muxux_core::types::session::{LayoutNode, LayoutEntry, SizeSpec};
 use cmx_utils::response::{Action, Response};
 use std::sync::Mutex;
 use tauri::Manager;
 
 
-/// Get home directory.
-fn dirs_next_home() -> Option<std::path::PathBuf> {
-    std::env::var("HOME").ok().map(std::path::PathBuf::from)
-}
-
-/// Append text to a file, creating it and parent directories if needed.
-fn append_to_file(path: &std::path::Path, content: &str) -> Result<(), String> {
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent)
-            .map_err(|e| format!("cannot create directory: {}", e))?;
-    }
-    use std::io::Write;
-    let mut file = std::fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(path)
-        .map_err(|e| format!("cannot open {}: {}", path.display(), e))?;
-    file.write_all(content.as_bytes())
-        .map_err(|e| format!("write error: {}", e))
-}
-
 /// Menu item IDs used by the tray icon menu.
 ///
 /// Exposed as constants so they can be tested and referenced consistently.
@@ -265,26 +247,47 @@ impl OverlayArgs {
 /// can safely access it from arbitrary threads.
 pub struct AppState {
     sys: Mutex<Sys>,
+    /// Runs the jobs `Sys` queues for commands that read tmux as they go.
+    runner: Mutex<Box<dyn CommandRunner>>,
 }
 
 
 impl AppState {
     /// Create a new AppState with the given project root.
     pub fn new(project_root: String) -> AppState {
-        let sys = Sys::new(project_root);
+        AppState::with_runner(project_root, Box::new(ShellRunner))
+    }
+
+    /// Create an AppState whose queued jobs talk to tmux through `runner`.
+    pub fn with_runner(project_root: String, runner: Box<dyn CommandRunner>) -> AppState {
         AppState {
-            sys: Mutex::new(sys),
+            sys: Mutex::new(Sys::new(project_root)),
+            runner: Mutex::new(runner),
         }
     }
 
-    /// Execute an arbitrary Command through the core runtime.
+    /// Create an AppState configured by `settings`, e.g. from
+    /// `MuxSettings::load`.
+    pub fn with_settings(settings: MuxSettings) -> AppState {
+        AppState {
+            sys: Mutex::new(Sys::with_settings(settings)),
+            runner: Mutex::new(Box::new(ShellRunner)),
+        }
+    }
+
+    /// Execute an arbitrary Command through the core runtime, running any
+    /// job it queues.
     pub fn execute(&self, cmd: Command) -> Response {
         let mut sys = self.sys.lock().unwrap();
-        sys.execute(cmd)
+        let response = sys.execute(cmd);
+        match sys.take_job() {
+            Some(job) => job.run(self.runner.lock().unwrap().as_ref()),
+            None => response,
+        }
     }
 
-    /// Drain pending actions from the last execute() call, convert them to
-    /// tmux commands via TmuxBackend, and run each via ShellRunner.
+    /// Drain pending actions from the last execute() call and run them
+    /// through the state's runner.
     ///
     /// Call this after any execute() that may emit Actions (layout ops).
     pub fn run_pending_actions(&self) {
@@ -292,22 +295,18 @@ impl AppState {
         if actions.is_empty() {
             return;
         }
-        let mut backend = TmuxBackend::new();
-        let runner = ShellRunner;
-        for action in &actions {
-            let _ = backend.execute_action(action);
-        }
-        for tmux_cmd in backend.drain_commands() {
-            match runner.run(&tmux_cmd) {
-                Ok(_) => eprintln!("[muxux] ran: {}", tmux_cmd),
-                Err(e) => eprintln!("[muxux] tmux error: {} (cmd: {})", e, tmux_cmd),
+        let runner = self.runner.lock().unwrap();
+        for outcome in run_actions(runner.as_ref(), &actions) {
+            match outcome.result {
+                Ok(_) => eprintln!("[muxux] ran: {}", outcome.command),
+                Err(e) => eprintln!("[muxux] tmux error: {} (cmd: {})", e, outcome.command),
             }
         }
     }
 
     /// Run a raw tmux command string and return the result.
     pub fn run_tmux(&self, cmd: &str) -> Result<String, String> {
-        ShellRunner.run(cmd)
+        self.runner.lock().unwrap().run(cmd)
     }
 
     /// Return the pending actions from the last execute() call.
@@ -350,7 +349,10 @@ impl AppState {
     }
 
     pub fn view(&self, name: String) -> Response {
-        self.execute(Command::View { name })
+        self.execute(Command::View {
+            name,
+            diagram: false,
+        })
     }
 
     pub fn help(&self, topic: Option<String>) -> Response {
@@ -369,8 +371,8 @@ impl AppState {
         self.execute(Command::LayoutColumn { session, percent })
     }
 
-    pub fn layout_merge(&self, session: String) -> Response {
-        self.execute(Command::LayoutMerge { session })
+    pub fn layout_merge(&self, session: String, keep: Option<String>, break_out: bool) -> Response {
+        self.execute(Command::LayoutMerge { session, keep, break_out })
     }
 
     pub fn layout_place(&self, pane: String, agent: String) -> Response {
@@ -381,6 +383,16 @@ impl AppState {
         self.execute(Command::LayoutCapture { session })
     }
 
+    pub fn layout_restore(
+        &self,
+        session: String,
+        part: Option<String>,
+        file: Option<String>,
+        at: Option<String>,
+    ) -> Response {
+        self.execute(Command::LayoutRestore { session, part, file, at })
+    }
+
     pub fn layout_session(&self, name: String, cwd: Option<String>) -> Response {
         self.execute(Command::LayoutSession { name, cwd })
     }
@@ -494,15 +506,10 @@ impl AppState {
     pub fn layout_capture_live(&self, session: &str) -> Response {
         let builder = TmuxCommandBuilder::new();
         let list_cmd = builder.list_panes(session);
-        let runner = ShellRunner;
-        match runner.run(&list_cmd) {
+        match self.run_tmux(&list_cmd) {
             Ok(output) => {
                 use muxux_core::layout::capture::capture_session;
-                let now_ms = std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .map(|d| d.as_millis() as u64)
-                    .unwrap_or(0);
-                match capture_session(session, &output, None, now_ms) {
+                match capture_session(session, &output, None, 0, now_ms()) {
                     Ok(result) => Response::Ok {
                         output: serde_json::json!({
                             "session": result.session,
@@ -517,37 +524,30 @@ impl AppState {
         }
     }
 
-    /// Capture the current layout and save it as a named part in parts.md.
+    /// Capture the current layout and add it to the parts catalog as `name`.
+    /// Fails rather than overwrite a part that already has that name.
     pub fn layout_capture_save(&self, session: &str, name: &str) -> Response {
         let builder = TmuxCommandBuilder::new();
         let list_cmd = builder.list_panes(session);
-        let runner = ShellRunner;
-        match runner.run(&list_cmd) {
+        match self.run_tmux(&list_cmd) {
             Ok(output) => {
                 use muxux_core::layout::capture::capture_session;
-                let now_ms = std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .map(|d| d.as_millis() as u64)
-                    .unwrap_or(0);
-                match capture_session(session, &output, None, now_ms) {
-                    Ok(result) => {
-                        // Write to parts.md
-                        let parts_path = dirs_next_home()
-                            .map(|h| h.join(".config/skd/skd-library/parts.md"))
-                            .unwrap_or_default();
-                        let entry = format!("\n## {}\n{}\n", name, result.layout_expr);
-                        match append_to_file(&parts_path, &entry) {
-                            Ok(_) => Response::Ok {
-                                output: format!(
-                                    "Layout '{}' saved: {}",
-                                    name, result.layout_expr
-                                ),
-                            },
-                            Err(e) => Response::Error {
-                                message: format!("Capture succeeded but save failed: {}", e),
-                            },
-                        }
-                    }
+                match capture_session(session, &output, None, 0, now_ms()) {
+                    Ok(result) => match self.execute(Command::PartsAdd {
+                        name: name.to_string(),
+                        body: result.layout_expr.clone(),
+                        file: None,
+                    }) {
+                        Response::Ok { output } => Response::Ok {
+                            output: format!(
+                                "Layout '{}' saved: {}\n{}",
+                                name, result.layout_expr, output
+                            ),
+                        },
+                        Response::Error { message } => Response::Error {
+                            message: format!("Capture succeeded but save failed: {}", message),
+                        },
+                    },
                     Err(e) => Response::Error { message: e },
                 }
             }
@@ -563,38 +563,66 @@ impl AppState {
         self.execute(Command::PartsList)
     }
 
-    /// Place a part from the catalog into the target pane.
+    /// A part's layout expression and box diagram, `width` x `height` characters.
+    pub fn parts_show(&self, name: &str, width: Option<u16>, height: Option<u16>) -> Response {
+        self.execute(Command::PartsShow {
+            name: name.to_string(),
+            width,
+            height,
+            ascii: false,
+        })
+    }
+
+    /// Add a part to the catalog; `body` is a layout expression or `role:` lines.
+    pub fn parts_add(&self, name: &str, body: &str) -> Response {
+        self.execute(Command::PartsAdd {
+            name: name.to_string(),
+            body: body.to_string(),
+            file: None,
+        })
+    }
+
+    /// Replace a part's definition, keeping its notes.
+    pub fn parts_update(&self, name: &str, body: &str) -> Response {
+        self.execute(Command::PartsUpdate {
+            name: name.to_string(),
+            body: body.to_string(),
+            file: None,
+        })
+    }
+
+    /// Delete a part that no other part uses.
+    pub fn parts_remove(&self, name: &str) -> Response {
+        self.execute(Command::PartsRemove {
+            name: name.to_string(),
+            file: None,
+        })
+    }
+
+    /// Rename a part and every layout that uses it.
+    pub fn parts_rename(&self, name: &str, to: &str) -> Response {
+        self.execute(Command::PartsRename {
+            name: name.to_string(),
+            to: to.to_string(),
+            file: None,
+        })
+    }
+
+    /// Place a part from the catalog into the window holding `pane`.
     ///
-    /// Looks up the part in the registry, expands it recursively, and
-    /// applies the resulting layout via realize_layout().
+    /// Reconciles the live window toward the expanded part, so panes that
+    /// already match are kept and re-applying the part is a no-op.
     pub fn parts_place(&self, pane: &str, part_name: &str) -> Response {
-        let registry = muxux_core::data::parts::PartRegistry::from_default_path();
-        match registry.expand(part_name) {
-            Some(layout) => {
-                let commands = realize_layout(pane, &layout);
-                let runner = ShellRunner;
-                for cmd in &commands {
-                    match runner.run(cmd) {
-                        Ok(_) => eprintln!("[muxux] part cmd: {}", cmd),
-                        Err(e) => {
-                            return Response::Error {
-                                message: format!("Part '{}' failed: {}", part_name, e),
-                            };
-                        }
-                    }
-                }
-                Response::Ok {
-                    output: format!(
-                        "Part '{}' applied ({} splits)",
-                        part_name,
-                        commands.len()
-                    ),
-                }
-            }
-            None => Response::Error {
+        let registry = self.sys.lock().unwrap().catalog();
+        if registry.get(part_name).is_none() {
+            return Response::Error {
                 message: format!("Part '{}' not found in catalog", part_name),
-            },
+            };
         }
+        self.execute(Command::LayoutApply {
+            session: pane.to_string(),
+            layout: part_name.to_string(),
+        })
     }
 
     // -------------------------------------------------------------------
@@ -605,21 +633,21 @@ impl AppState {
         let layout = match template {
             "2-col" => LayoutNode::Row {
                 children: vec![
-                    LayoutEntry { node: LayoutNode::Pane { agent: "".into() }, percent: Some(50) },
-                    LayoutEntry { node: LayoutNode::Pane { agent: "".into() }, percent: Some(50) },
+                    LayoutEntry { node: LayoutNode::pane(""), percent: Some(50), size: SizeSpec::default() },
+                    LayoutEntry { node: LayoutNode::pane(""), percent: Some(50), size: SizeSpec::default() },
                 ],
             },
             "3-col" => LayoutNode::Row {
                 children: vec![
-                    LayoutEntry { node: LayoutNode::Pane { agent: "".into() }, percent: Some(33) },
-                    LayoutEntry { node: LayoutNode::Pane { agent: "".into() }, percent: Some(34) },
-                    LayoutEntry { node: LayoutNode::Pane { agent: "".into() }, percent: Some(33) },
+                    LayoutEntry { node: LayoutNode::pane(""), percent: Some(33), size: SizeSpec::default() },
+                    LayoutEntry { node: LayoutNode::pane(""), percent: Some(34), size: SizeSpec::default() },
+                    LayoutEntry { node: LayoutNode::pane(""), percent: Some(33), size: SizeSpec::default() },
                 ],
             },
             "2-row" => LayoutNode::Col {
                 children: vec![
-                    LayoutEntry { node: LayoutNode::Pane { agent: "".into() }, percent: Some(50) },
-                    LayoutEntry { node: LayoutNode::Pane { agent: "".into() }, percent: Some(50) },
+                    LayoutEntry { node: LayoutNode::pane(""), percent: Some(50), size: SizeSpec::default() },
+                    LayoutEntry { node: LayoutNode::pane(""), percent: Some(50), size: SizeSpec::default() },
                 ],
             },
             "dashboard" => LayoutNode::Row {
@@ -627,20 +655,22 @@ impl AppState {
                     LayoutEntry {
                         node: LayoutNode::Col {
                             children: vec![
-                                LayoutEntry { node: LayoutNode::Pane { agent: "".into() }, percent: Some(50) },
-                                LayoutEntry { node: LayoutNode::Pane { agent: "".into() }, percent: Some(50) },
+                                LayoutEntry { node: LayoutNode::pane(""), percent: Some(50), size: SizeSpec::default() },
+                                LayoutEntry { node: LayoutNode::pane(""), percent: Some(50), size: SizeSpec::default() },
                             ],
                         },
                         percent: Some(50),
+                        size: SizeSpec::default(),
                     },
                     LayoutEntry {
                         node: LayoutNode::Col {
                             children: vec![
-                                LayoutEntry { node: LayoutNode::Pane { agent: "".into() }, percent: Some(50) },
-                                LayoutEntry { node: LayoutNode::Pane { agent: "".into() }, percent: Some(50) },
+                                LayoutEntry { node: LayoutNode::pane(""), percent: Some(50), size: SizeSpec::default() },
+                                LayoutEntry { node: LayoutNode::pane(""), percent: Some(50), size: SizeSpec::default() },
                             ],
                         },
                         percent: Some(50),
+                        size: SizeSpec::default(),
                     },
                 ],
             },
@@ -649,21 +679,15 @@ impl AppState {
             },
         };
 
-        let commands = realize_layout(pane, &layout);
-        let runner = ShellRunner;
-        for cmd in &commands {
-            match runner.run(cmd) {
-                Ok(_) => eprintln!("[muxux] template cmd: {}", cmd),
-                Err(e) => {
-                    return Response::Error {
-                        message: format!("Template failed: {}", e),
-                    };
-                }
-            }
-        }
-        Response::Ok {
-            output: format!("Template '{}' applied ({} splits)", template, commands.len()),
-        }
+        // Queue the tree itself: its leaves are unnamed panes, which a
+        // round trip through an expression would not keep.
+        let job = Job::Apply {
+            session: pane.to_string(),
+            layout: template.to_string(),
+            desired: layout,
+            catalog: self.sys.lock().unwrap().catalog(),
+        };
+        job.run(self.runner.lock().unwrap().as_ref())
     }
 }
 
@@ -800,8 +824,15 @@ pub fn run() {
         }
     };
 
-    let project_root = std::env::var("MUX_PROJECT_ROOT").unwrap_or_default();
-    let state = AppState::new(project_root);
+    let settings = MuxSettings::load(&config_dir).unwrap_or_else(|e| {
+        eprintln!("[muxux] {}; using default settings", e);
+        MuxSettings::default()
+    });
+    let state = AppState::with_settings(MuxSettings {
+        project_root: std::env::var("MUX_PROJECT_ROOT").unwrap_or_default(),
+        config_dir: config_dir.to_string_lossy().into(),
+        ..settings
+    });
     let overlay_state = OverlayState::new();
     let overlay_args = OverlayArgs::from_env();
 
@@ -823,6 +854,7 @@ pub fn run() {
             ipc::mux_layout_merge,
             ipc::mux_layout_place,
             ipc::mux_layout_capture,
+            ipc::mux_layout_restore,
             ipc::mux_layout_session,
             // Layout (direct tmux — Phase 1)
             ipc::mux_layout_resize,
@@ -843,6 +875,11 @@ pub fn run() {
             // Parts catalog (Phase 4)
             ipc::mux_parts_list,
             ipc::mux_parts_place,
+            ipc::mux_parts_show,
+            ipc::mux_parts_add,
+            ipc::mux_parts_update,
+            ipc::mux_parts_rm,
+            ipc::mux_parts_mv,
             // Overlay
             ipc::mux_show_overlay,
             ipc::mux_hide_overlay,
@@ -1028,7 +1065,12 @@ mod tests {
 
     #[test]
     fn view_returns_name() {
-        let state = test_state();
+        let runner = muxux_core::infrastructure::runner::MockRunner::with_responses(vec![
+            Ok("main\n".into()),
+            Ok("0:zsh:1:b25d,80x24,0,0,0\n".into()),
+            Ok("%0\t0\t80\t24\t0\t0\t1\t\tzsh\t\n".into()),
+        ]);
+        let state = AppState::with_runner("/tmp".into(), Box::new(runner));
         let r = state.view("main".into());
         assert!(is_ok(&r));
         assert!(output(&r).contains("main"));
@@ -1060,25 +1102,59 @@ mod tests {
 
     #[test]
     fn layout_merge() {
-        let state = test_state();
-        let r = state.layout_merge("main".into());
+        let runner = muxux_core::infrastructure::runner::MockRunner::with_responses(vec![Ok(
+            "%0\t0\t40\t24\t0\t0\t0\n%1\t1\t39\t24\t0\t41\t1\n".into(),
+        )]);
+        let state = AppState::with_runner("/tmp".into(), Box::new(runner));
+        let r = state.layout_merge("main".into(), None, true);
+        assert!(is_ok(&r));
+        assert!(output(&r).contains("into pane %1"));
+        assert!(state.pending_actions().is_empty());
+    }
+
+    #[test]
+    fn template_apply_keeps_unnamed_panes() {
+        let two_panes = "%0\t0\t40\t24\t0\t0\t1\t\tzsh\t\n%1\t1\t39\t24\t0\t41\t0\t\tzsh\t\n";
+        let runner = muxux_core::infrastructure::runner::MockRunner::with_responses(vec![
+            Ok(two_panes.into()),
+            Ok(two_panes.into()),
+        ]);
+        let state = AppState::with_runner("/tmp".into(), Box::new(runner));
+        let r = state.template_apply("main", "2-col");
         assert!(is_ok(&r));
-        assert!(output(&r).contains("Merge queued"));
+        assert_eq!(output(&r), "Session 'main' already matches 2-col");
     }
 
     #[test]
     fn layout_place() {
-        let state = test_state();
+        let runner = muxux_core::infrastructure::runner::MockRunner::with_responses(vec![Ok(
+            "%3\t0\t80\t24\t0\t0\t1\t\tzsh\t\n".into(),
+        )]);
+        let state = AppState::with_runner("/tmp".into(), Box::new(runner));
         let r = state.layout_place("%3".into(), "w1".into());
         assert!(is_ok(&r));
     }
 
+    #[test]
+    fn layout_place_refuses_busy_pane() {
+        let runner = muxux_core::infrastructure::runner::MockRunner::with_responses(vec![Ok(
+            "%3\t0\t80\t24\t0\t0\t1\tpm\tclaude\t\n".into(),
+        )]);
+        let state = AppState::with_runner("/tmp".into(), Box::new(runner));
+        let r = state.layout_place("%3".into(), "w1".into());
+        assert_eq!(output(&r), "cannot place w1: %3 runs pm");
+    }
+
     #[test]
     fn layout_capture() {
-        let state = test_state();
+        let runner = muxux_core::infrastructure::runner::MockRunner::with_responses(vec![
+            Ok("0:main:1:b25d,80x24,0,0,0\n".into()),
+            Ok("%0\t0\t80\t24\t0\t0\t1\tpm\tclaude\t\n".into()),
+        ]);
+        let state = AppState::with_runner("/tmp".into(), Box::new(runner));
         let r = state.layout_capture("main".into());
         assert!(is_ok(&r));
-        assert!(output(&r).contains("Capture queued"));
+        assert!(output(&r).contains("\"windows\""));
     }
 
     #[test]